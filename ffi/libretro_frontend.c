@@ -0,0 +1,259 @@
+/*
+ * Minimal headless libretro frontend used to check the rust8 core without RetroArch.
+ *
+ *   libretro_frontend <core library> <rom> [frames]
+ *
+ * Loads the core with dlopen, runs the ROM for the given number of frames, prints the screen
+ * as ASCII art and checks that retro_serialize/retro_unserialize reproduce the same frames.
+ */
+#include <dlfcn.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define RETRO_ENVIRONMENT_SET_PIXEL_FORMAT 10
+#define RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS 11
+#define RETRO_ENVIRONMENT_GET_VARIABLE 15
+#define RETRO_ENVIRONMENT_SET_VARIABLES 16
+#define RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE 17
+#define RETRO_PIXEL_FORMAT_XRGB8888 1
+
+struct retro_system_info {
+    const char *library_name;
+    const char *library_version;
+    const char *valid_extensions;
+    bool need_fullpath;
+    bool block_extract;
+};
+
+struct retro_game_geometry {
+    unsigned base_width;
+    unsigned base_height;
+    unsigned max_width;
+    unsigned max_height;
+    float aspect_ratio;
+};
+
+struct retro_system_timing {
+    double fps;
+    double sample_rate;
+};
+
+struct retro_system_av_info {
+    struct retro_game_geometry geometry;
+    struct retro_system_timing timing;
+};
+
+struct retro_game_info {
+    const char *path;
+    const void *data;
+    size_t size;
+    const char *meta;
+};
+
+struct retro_variable {
+    const char *key;
+    const char *value;
+};
+
+typedef bool (*retro_environment_t)(unsigned cmd, void *data);
+typedef void (*retro_video_refresh_t)(const void *data, unsigned width, unsigned height, size_t pitch);
+typedef size_t (*retro_audio_sample_batch_t)(const int16_t *data, size_t frames);
+typedef void (*retro_input_poll_t)(void);
+typedef int16_t (*retro_input_state_t)(unsigned port, unsigned device, unsigned index, unsigned id);
+
+static uint32_t frame[64 * 32];
+static unsigned frame_width, frame_height;
+static size_t audio_frames;
+static int option_count;
+
+static bool environment(unsigned cmd, void *data) {
+    switch (cmd) {
+    case RETRO_ENVIRONMENT_SET_PIXEL_FORMAT:
+        return *(unsigned *) data == RETRO_PIXEL_FORMAT_XRGB8888;
+    case RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS:
+        return true;
+    case RETRO_ENVIRONMENT_SET_VARIABLES: {
+        const struct retro_variable *variable = data;
+        for (option_count = 0; variable[option_count].key; option_count++) {
+            printf("option %s: %s\n", variable[option_count].key, variable[option_count].value);
+        }
+        return true;
+    }
+    case RETRO_ENVIRONMENT_GET_VARIABLE: {
+        struct retro_variable *variable = data;
+        if (strcmp(variable->key, "rust8_instructions_per_frame") == 0) {
+            variable->value = "15";
+            return true;
+        }
+        return false;
+    }
+    case RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE:
+        *(bool *) data = false;
+        return true;
+    default:
+        return false;
+    }
+}
+
+static void video_refresh(const void *data, unsigned width, unsigned height, size_t pitch) {
+    frame_width = width;
+    frame_height = height;
+    for (unsigned y = 0; y < height && y < 32; y++) {
+        memcpy(&frame[y * 64], (const uint8_t *) data + y * pitch, (width < 64 ? width : 64) * sizeof(uint32_t));
+    }
+}
+
+static size_t audio_sample_batch(const int16_t *data, size_t frames) {
+    (void) data;
+    audio_frames += frames;
+    return frames;
+}
+
+static void input_poll(void) {
+}
+
+static int16_t input_state(unsigned port, unsigned device, unsigned index, unsigned id) {
+    (void) port;
+    (void) device;
+    (void) index;
+    (void) id;
+    return 0;
+}
+
+static void *symbol(void *core, const char *name) {
+    void *function = dlsym(core, name);
+    if (!function) {
+        fprintf(stderr, "missing symbol %s\n", name);
+        exit(1);
+    }
+    return function;
+}
+
+static uint8_t *read_file(const char *path, size_t *size) {
+    FILE *file = fopen(path, "rb");
+    if (!file) {
+        return NULL;
+    }
+    fseek(file, 0, SEEK_END);
+    *size = (size_t) ftell(file);
+    fseek(file, 0, SEEK_SET);
+    uint8_t *data = malloc(*size);
+    if (fread(data, 1, *size, file) != *size) {
+        free(data);
+        data = NULL;
+    }
+    fclose(file);
+    return data;
+}
+
+int main(int argc, char **argv) {
+    if (argc < 3) {
+        fprintf(stderr, "usage: %s <core library> <rom> [frames]\n", argv[0]);
+        return 2;
+    }
+    int frames = argc > 3 ? atoi(argv[3]) : 120;
+
+    void *core = dlopen(argv[1], RTLD_NOW);
+    if (!core) {
+        fprintf(stderr, "%s\n", dlerror());
+        return 1;
+    }
+
+    unsigned (*retro_api_version)(void) = symbol(core, "retro_api_version");
+    void (*retro_set_environment)(retro_environment_t) = symbol(core, "retro_set_environment");
+    void (*retro_set_video_refresh)(retro_video_refresh_t) = symbol(core, "retro_set_video_refresh");
+    void (*retro_set_audio_sample_batch)(retro_audio_sample_batch_t) = symbol(core, "retro_set_audio_sample_batch");
+    void (*retro_set_input_poll)(retro_input_poll_t) = symbol(core, "retro_set_input_poll");
+    void (*retro_set_input_state)(retro_input_state_t) = symbol(core, "retro_set_input_state");
+    void (*retro_init)(void) = symbol(core, "retro_init");
+    void (*retro_deinit)(void) = symbol(core, "retro_deinit");
+    void (*retro_get_system_info)(struct retro_system_info *) = symbol(core, "retro_get_system_info");
+    void (*retro_get_system_av_info)(struct retro_system_av_info *) = symbol(core, "retro_get_system_av_info");
+    bool (*retro_load_game)(const struct retro_game_info *) = symbol(core, "retro_load_game");
+    void (*retro_unload_game)(void) = symbol(core, "retro_unload_game");
+    void (*retro_run)(void) = symbol(core, "retro_run");
+    size_t (*retro_serialize_size)(void) = symbol(core, "retro_serialize_size");
+    bool (*retro_serialize)(void *, size_t) = symbol(core, "retro_serialize");
+    bool (*retro_unserialize)(const void *, size_t) = symbol(core, "retro_unserialize");
+
+    if (retro_api_version() != 1) {
+        fprintf(stderr, "unexpected API version\n");
+        return 1;
+    }
+
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+
+    struct retro_system_info system_info;
+    retro_get_system_info(&system_info);
+    printf("%s %s (%s), %d options\n", system_info.library_name, system_info.library_version,
+           system_info.valid_extensions, option_count);
+
+    struct retro_game_info game = { argv[2], NULL, 0, NULL };
+    uint8_t *rom = read_file(argv[2], &game.size);
+    if (!rom) {
+        fprintf(stderr, "could not read %s\n", argv[2]);
+        return 1;
+    }
+    game.data = rom;
+    if (!retro_load_game(&game)) {
+        fprintf(stderr, "core refused the ROM\n");
+        return 1;
+    }
+
+    struct retro_system_av_info av_info;
+    retro_get_system_av_info(&av_info);
+    printf("%ux%u @ %.0f fps, %.0f Hz audio\n", av_info.geometry.base_width, av_info.geometry.base_height,
+           av_info.timing.fps, av_info.timing.sample_rate);
+
+    for (int i = 0; i < frames / 2; i++) {
+        retro_run();
+    }
+
+    size_t state_size = retro_serialize_size();
+    void *state = malloc(state_size);
+    if (!retro_serialize(state, state_size)) {
+        fprintf(stderr, "retro_serialize failed\n");
+        return 1;
+    }
+    for (int i = frames / 2; i < frames; i++) {
+        retro_run();
+    }
+    uint32_t expected[64 * 32];
+    memcpy(expected, frame, sizeof(frame));
+
+    if (!retro_unserialize(state, state_size)) {
+        fprintf(stderr, "retro_unserialize failed\n");
+        return 1;
+    }
+    for (int i = frames / 2; i < frames; i++) {
+        retro_run();
+    }
+    if (memcmp(expected, frame, sizeof(frame)) != 0) {
+        fprintf(stderr, "frames differ after retro_unserialize\n");
+        return 1;
+    }
+
+    for (unsigned y = 0; y < frame_height; y++) {
+        for (unsigned x = 0; x < frame_width; x++) {
+            putchar((frame[y * 64 + x] & 0xFFFFFF) ? '#' : '.');
+        }
+        putchar('\n');
+    }
+    printf("ran %d frames, %zu audio frames, %zu byte save state, serialize round trip ok\n",
+           frames + frames / 2, audio_frames, state_size);
+
+    retro_unload_game();
+    retro_deinit();
+    dlclose(core);
+    free(state);
+    free(rom);
+    return 0;
+}
@@ -0,0 +1,154 @@
+#ifndef RUST8_H
+#define RUST8_H
+
+/* Generated by cbindgen from src/ffi.rs: cbindgen --config cbindgen.toml --output ffi/rust8.h */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define RUST8_OK 0
+
+#define RUST8_ERROR -1
+
+typedef struct Rust8 Rust8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct Rust8 *rust8_create(void);
+
+struct Rust8 *rust8_create_with_seed(uint64_t seed);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet. It
+ * must not be used after this call.
+ */
+void rust8_destroy(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `data` must be null or point to `length` readable
+ * bytes, which are copied before the call returns.
+ */
+int rust8_load_rom(struct Rust8 *handle, const uint8_t *data, size_t length);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_step(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_run_frame(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_run_frames(struct Rust8 *handle, uint32_t frames);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_advance(struct Rust8 *handle, uint64_t microseconds);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_reset(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_is_halted(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+ * bytes that stay valid and are not accessed elsewhere during the call.
+ */
+int rust8_read_memory(struct Rust8 *handle, uint16_t address, uint8_t *buffer, size_t length);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_screen_width(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_screen_height(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+ * bytes that stay valid and are not accessed elsewhere during the call.
+ */
+int rust8_get_framebuffer(struct Rust8 *handle, uint8_t *buffer, size_t length);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_press_key(struct Rust8 *handle, uint8_t key);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_release_key(struct Rust8 *handle, uint8_t key);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_set_sample_rate(struct Rust8 *handle, uint32_t sample_rate);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_audio_samples_per_frame(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+ * `int16_t` samples that stay valid and are not accessed elsewhere during the call.
+ */
+int rust8_get_audio_samples(struct Rust8 *handle, int16_t *buffer, size_t length);
+
+/**
+ * # Safety
+ * `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+ */
+int rust8_save_state_size(struct Rust8 *handle);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+ * bytes that stay valid and are not accessed elsewhere during the call.
+ */
+int rust8_save_state(struct Rust8 *handle, uint8_t *buffer, size_t length);
+
+/**
+ * # Safety
+ * `handle` must be null or a live handle. `data` must be null or point to `length` readable
+ * bytes, which are only read during the call.
+ */
+int rust8_load_state(struct Rust8 *handle, const uint8_t *data, size_t length);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RUST8_H */
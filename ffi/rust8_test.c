@@ -0,0 +1,73 @@
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "rust8.h"
+
+static const uint8_t ROM[] = {
+    0x60, 0x00, /* LD V0, 0x00     */
+    0xF0, 0x29, /* LDSPR V0        */
+    0xD0, 0x05, /* DRW V0, V0, 5   */
+    0x61, 0x10, /* LD V1, 0x10     */
+    0xF1, 0x18, /* SSTR V1         */
+    0x12, 0x0A, /* JMP 0x20A       */
+};
+
+int main(void) {
+    Rust8 *emulator = rust8_create_with_seed(1);
+    assert(emulator != NULL);
+    int result = rust8_load_rom(emulator, ROM, sizeof(ROM));
+    assert(result == RUST8_OK);
+
+    for (int frame = 0; frame < 6; frame++) {
+        result = rust8_run_frame(emulator);
+        assert(result == RUST8_OK);
+    }
+
+    int width = rust8_screen_width(emulator);
+    int height = rust8_screen_height(emulator);
+    assert(width == 64 && height == 32);
+
+    uint8_t *pixels = malloc((size_t) (width * height));
+    result = rust8_get_framebuffer(emulator, pixels, (size_t) (width * height));
+    assert(result == width * height);
+    /* top row of the "0" glyph is 0xF0 */
+    assert(pixels[0] == 1 && pixels[3] == 1 && pixels[4] == 0);
+
+    int samples = rust8_audio_samples_per_frame(emulator);
+    int16_t *audio = malloc(sizeof(int16_t) * (size_t) samples);
+    result = rust8_get_audio_samples(emulator, audio, (size_t) samples);
+    assert(result == samples);
+    assert(audio[0] != 0);
+
+    int state_size = rust8_save_state_size(emulator);
+    uint8_t *state = malloc((size_t) state_size);
+    result = rust8_save_state(emulator, state, (size_t) state_size);
+    assert(result == state_size);
+
+    Rust8 *restored = rust8_create();
+    result = rust8_load_state(restored, state, (size_t) state_size);
+    assert(result == RUST8_OK);
+    result = rust8_load_state(restored, state, 3);
+    assert(result == RUST8_ERROR);
+    result = rust8_press_key(restored, 0x10);
+    assert(result == RUST8_ERROR);
+    result = rust8_press_key(restored, 0xA);
+    assert(result == RUST8_OK);
+    /* 25 ms is one 60 Hz frame, the remaining 8.3 ms complete a second one */
+    result = rust8_advance(restored, 25000);
+    assert(result == 1);
+    result = rust8_advance(restored, 9000);
+    assert(result == 1);
+
+    /* only read by assert, which -DNDEBUG removes */
+    (void) result;
+
+    rust8_destroy(restored);
+    rust8_destroy(emulator);
+    free(state);
+    free(audio);
+    free(pixels);
+    printf("rust8 C API test passed\n");
+    return 0;
+}
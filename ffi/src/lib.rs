@@ -0,0 +1,5 @@
+extern crate rust8_core;
+
+pub use rust8_core::ffi::*;
+#[cfg(feature = "libretro")]
+pub use rust8_core::libretro::*;
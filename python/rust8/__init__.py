@@ -0,0 +1,160 @@
+"""ctypes bindings for the rust8 C API (see ffi/rust8.h)."""
+
+import ctypes
+import ctypes.util
+import os
+import sys
+
+from .env import Chip8Env
+
+__all__ = ["Chip8", "Chip8Env", "Rust8Error", "load_library"]
+
+_ERROR = -1
+_library = None
+
+
+class Rust8Error(RuntimeError):
+    pass
+
+
+def _library_names():
+    if sys.platform == "darwin":
+        return ["librust8.dylib"]
+    if sys.platform == "win32":
+        return ["rust8.dll"]
+    return ["librust8.so"]
+
+
+def _candidate_paths():
+    explicit = os.environ.get("RUST8_LIBRARY")
+    if explicit:
+        yield explicit
+    root = os.path.abspath(os.path.join(os.path.dirname(__file__), "..", ".."))
+    for profile in ("release", "debug"):
+        for name in _library_names():
+            yield os.path.join(root, "target", profile, name)
+    found = ctypes.util.find_library("rust8")
+    if found:
+        yield found
+
+
+def load_library(path=None):
+    global _library
+    if _library is not None and path is None:
+        return _library
+    paths = [path] if path else [p for p in _candidate_paths() if os.path.exists(p) or not os.path.isabs(p)]
+    for candidate in paths:
+        try:
+            library = ctypes.CDLL(candidate)
+        except OSError:
+            continue
+        _declare(library)
+        _library = library
+        return library
+    raise Rust8Error("Could not find the rust8 shared library, build it with 'cargo build --release -p rust8-ffi' "
+                     "or point RUST8_LIBRARY at it")
+
+
+def _declare(library):
+    handle = ctypes.c_void_p
+    size = ctypes.c_size_t
+    signatures = {
+        "rust8_create": (handle, []),
+        "rust8_create_with_seed": (handle, [ctypes.c_uint64]),
+        "rust8_destroy": (None, [handle]),
+        "rust8_load_rom": (ctypes.c_int, [handle, ctypes.c_char_p, size]),
+        "rust8_step": (ctypes.c_int, [handle]),
+        "rust8_run_frame": (ctypes.c_int, [handle]),
+        "rust8_run_frames": (ctypes.c_int, [handle, ctypes.c_uint32]),
+        "rust8_reset": (ctypes.c_int, [handle]),
+        "rust8_is_halted": (ctypes.c_int, [handle]),
+        "rust8_read_memory": (ctypes.c_int, [handle, ctypes.c_uint16, ctypes.c_void_p, size]),
+        "rust8_screen_width": (ctypes.c_int, [handle]),
+        "rust8_screen_height": (ctypes.c_int, [handle]),
+        "rust8_get_framebuffer": (ctypes.c_int, [handle, ctypes.c_void_p, size]),
+        "rust8_press_key": (ctypes.c_int, [handle, ctypes.c_uint8]),
+        "rust8_release_key": (ctypes.c_int, [handle, ctypes.c_uint8]),
+        "rust8_save_state_size": (ctypes.c_int, [handle]),
+        "rust8_save_state": (ctypes.c_int, [handle, ctypes.c_void_p, size]),
+        "rust8_load_state": (ctypes.c_int, [handle, ctypes.c_char_p, size]),
+    }
+    for name, (restype, argtypes) in signatures.items():
+        function = getattr(library, name)
+        function.restype = restype
+        function.argtypes = argtypes
+
+
+def _check(result, action):
+    if result == _ERROR:
+        raise Rust8Error("rust8: could not " + action)
+    return result
+
+
+class Chip8(object):
+    """A headless CHIP-8 machine."""
+
+    def __init__(self, seed=None, library=None):
+        self._lib = load_library(library)
+        if seed is None:
+            self._handle = self._lib.rust8_create()
+        else:
+            self._handle = self._lib.rust8_create_with_seed(seed)
+        if not self._handle:
+            raise Rust8Error("rust8: could not create emulator")
+        self.width = self._lib.rust8_screen_width(self._handle)
+        self.height = self._lib.rust8_screen_height(self._handle)
+        self._framebuffer = bytearray(self.width * self.height)
+        self._framebuffer_pointer = (ctypes.c_uint8 * len(self._framebuffer)).from_buffer(self._framebuffer)
+
+    def close(self):
+        if self._handle:
+            self._lib.rust8_destroy(self._handle)
+            self._handle = None
+
+    def __del__(self):
+        self.close()
+
+    def load_rom(self, data):
+        data = bytes(data)
+        _check(self._lib.rust8_load_rom(self._handle, data, len(data)), "load ROM")
+
+    def reset(self):
+        _check(self._lib.rust8_reset(self._handle), "reset")
+
+    def step(self):
+        _check(self._lib.rust8_step(self._handle), "step")
+
+    def run_frames(self, frames=1):
+        """Runs up to `frames` frames, stopping early on halt. Returns the number of frames run."""
+        return _check(self._lib.rust8_run_frames(self._handle, frames), "run frames")
+
+    @property
+    def halted(self):
+        return bool(_check(self._lib.rust8_is_halted(self._handle), "query halt state"))
+
+    def read_memory(self, address, length=1):
+        buffer = (ctypes.c_uint8 * length)()
+        read = _check(self._lib.rust8_read_memory(self._handle, address, buffer, length), "read memory")
+        return bytes(bytearray(buffer[:read]))
+
+    def framebuffer(self):
+        """Returns the screen as a memoryview of height x width bytes (0 or 1), shared between calls."""
+        _check(self._lib.rust8_get_framebuffer(self._handle, self._framebuffer_pointer, len(self._framebuffer)),
+               "read framebuffer")
+        return memoryview(self._framebuffer).cast("B", (self.height, self.width))
+
+    def press_key(self, key):
+        _check(self._lib.rust8_press_key(self._handle, key), "press key")
+
+    def release_key(self, key):
+        _check(self._lib.rust8_release_key(self._handle, key), "release key")
+
+    def save_state(self):
+        size = _check(self._lib.rust8_save_state_size(self._handle), "size state")
+        buffer = (ctypes.c_uint8 * size)()
+        _check(self._lib.rust8_save_state(self._handle, buffer, size), "save state")
+        return bytes(bytearray(buffer))
+
+    def load_state(self, state):
+        state = bytes(state)
+        _check(self._lib.rust8_load_state(self._handle, state, len(state)), "load state")
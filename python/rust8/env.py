@@ -0,0 +1,110 @@
+"""Gym-style reinforcement learning environment around a headless CHIP-8 machine."""
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+NO_KEY = None
+
+
+def bcd_score(digits):
+    score = 0
+    for digit in bytearray(digits):
+        score = score * 10 + digit
+    return score
+
+
+def uint_score(data):
+    score = 0
+    for byte in bytearray(data):
+        score = (score << 8) + byte
+    return score
+
+
+class Chip8Env(object):
+    """Runs a ROM with `reset()` / `step(action)` in the style of gym.Env.
+
+    `actions` lists the hex keys an agent may hold for one step, `None` meaning no key.
+    The reward is the change of the score stored at `score_address` (`score_length` bytes,
+    decoded by `score_format`: "bcd" for digits written by FX33 or "uint" for big-endian bytes).
+    An episode ends when the ROM halts, when memory at `done_address` equals `done_value`,
+    or when the optional `done_when(chip8)` callable returns true.
+    Observations are uint8 numpy arrays of `observation_shape` when numpy is installed, otherwise
+    the framebuffer as bytes, one per pixel row by row.
+    """
+
+    def __init__(self, rom, actions=None, score_address=None, score_length=3, score_format="bcd",
+                 done_address=None, done_value=0, done_when=None, frame_skip=4, max_frames=None,
+                 seed=0, library=None):
+        from . import Chip8
+        if isinstance(rom, str):
+            with open(rom, "rb") as rom_file:
+                rom = rom_file.read()
+        if score_format not in ("bcd", "uint"):
+            raise ValueError("score_format must be 'bcd' or 'uint'")
+        self.chip8 = Chip8(seed=seed, library=library)
+        self.chip8.load_rom(rom)
+        self.actions = list(actions) if actions is not None else [NO_KEY] + list(range(16))
+        self.score_address = score_address
+        self.score_length = score_length
+        self.score_decoder = bcd_score if score_format == "bcd" else uint_score
+        self.done_address = done_address
+        self.done_value = done_value
+        self.done_when = done_when
+        self.frame_skip = frame_skip
+        self.max_frames = max_frames
+        self.observation_shape = (self.chip8.height, self.chip8.width)
+        self._held_key = NO_KEY
+        self._score = 0
+        self._frames = 0
+
+    @property
+    def action_count(self):
+        return len(self.actions)
+
+    def reset(self):
+        self.chip8.reset()
+        self._held_key = NO_KEY
+        self._frames = 0
+        self._score = self._read_score()
+        return self._observation()
+
+    def step(self, action):
+        key = self.actions[action]
+        if key != self._held_key:
+            if self._held_key is not NO_KEY:
+                self.chip8.release_key(self._held_key)
+            if key is not NO_KEY:
+                self.chip8.press_key(key)
+            self._held_key = key
+        frames = self.chip8.run_frames(self.frame_skip)
+        self._frames += frames
+        score = self._read_score()
+        reward = score - self._score
+        self._score = score
+        info = {"frames": self._frames, "score": score, "halted": self.chip8.halted}
+        return self._observation(), reward, self._is_done(), info
+
+    def close(self):
+        self.chip8.close()
+
+    def _read_score(self):
+        if self.score_address is None:
+            return 0
+        return self.score_decoder(self.chip8.read_memory(self.score_address, self.score_length))
+
+    def _is_done(self):
+        if self.chip8.halted:
+            return True
+        if self.max_frames is not None and self._frames >= self.max_frames:
+            return True
+        if self.done_address is not None and bytearray(self.chip8.read_memory(self.done_address))[0] == self.done_value:
+            return True
+        return bool(self.done_when and self.done_when(self.chip8))
+
+    def _observation(self):
+        framebuffer = self.chip8.framebuffer()
+        if numpy is not None:
+            return numpy.frombuffer(framebuffer, dtype=numpy.uint8).reshape(self.observation_shape).copy()
+        return framebuffer.tobytes()
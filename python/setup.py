@@ -0,0 +1,9 @@
+from setuptools import setup
+
+setup(
+    name="rust8",
+    version="0.1.0",
+    description="Python bindings and a Gym-style environment for the rust8 CHIP-8 emulator",
+    packages=["rust8"],
+    extras_require={"numpy": ["numpy"]},
+)
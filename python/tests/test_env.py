@@ -0,0 +1,117 @@
+import os
+import sys
+import unittest
+
+sys.path.insert(0, os.path.join(os.path.dirname(__file__), ".."))
+
+from rust8 import Chip8, Chip8Env
+from rust8 import env as env_module
+
+# Counts V0 up while key 5 is held, writes it as BCD to 0x300 and halts once V0 reaches 3.
+SCORE_ROM = bytes(bytearray([
+    0xA3, 0x00,  # 200: LDI 0x300
+    0x61, 0x05,  # 202: LD V1, 5
+    0xE1, 0xA1,  # 204: SKPNK V1
+    0x70, 0x01,  # 206: ADDI V0, 1
+    0xF0, 0x33,  # 208: BCD V0
+    0x30, 0x03,  # 20A: SE V0, 3
+    0x12, 0x04,  # 20C: JMP 0x204
+    0x12, 0x0E,  # 20E: JMP 0x20E
+]))
+
+# Draws the font sprite of 0 at (0, 0) and halts.
+DRAW_ROM = bytes(bytearray([
+    0x60, 0x00,  # 200: LD V0, 0
+    0xF0, 0x29,  # 202: LD F, V0
+    0xD0, 0x05,  # 204: DRW V0, V0, 5
+    0x12, 0x06,  # 206: JMP 0x206
+]))
+
+
+class Chip8EnvTest(unittest.TestCase):
+
+    def create_env(self):
+        return Chip8Env(SCORE_ROM, actions=[None, 0x5], score_address=0x300, frame_skip=1)
+
+    def test_reward_follows_bcd_score(self):
+        env = self.create_env()
+        env.reset()
+
+        rewards = [env.step(1)[1] for _ in range(5)]
+
+        self.assertEqual(sum(rewards), 1)
+
+    def test_done_on_halt(self):
+        env = self.create_env()
+        env.reset()
+
+        done = False
+        for _ in range(100):
+            _, _, done, info = env.step(1)
+            if done:
+                break
+
+        self.assertTrue(done)
+        self.assertEqual(info["score"], 3)
+
+    def test_reset_restarts_episode(self):
+        env = self.create_env()
+        env.reset()
+        for _ in range(20):
+            env.step(1)
+
+        env.reset()
+        _, reward, done, info = env.step(0)
+
+        self.assertEqual(info["score"], 0)
+        self.assertFalse(done)
+
+    def test_observation_shape(self):
+        env = self.create_env()
+
+        observation = env.reset()
+
+        if env_module.numpy is not None:
+            self.assertEqual(observation.shape, env.observation_shape)
+        else:
+            self.assertEqual(len(observation), 64 * 32)
+
+    def test_observation_bytes_without_numpy(self):
+        numpy = env_module.numpy
+        env_module.numpy = None
+        try:
+            observation = self.create_env().reset()
+        finally:
+            env_module.numpy = numpy
+
+        self.assertEqual(observation, bytes(bytearray(64 * 32)))
+
+    @unittest.skipIf(env_module.numpy is None, "numpy is not installed")
+    def test_observation_numpy_array(self):
+        numpy = env_module.numpy
+        env = Chip8Env(DRAW_ROM)
+        env.reset()
+
+        observation = env.step(0)[0]
+        framebuffer = numpy.asarray(env.chip8.framebuffer())
+
+        self.assertEqual(observation.dtype, numpy.uint8)
+        self.assertEqual(observation.shape, (32, 64))
+        self.assertEqual(list(observation[0, :5]), [1, 1, 1, 1, 0])
+        self.assertTrue(numpy.array_equal(framebuffer, observation))
+
+    def test_save_load_state(self):
+        chip8 = Chip8(seed=3)
+        chip8.load_rom(SCORE_ROM)
+        chip8.press_key(5)
+        chip8.run_frames(5)
+        state = chip8.save_state()
+        chip8.run_frames(20)
+
+        chip8.load_state(state)
+
+        self.assertEqual(chip8.read_memory(0x300, 3), b"\x00\x00\x01")
+
+
+if __name__ == "__main__":
+    unittest.main()
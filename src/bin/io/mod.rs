@@ -12,24 +12,42 @@ use self::glutin_window::GlutinWindow as Window;
 use self::opengl_graphics::{GlGraphics, OpenGL};
 
 use rust8::emulator::Emulator;
+use rust8::emulator::audio::DEFAULT_SAMPLE_RATE;
+use rust8::emulator::keypad::{HexKey, KeyMap, KeyProfiles};
+use rust8::emulator::overlay::{draw_lines, panel_lines, text_pixels, MachineSnapshot, GLYPH_HEIGHT, GLYPH_WIDTH, PANEL_COLUMNS};
+use rust8::emulator::runner::{Command, Event, Frame, Runner, DEFAULT_FRAMES_PER_SECOND};
 
-const UPDATE_LIMIT: f64 = 1.0 / 60.0;
-
+const DISASSEMBLY_LINES: usize = 9;
+const MEMORY_ROWS: usize = 8;
+const DRAW_LINES: usize = 6;
+const PANEL_LINES: usize = 3 + 7 + 1 + DISASSEMBLY_LINES + 1 + MEMORY_ROWS + 2 + DRAW_LINES;
+const PANEL_MARGIN: usize = 8;
+const HOTKEYS: &'static str = "F5 PAUSE  F6 STEP  F7 FRAME";
 
 pub struct App {
     gl: GlGraphics,
     window: Window,
-    emulator: Box<Emulator>,
-    update_time: f64,
+    runner: Runner,
+    frame: Option<Frame>,
+    keymap: KeyMap<Key>,
+    debug: bool,
+    overlay: bool,
+    paused: bool,
+    machine: Option<MachineSnapshot>,
+    cursor: [f64; 2],
+    screen_area: (f64, f64, f64, f64),
+    selected_pixel: Option<(u8, u8)>,
 }
 
 impl App {
-    pub fn new(selected_emulator: Box<Emulator>) -> App {
+    /// With `debug` the window opens larger with the debug overlay shown and F1 toggles it. The
+    /// normal window is too small for the overlay, so F1 does nothing there.
+    pub fn new(selected_emulator: Box<Emulator>, keymap: KeyMap<Key>, debug: bool) -> App {
         let opengl = OpenGL::V3_2;
 
         let window_gl: Window = WindowSettings::new(
             selected_emulator.get_name(),
-            [200, 200]
+            if debug { [800, 460] } else { [200, 200] }
         )
             .opengl(opengl)
             .exit_on_esc(true)
@@ -39,12 +57,22 @@ impl App {
         App {
             gl: GlGraphics::new(opengl),
             window: window_gl,
-            emulator: selected_emulator,
-            update_time: 0.0,
+            runner: Runner::spawn(selected_emulator, DEFAULT_FRAMES_PER_SECOND, DEFAULT_SAMPLE_RATE),
+            frame: None,
+            keymap: keymap,
+            debug: debug,
+            overlay: debug,
+            paused: false,
+            machine: None,
+            cursor: [0.0, 0.0],
+            screen_area: (0.0, 0.0, 0.0, 0.0),
+            selected_pixel: None,
         }
     }
 
     pub fn run(&mut self) {
+        self.runner.send(Command::Inspect(self.overlay));
+        self.runner.send(Command::Resume);
         let mut events = Events::new(EventSettings::new());
         while let Some(event) = events.next(&mut self.window) {
             if let Some(render) = event.render_args() {
@@ -55,12 +83,20 @@ impl App {
                 self.handle_key_press(&key);
             }
 
+            if let Some(cursor) = event.mouse_cursor_args() {
+                self.cursor = cursor;
+            }
+
+            if let Some(Button::Mouse(MouseButton::Left)) = event.press_args() {
+                self.select_pixel();
+            }
+
             if let Some(Button::Keyboard(key)) = event.release_args() {
                 self.handle_key_release(&key);
             }
 
-            if let Some(update) = event.update_args() {
-                self.update(&update);
+            if event.update_args().is_some() {
+                self.update();
             }
         }
     }
@@ -68,19 +104,48 @@ impl App {
     fn render(&mut self, args: &RenderArgs) {
         use self::graphics::*;
 
-        let (screen_width, screen_height) = self.emulator.retrieve_screen_size();
-        let (pixel_width, pixel_height) = ((args.width / screen_width as u32) as u32, (args.height / screen_height as u32) as u32);
-        let center_width: f64 = ((args.width - ((screen_width as u32) * pixel_width)) / 2) as f64;
+        let frame = match self.frame {
+            Some(ref frame) => frame,
+            None => return,
+        };
+        let panel = match self.machine {
+            Some(ref machine) if self.overlay => {
+                let mut lines = vec![String::from(if self.paused { "PAUSED" } else { "RUNNING" }), String::from(HOTKEYS), String::new()];
+                lines.extend(panel_lines(machine, DISASSEMBLY_LINES, MEMORY_ROWS));
+                lines.push(String::new());
+                match self.selected_pixel {
+                    Some((x, y)) => lines.extend(draw_lines(machine, x, y, DRAW_LINES)),
+                    None => lines.push(String::from("CLICK A PIXEL FOR ITS DRAWS")),
+                }
+                lines
+            },
+            _ => Vec::new(),
+        };
+        let scale = ((args.height as usize).saturating_sub(2 * PANEL_MARGIN) / (PANEL_LINES * (GLYPH_HEIGHT + 1))).max(1);
+        let panel_width = if self.overlay { (PANEL_COLUMNS * (GLYPH_WIDTH + 1) * scale + 2 * PANEL_MARGIN) as u32 } else { 0 };
+        let game_width = args.width.saturating_sub(panel_width);
+
+        let (screen_width, screen_height) = (frame.width, frame.height);
+        let (pixel_width, pixel_height) = ((game_width / screen_width as u32) as u32, (args.height / screen_height as u32) as u32);
+        let center_width: f64 = ((game_width - ((screen_width as u32) * pixel_width)) / 2) as f64;
         let center_height: f64 = ((args.height - ((screen_height as u32) * pixel_height)) / 2) as f64;
+        self.screen_area = (center_width, center_height, pixel_width as f64, pixel_height as f64);
+        let selected = match self.selected_pixel {
+            Some((x, y)) if self.overlay => Some(y as usize * screen_width + x as usize),
+            _ => None,
+        };
 
         let pixel = rectangle::rectangle_by_corners(0.0, 0.0, pixel_width as f64, pixel_height as f64);
-        let iterator = self.emulator.retrieve_screen_pixels().into_iter();
+        let dot = rectangle::square(0.0, 0.0, scale as f64);
+        let iterator = frame.pixels.iter();
         self.gl.draw(args.viewport(), |c, gl| {
 
             clear(color::hex("888888"), gl);
 
             for (index, value) in iterator.enumerate() {
-                let draw_color = if *value { color::WHITE } else { color::BLACK };
+                let draw_color = if Some(index) == selected {
+                    if *value { [1.0, 0.5, 0.5, 1.0] } else { [0.6, 0.0, 0.0, 1.0] }
+                } else if *value { color::WHITE } else { color::BLACK };
                 let pos_x = (((index % screen_width) as u32) * pixel_width) as f64;
                 let pos_y = (((index / screen_width) as f64).floor() * pixel_height as f64) as f64;
 
@@ -88,52 +153,106 @@ impl App {
 
                 rectangle(draw_color, pixel, transform, gl);
             }
+
+            if panel_width > 0 {
+                let background = rectangle::rectangle_by_corners(0.0, 0.0, panel_width as f64, args.height as f64);
+                rectangle(color::BLACK, background, c.transform.trans(game_width as f64, 0.0), gl);
+            }
+            for (line, text) in panel.iter().enumerate() {
+                let top = PANEL_MARGIN + line * (GLYPH_HEIGHT + 1) * scale;
+                for (x, y) in text_pixels(text) {
+                    let transform = c.transform.trans((game_width as usize + PANEL_MARGIN + x * scale) as f64, (top + y * scale) as f64);
+                    rectangle(color::WHITE, dot, transform, gl);
+                }
+            }
         });
     }
 
-    fn handle_key_press(&mut self, key: &Key) {
-        if let Some(key_value) = self.handle_key(key) {
-            self.emulator.press_key(0b1 << key_value)
+    /// Selects the screen pixel under the cursor, so the overlay lists the draw calls that
+    /// flipped it. Clicking outside of the screen clears the selection.
+    fn select_pixel(&mut self) {
+        let frame = match self.frame {
+            Some(ref frame) if self.overlay => frame,
+            _ => return,
+        };
+        let (left, top, pixel_width, pixel_height) = self.screen_area;
+        if pixel_width <= 0.0 || pixel_height <= 0.0 {
+            return;
         }
+        let x = ((self.cursor[0] - left) / pixel_width).floor();
+        let y = ((self.cursor[1] - top) / pixel_height).floor();
+        self.selected_pixel = if x >= 0.0 && y >= 0.0 && (x as usize) < frame.width && (y as usize) < frame.height {
+            Some((x as u8, y as u8))
+        } else {
+            None
+        };
     }
 
-    fn handle_key_release(&mut self, key: &Key) {
-        if let Some(key_value) = self.handle_key(key) {
-            self.emulator.release_key(0b1 << key_value)
+    fn handle_key_press(&mut self, key: &Key) {
+        match *key {
+            Key::F1 if self.debug => {
+                self.overlay = !self.overlay;
+                self.runner.send(Command::Inspect(self.overlay));
+                return;
+            },
+            Key::F5 => {
+                self.paused = !self.paused;
+                self.runner.send(if self.paused { Command::Pause } else { Command::Resume });
+                return;
+            },
+            Key::F6 | Key::F7 => {
+                if !self.paused {
+                    self.paused = true;
+                    self.runner.send(Command::Pause);
+                }
+                self.runner.send(if *key == Key::F6 { Command::Step } else { Command::AdvanceFrame });
+                return;
+            },
+            _ => (),
+        }
+        for hex_key in self.keymap.press(key) {
+            self.runner.send(Command::PressKey(hex_key));
         }
     }
 
-    fn handle_key(&self, key: &Key) -> Option<u16> {
-        match *key {
-            Key::Space => Option::Some(0x0),
-            Key::Y => Option::Some(0x1),
-            Key::X => Option::Some(0x2),
-            Key::C => Option::Some(0x3),
-            Key::A => Option::Some(0x4),
-            Key::S => Option::Some(0x5),
-            Key::D => Option::Some(0x6),
-            Key::Q => Option::Some(0x7),
-            Key::W => Option::Some(0x8),
-            Key::E => Option::Some(0x9),
-            Key::D1 => Option::Some(0xA),
-            Key::D2 => Option::Some(0xB),
-            Key::D3 => Option::Some(0xC),
-            Key::V => Option::Some(0xD),
-            Key::F => Option::Some(0xE),
-            Key::R => Option::Some(0xF),
-            _ => Option::None,
+    fn handle_key_release(&mut self, key: &Key) {
+        for hex_key in self.keymap.release(key) {
+            self.runner.send(Command::ReleaseKey(hex_key));
         }
     }
 
-    fn update(&mut self, args: &UpdateArgs) {
-        self.update_time += args.dt;
-        if self.update_time > UPDATE_LIMIT {
-            self.emulator.update();
-            if self.emulator.should_beep() {
-                // todo: BEEP!
+    fn update(&mut self) {
+        while let Some(event) = self.runner.try_recv() {
+            match event {
+                Event::Frame(frame) => self.frame = Some(frame),
+                Event::Audio(_) => {
+                    // todo: BEEP!
+                },
+                Event::Error(error) => error!("{}", error),
+                Event::State(_) => {},
+                Event::Machine(machine) => self.machine = Some(machine),
             }
-            self.update_time -= UPDATE_LIMIT;
         }
-
     }
-}
\ No newline at end of file
+}
+
+pub fn default_key_profiles() -> KeyProfiles<Key> {
+    KeyProfiles::new(KeyMap::from_bindings(&[
+        (Key::Space, HexKey::Key0),
+        (Key::Y, HexKey::Key1),
+        (Key::X, HexKey::Key2),
+        (Key::C, HexKey::Key3),
+        (Key::A, HexKey::Key4),
+        (Key::S, HexKey::Key5),
+        (Key::D, HexKey::Key6),
+        (Key::Q, HexKey::Key7),
+        (Key::W, HexKey::Key8),
+        (Key::E, HexKey::Key9),
+        (Key::D1, HexKey::KeyA),
+        (Key::D2, HexKey::KeyB),
+        (Key::D3, HexKey::KeyC),
+        (Key::V, HexKey::KeyD),
+        (Key::F, HexKey::KeyE),
+        (Key::R, HexKey::KeyF),
+    ]))
+}
@@ -0,0 +1,78 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::{RunCondition, SymbolTable};
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::coverage::Coverage;
+
+const DEFAULT_FRAMES: u64 = 600;
+const USAGE: &'static str = "Usage: rust8cov <rom> [--frames n] [--ipf n] [--seed n] [--symbols file] [--map output] [--report output]";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        println!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    if arguments.is_empty() {
+        return Err(String::from(USAGE));
+    }
+    let rom_file = &arguments[0];
+    let mut frames = DEFAULT_FRAMES;
+    let (mut map_file, mut report_file) = (None, None);
+    let mut symbols = SymbolTable::new();
+    let mut builder = Chip8Builder::new().log_logo(false);
+    let mut options = arguments[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--frames" => frames = parse_number(options.next().ok_or(USAGE)?)?,
+            "--ipf" => builder = builder.instructions_per_frame(parse_number(options.next().ok_or(USAGE)?)? as u32),
+            "--seed" => builder = builder.seed(parse_number(options.next().ok_or(USAGE)?)?),
+            "--symbols" => symbols = SymbolTable::from_file(options.next().ok_or(USAGE)?)?,
+            "--map" => map_file = Some(options.next().ok_or(USAGE)?),
+            "--report" => report_file = Some(options.next().ok_or(USAGE)?),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+
+    let mut rom = Vec::new();
+    File::open(rom_file)
+        .and_then(|mut file| file.read_to_end(&mut rom))
+        .map_err(|error| format!("Could not read {}: {}", rom_file, error))?;
+    let mut chip8 = builder.build().map_err(|error| format!("{}", error))?;
+    chip8.load(rom);
+    *chip8.symbols_mut() = symbols;
+
+    let coverage = Coverage::new(&chip8);
+    let id = chip8.observers_mut().add(Box::new(coverage));
+    chip8.run_until(&[RunCondition::Frames(frames)]);
+    let coverage = chip8.observers().get::<Coverage>(id).unwrap();
+    if let Some(map_file) = map_file {
+        write_file(map_file, &coverage.map())?;
+    }
+    let report = coverage.report(&chip8);
+    match report_file {
+        Some(report_file) => {
+            write_file(report_file, &report)?;
+            println!("{}", report.lines().nth(1).unwrap_or(""));
+        },
+        None => println!("{}", report),
+    }
+    Ok(())
+}
+
+fn write_file(filename: &str, text: &str) -> Result<(), String> {
+    File::create(filename)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|error| format!("Could not write {}: {}", filename, error))
+}
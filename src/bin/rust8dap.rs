@@ -0,0 +1,66 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::net::TcpListener;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::dap::DapServer;
+
+const USAGE: &'static str = "Usage: rust8dap [rom] [--port n]";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    let (mut rom_file, mut port) = (None, None);
+    let mut options = arguments.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--port" => port = Some(parse_number(options.next().ok_or(USAGE)?)? as u16),
+            _ if !option.starts_with("--") && rom_file.is_none() => rom_file = Some(option),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+
+    let mut chip8 = Chip8Builder::new().log_logo(false).build().map_err(|error| format!("{}", error))?;
+    if let Some(rom_file) = rom_file {
+        let mut rom = Vec::new();
+        File::open(rom_file)
+            .and_then(|mut file| file.read_to_end(&mut rom))
+            .map_err(|error| format!("Could not read {}: {}", rom_file, error))?;
+        chip8.load(rom);
+    }
+    let mut server = DapServer::new(chip8);
+
+    let port = match port {
+        Some(port) => port,
+        None => return server.serve(io::stdin(), io::stdout()).map_err(|error| format!("{}", error)),
+    };
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|error| format!("Could not listen on port {}: {}", port, error))?;
+    eprintln!("Waiting for a debug adapter client on 127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            let reader = stream.try_clone()?;
+            server.serve(reader, stream)
+        });
+        if let Err(error) = result {
+            eprintln!("Connection lost: {}", error);
+        }
+        if server.is_terminated() {
+            break;
+        }
+    }
+    Ok(())
+}
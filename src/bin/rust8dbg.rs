@@ -0,0 +1,54 @@
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::debugger::Debugger;
+
+fn main() {
+    let rom_file = match env::args().nth(1) {
+        Some(rom_file) => rom_file,
+        None => {
+            println!("Usage: rust8dbg <rom> [symbols]");
+            process::exit(1);
+        }
+    };
+    let mut rom = Vec::new();
+    if let Err(error) = File::open(&rom_file).and_then(|mut file| file.read_to_end(&mut rom)) {
+        println!("Could not read {}: {}", rom_file, error);
+        process::exit(1);
+    }
+    let mut chip8 = Chip8Builder::new().log_logo(false).build().unwrap_or_else(|error| panic!("{}", error));
+    chip8.load(rom);
+    let mut debugger = Debugger::new(chip8);
+    if let Some(symbol_file) = env::args().nth(2) {
+        match debugger.execute(&format!("symbols {}", symbol_file)) {
+            Ok(output) => println!("{}", output),
+            Err(error) => println!("Error: {}", error),
+        }
+    }
+    println!("Loaded {}, type help for a list of commands", rom_file);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(rust8dbg) ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        match line.trim() {
+            "quit" | "q" => break,
+            command => match debugger.execute(command) {
+                Ok(ref output) if output.is_empty() => (),
+                Ok(output) => println!("{}", output),
+                Err(error) => println!("Error: {}", error),
+            },
+        }
+    }
+}
@@ -0,0 +1,70 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::emulator::chip8::SymbolTable;
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::diff::{first_divergence, format_divergence, Alignment};
+use rust8::emulator::chip8::symbols::symbolize_asm;
+use rust8::emulator::chip8::trace::{read_trace, TraceRecord};
+
+const DEFAULT_CONTEXT: usize = 5;
+const USAGE: &'static str = "Usage: rust8diff <left trace> <right trace> [--frames] [--context n] [--symbols file]";
+
+fn main() {
+    match run(env::args().skip(1).collect()) {
+        Ok(identical) => process::exit(if identical { 0 } else { 1 }),
+        Err(error) => {
+            println!("{}", error);
+            process::exit(2);
+        }
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<bool, String> {
+    if arguments.len() < 2 {
+        return Err(String::from(USAGE));
+    }
+    let mut alignment = Alignment::Instruction;
+    let mut context = DEFAULT_CONTEXT;
+    let mut symbols = None;
+    let mut options = arguments[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--frames" => alignment = Alignment::Frame,
+            "--context" => context = parse_number(options.next().ok_or(USAGE)?)? as usize,
+            "--symbols" => symbols = Some(SymbolTable::from_file(options.next().ok_or(USAGE)?)?),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+    let mut left = load(&arguments[0])?;
+    let mut right = load(&arguments[1])?;
+    if let Some(symbols) = symbols {
+        for record in left.iter_mut().chain(right.iter_mut()) {
+            record.asm = symbolize_asm(record.opcode, &symbols);
+        }
+    }
+    match first_divergence(&left, &right, alignment) {
+        Some(divergence) => {
+            println!("{}", format_divergence(&left, &right, &divergence, context));
+            Ok(false)
+        },
+        None => {
+            println!("Traces match ({} records)", left.len());
+            Ok(true)
+        },
+    }
+}
+
+fn load(filename: &str) -> Result<Vec<TraceRecord>, String> {
+    let mut trace = Vec::new();
+    File::open(filename)
+        .and_then(|mut file| file.read_to_end(&mut trace))
+        .map_err(|error| format!("Could not read {}: {}", filename, error))?;
+    read_trace(&trace).map_err(|error| format!("{}: {}", filename, error))
+}
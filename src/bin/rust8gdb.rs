@@ -0,0 +1,49 @@
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::net::TcpListener;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::gdb::GdbStub;
+
+const DEFAULT_PORT: u16 = 1234;
+
+fn main() {
+    let rom_file = match env::args().nth(1) {
+        Some(rom_file) => rom_file,
+        None => {
+            println!("Usage: rust8gdb <rom> [port]");
+            process::exit(1);
+        }
+    };
+    let port = env::args().nth(2).map(|port| port.parse().unwrap_or_else(|_| panic!("Invalid port {}", port))).unwrap_or(DEFAULT_PORT);
+    let mut rom = Vec::new();
+    if let Err(error) = File::open(&rom_file).and_then(|mut file| file.read_to_end(&mut rom)) {
+        println!("Could not read {}: {}", rom_file, error);
+        process::exit(1);
+    }
+    let mut chip8 = Chip8Builder::new().log_logo(false).build().unwrap_or_else(|error| panic!("{}", error));
+    chip8.load(rom);
+    let mut stub = GdbStub::new(chip8);
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|error| panic!("Could not listen on port {}: {}", port, error));
+    println!("Waiting for GDB on 127.0.0.1:{}, connect with: target remote :{}", port, port);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                println!("Connection failed: {}", error);
+                continue;
+            }
+        };
+        match stub.serve(stream) {
+            Ok(true) => break,
+            Ok(false) => println!("GDB disconnected, waiting for the next connection"),
+            Err(error) => println!("Connection lost: {}", error),
+        }
+    }
+}
@@ -1,25 +1,37 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
 mod io;
 
+#[macro_use]
 extern crate log;
 extern crate log4rs;
 extern crate rust8;
 
+use std::env;
+
 use log::LogLevelFilter;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::file::FileAppender;
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::config::{Appender, Config, Root};
 
-use rust8::emulator::Emulator;
+use rust8::Registry;
 use io::App;
+use io::default_key_profiles;
 use io::romloader;
 
 fn main() {
     configure_logger("config/log4rs.yml".to_string());
-    let mut emulator: Box<Emulator> = rust8::create_chip8();
-    let game = romloader::load_rom("games/game.c8");
+    let debug = env::args().skip(1).any(|argument| argument == "--debug");
+    let rom_file = env::args().skip(1).find(|argument| argument != "--debug").unwrap_or(String::from("games/game.c8"));
+    let registry = Registry::default();
+    let system = registry.find_for_file(&rom_file).unwrap_or_else(|| panic!("No emulator supports {}", rom_file));
+    info!("Starting {} ({}) for {}", system.name, system.description, rom_file);
+    let mut emulator = system.create().unwrap();
+    let game = romloader::load_rom(&rom_file);
     emulator.load(game);
-    let mut app: App = App::new(emulator);
+    let keymap = default_key_profiles().for_rom(&rom_file).clone();
+    let mut app: App = App::new(emulator, keymap, debug);
     app.run();
 }
 
@@ -0,0 +1,67 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::{RunCondition, SymbolTable};
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::profile::Profiler;
+
+const DEFAULT_FRAMES: u64 = 600;
+const DEFAULT_TOP: usize = 10;
+const USAGE: &'static str = "Usage: rust8prof <rom> [--frames n] [--ipf n] [--seed n] [--symbols file] [--folded output] [--top n]";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        println!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    if arguments.is_empty() {
+        return Err(String::from(USAGE));
+    }
+    let rom_file = &arguments[0];
+    let (mut frames, mut top) = (DEFAULT_FRAMES, DEFAULT_TOP);
+    let mut folded_file = None;
+    let mut symbols = SymbolTable::new();
+    let mut builder = Chip8Builder::new().log_logo(false);
+    let mut options = arguments[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--frames" => frames = parse_number(options.next().ok_or(USAGE)?)?,
+            "--ipf" => builder = builder.instructions_per_frame(parse_number(options.next().ok_or(USAGE)?)? as u32),
+            "--seed" => builder = builder.seed(parse_number(options.next().ok_or(USAGE)?)?),
+            "--symbols" => symbols = SymbolTable::from_file(options.next().ok_or(USAGE)?)?,
+            "--folded" => folded_file = Some(options.next().ok_or(USAGE)?),
+            "--top" => top = parse_number(options.next().ok_or(USAGE)?)? as usize,
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+
+    let mut rom = Vec::new();
+    File::open(rom_file)
+        .and_then(|mut file| file.read_to_end(&mut rom))
+        .map_err(|error| format!("Could not read {}: {}", rom_file, error))?;
+    let mut chip8 = builder.build().map_err(|error| format!("{}", error))?;
+    chip8.load(rom);
+
+    let profiler = Profiler::new(&chip8);
+    let id = chip8.observers_mut().add(Box::new(profiler));
+    chip8.run_until(&[RunCondition::Frames(frames)]);
+    let profiler = chip8.observers().get::<Profiler>(id).unwrap();
+    if let Some(folded_file) = folded_file {
+        File::create(folded_file)
+            .and_then(|mut file| file.write_all(profiler.folded_stacks(&symbols).as_bytes()))
+            .map_err(|error| format!("Could not write {}: {}", folded_file, error))?;
+    }
+    println!("{}", profiler.report(&symbols, top));
+    Ok(())
+}
@@ -0,0 +1,72 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::{Inspect, MEM_SIZE, PROGRAM_ADDRESS};
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::control::RunCondition;
+use rust8::emulator::chip8::sprites::{render_sheet, SpriteFormat};
+
+const DEFAULT_ROWS: usize = 8;
+const DEFAULT_COLUMNS: usize = 16;
+const DEFAULT_SCALE: usize = 4;
+const USAGE: &'static str = "Usage: rust8sprites <rom> <output.ppm> [--start address] [--length n] [--rows n | --schip] [--columns n] [--scale n] [--frames n] [--seed n]";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        println!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    if arguments.len() < 2 {
+        return Err(String::from(USAGE));
+    }
+    let (rom_file, output_file) = (&arguments[0], &arguments[1]);
+    let mut start = PROGRAM_ADDRESS;
+    let mut length = None;
+    let mut format = SpriteFormat::Chip8(DEFAULT_ROWS);
+    let (mut columns, mut scale, mut frames) = (DEFAULT_COLUMNS, DEFAULT_SCALE, 0);
+    let mut builder = Chip8Builder::new().log_logo(false);
+    let mut options = arguments[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--start" => start = parse_number(options.next().ok_or(USAGE)?)? as u16,
+            "--length" => length = Some(parse_number(options.next().ok_or(USAGE)?)? as usize),
+            "--rows" => format = SpriteFormat::Chip8(parse_number(options.next().ok_or(USAGE)?)? as usize),
+            "--schip" => format = SpriteFormat::SuperChip,
+            "--columns" => columns = parse_number(options.next().ok_or(USAGE)?)? as usize,
+            "--scale" => scale = parse_number(options.next().ok_or(USAGE)?)? as usize,
+            "--frames" => frames = parse_number(options.next().ok_or(USAGE)?)?,
+            "--seed" => builder = builder.seed(parse_number(options.next().ok_or(USAGE)?)?),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+
+    let mut rom = Vec::new();
+    File::open(rom_file)
+        .and_then(|mut file| file.read_to_end(&mut rom))
+        .map_err(|error| format!("Could not read {}: {}", rom_file, error))?;
+    let length = length.unwrap_or(rom.len());
+    let mut chip8 = builder.build().map_err(|error| format!("{}", error))?;
+    chip8.load(rom);
+    if frames > 0 {
+        chip8.run_until(&[RunCondition::Frames(frames), RunCondition::KeyWait]);
+    }
+
+    let highlights = chip8.sprite_highlights(format);
+    let image = render_sheet(chip8.read_memory(0, MEM_SIZE), start, length, format, columns, scale, &highlights);
+    File::create(output_file)
+        .and_then(|mut file| file.write_all(&image.to_ppm()))
+        .map_err(|error| format!("Could not write {}: {}", output_file, error))?;
+    println!("Wrote {}x{} sheet of 0x{:03X}-0x{:03X} to {}", image.width, image.height, start, start as usize + length, output_file);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::redundant_static_lifetimes)]
+
+extern crate rust8;
+
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::process;
+
+use rust8::Chip8Builder;
+use rust8::Emulator;
+use rust8::emulator::chip8::{RunCondition, SymbolTable};
+use rust8::emulator::chip8::builder::parse_number;
+use rust8::emulator::chip8::trace::{TraceFilter, TraceFormat, TraceRecorder};
+
+const DEFAULT_INSTRUCTIONS: u64 = 100000;
+const USAGE: &'static str = "Usage: rust8trace <rom> <output> [--binary] [--instructions n] [--seed n] [--symbols file] [--addresses start-end] [--frames first-last]";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        println!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    if arguments.len() < 2 {
+        return Err(String::from(USAGE));
+    }
+    let (rom_file, output_file) = (&arguments[0], &arguments[1]);
+    let mut format = TraceFormat::Jsonl;
+    let mut instructions = DEFAULT_INSTRUCTIONS;
+    let mut filter = TraceFilter::default();
+    let mut symbols = SymbolTable::new();
+    let mut builder = Chip8Builder::new().log_logo(false);
+    let mut options = arguments[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--binary" => format = TraceFormat::Binary,
+            "--instructions" => instructions = parse_number(options.next().ok_or(USAGE)?)?,
+            "--seed" => builder = builder.seed(parse_number(options.next().ok_or(USAGE)?)?),
+            "--symbols" => symbols = SymbolTable::from_file(options.next().ok_or(USAGE)?)?,
+            "--addresses" => {
+                let (start, end) = parse_range(options.next().ok_or(USAGE)?, |address| symbols.resolve(address))?;
+                filter.addresses = Some((start, end));
+            },
+            "--frames" => filter.frames = Some(parse_range(options.next().ok_or(USAGE)?, parse_number)?),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+
+    let mut rom = Vec::new();
+    File::open(rom_file)
+        .and_then(|mut file| file.read_to_end(&mut rom))
+        .map_err(|error| format!("Could not read {}: {}", rom_file, error))?;
+    let mut chip8 = builder.build().map_err(|error| format!("{}", error))?;
+    chip8.load(rom);
+    *chip8.symbols_mut() = symbols;
+
+    let output = File::create(output_file).map_err(|error| format!("Could not create {}: {}", output_file, error))?;
+    let recorder = TraceRecorder::new(BufWriter::new(output), format)
+        .map_err(|error| format!("Could not write {}: {}", output_file, error))?
+        .filter(filter);
+    let id = chip8.observers_mut().add(Box::new(recorder));
+    chip8.run_until(&[RunCondition::Instructions(instructions), RunCondition::Halt]);
+    let recorder = chip8.observers_mut().get_mut::<TraceRecorder<BufWriter<File>>>(id).unwrap();
+    recorder.finish().map_err(|error| format!("Could not write {}: {}", output_file, error))?;
+    let recorded = recorder.recorded();
+    println!("Recorded {} of {} instructions to {}", recorded, chip8.get_instruction_count(), output_file);
+    Ok(())
+}
+
+fn parse_range<T: Copy, F: Fn(&str) -> Result<T, String>>(range: &str, parse: F) -> Result<(T, T), String> {
+    let mut parts = range.splitn(2, '-');
+    let start = parse(parts.next().unwrap_or(""))?;
+    let end = match parts.next() {
+        Some(end) => parse(end)?,
+        None => start,
+    };
+    Ok((start, end))
+}
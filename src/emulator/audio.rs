@@ -0,0 +1,86 @@
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+pub const DEFAULT_FREQUENCY: u32 = 440;
+pub const DEFAULT_AMPLITUDE: i16 = 8000;
+
+pub struct SquareWave {
+    sample_rate: u32,
+    frequency: u32,
+    amplitude: i16,
+    phase: u32,
+}
+
+impl Default for SquareWave {
+    fn default() -> SquareWave {
+        SquareWave::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl SquareWave {
+    pub fn new(sample_rate: u32) -> SquareWave {
+        SquareWave { sample_rate: sample_rate, frequency: DEFAULT_FREQUENCY, amplitude: DEFAULT_AMPLITUDE, phase: 0 }
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.phase = 0;
+    }
+
+    pub fn samples_per_frame(&self, frames_per_second: u32) -> usize {
+        (self.sample_rate / frames_per_second) as usize
+    }
+
+    pub fn fill(&mut self, buffer: &mut [i16], active: bool) {
+        for sample in buffer.iter_mut() {
+            if !active || self.sample_rate == 0 {
+                *sample = 0;
+                continue;
+            }
+            let high = self.phase < self.sample_rate / 2;
+            *sample = if high { self.amplitude } else { -self.amplitude };
+            self.phase = (self.phase + self.frequency) % self.sample_rate;
+        }
+        if !active {
+            self.phase = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SquareWave;
+
+    #[test]
+    fn fill_inactive_is_silent() {
+        let mut under_test = SquareWave::new(8);
+        let mut buffer = [1; 8];
+
+        under_test.fill(&mut buffer, false);
+
+        assert!(buffer.iter().all(|sample| *sample == 0));
+    }
+
+    #[test]
+    fn fill_active_alternates() {
+        let mut under_test = SquareWave::new(8);
+        under_test.frequency = 2;
+        let mut buffer = [0; 8];
+
+        under_test.fill(&mut buffer, true);
+
+        let amplitude = under_test.amplitude;
+        assert!(buffer == [amplitude, amplitude, -amplitude, -amplitude, amplitude, amplitude, -amplitude, -amplitude]);
+    }
+
+    #[test]
+    fn samples_per_frame_success() {
+        let under_test = SquareWave::new(44100);
+
+        let result = under_test.samples_per_frame(60);
+
+        assert!(result == 735);
+    }
+}
@@ -0,0 +1,484 @@
+use core::fmt::{self, Formatter, Display};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Chip8, Inspect};
+use super::builder::parse_number;
+use super::symbols::SymbolTable;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum Operands {
+    None,
+    Address,
+    RegisterByte,
+    RegisterRegister,
+    Register,
+    RegisterRegisterNibble,
+}
+
+const MNEMONICS: [(&'static str, u16, u16, Operands); 35] = [
+    ("CLS", 0x00E0, 0xFFFF, Operands::None),
+    ("RET", 0x00EE, 0xFFFF, Operands::None),
+    ("SYS", 0x0000, 0xF000, Operands::Address),
+    ("JMP", 0x1000, 0xF000, Operands::Address),
+    ("CALL", 0x2000, 0xF000, Operands::Address),
+    ("SE", 0x3000, 0xF000, Operands::RegisterByte),
+    ("SNE", 0x4000, 0xF000, Operands::RegisterByte),
+    ("CPSE", 0x5000, 0xF00F, Operands::RegisterRegister),
+    ("LD", 0x6000, 0xF000, Operands::RegisterByte),
+    ("ADDI", 0x7000, 0xF000, Operands::RegisterByte),
+    ("CP", 0x8000, 0xF00F, Operands::RegisterRegister),
+    ("OR", 0x8001, 0xF00F, Operands::RegisterRegister),
+    ("AND", 0x8002, 0xF00F, Operands::RegisterRegister),
+    ("XOR", 0x8003, 0xF00F, Operands::RegisterRegister),
+    ("ADD", 0x8004, 0xF00F, Operands::RegisterRegister),
+    ("SUB", 0x8005, 0xF00F, Operands::RegisterRegister),
+    ("SHR", 0x8006, 0xF00F, Operands::Register),
+    ("SUBN", 0x8007, 0xF00F, Operands::RegisterRegister),
+    ("SHL", 0x800E, 0xF00F, Operands::Register),
+    ("SNER", 0x9000, 0xF00F, Operands::RegisterRegister),
+    ("LDI", 0xA000, 0xF000, Operands::Address),
+    ("RJMP", 0xB000, 0xF000, Operands::Address),
+    ("RND", 0xC000, 0xF000, Operands::RegisterByte),
+    ("DRW", 0xD000, 0xF000, Operands::RegisterRegisterNibble),
+    ("SKPK", 0xE09E, 0xF0FF, Operands::Register),
+    ("SKPNK", 0xE0A1, 0xF0FF, Operands::Register),
+    ("LDDT", 0xF007, 0xF0FF, Operands::Register),
+    ("WLDK", 0xF00A, 0xF0FF, Operands::Register),
+    ("SDTR", 0xF015, 0xF0FF, Operands::Register),
+    ("SSTR", 0xF018, 0xF0FF, Operands::Register),
+    ("ADDIR", 0xF01E, 0xF0FF, Operands::Register),
+    ("LDSPR", 0xF029, 0xF0FF, Operands::Register),
+    ("BCD", 0xF033, 0xF0FF, Operands::Register),
+    ("STOR", 0xF055, 0xF0FF, Operands::Register),
+    ("READ", 0xF065, 0xF0FF, Operands::Register),
+];
+
+impl Operands {
+    // (shift, mask) of each operand field, in the order they are written.
+    fn fields(&self) -> &'static [(u16, u16)] {
+        match *self {
+            Operands::None => &[],
+            Operands::Address => &[(0, 0xFFF)],
+            Operands::RegisterByte => &[(8, 0xF), (0, 0xFF)],
+            Operands::RegisterRegister => &[(8, 0xF), (4, 0xF)],
+            Operands::Register => &[(8, 0xF)],
+            Operands::RegisterRegisterNibble => &[(8, 0xF), (4, 0xF), (0, 0xF)],
+        }
+    }
+}
+
+/// Matches opcodes by mnemonic, e.g. `DRW` for any draw or `CALL 0x3A2` for one call target.
+/// Operands that are left out or written as `*` match anything, addresses may be symbol names.
+/// `SYS` does not match `CLS` and `RET`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct OpcodePattern {
+    value: u16,
+    mask: u16,
+    excluded: &'static [u16],
+    source: String,
+}
+
+impl OpcodePattern {
+    pub fn parse(pattern: &str) -> Result<OpcodePattern, String> {
+        OpcodePattern::parse_with_symbols(pattern, &SymbolTable::new())
+    }
+
+    pub fn parse_with_symbols(pattern: &str, symbols: &SymbolTable) -> Result<OpcodePattern, String> {
+        let mut tokens = pattern.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty());
+        let mnemonic = tokens.next().ok_or(String::from("Empty opcode pattern"))?;
+        let &(name, mut value, mut mask, operands) = MNEMONICS.iter()
+            .find(|entry| str::eq_ignore_ascii_case(entry.0, mnemonic))
+            .ok_or(format!("Unknown mnemonic '{}'", mnemonic))?;
+        let fields = operands.fields();
+        for (index, token) in tokens.enumerate() {
+            let &(shift, field_mask) = fields.get(index).ok_or(format!("Too many operands for {}", name))?;
+            if token == "*" {
+                continue;
+            }
+            let operand = parse_operand(token, symbols)?;
+            if operand > field_mask as u64 {
+                return Err(format!("Operand '{}' is out of range for {}", token, name));
+            }
+            value |= (operand as u16) << shift;
+            mask |= field_mask << shift;
+        }
+        let excluded: &'static [u16] = if name == "SYS" { &[0x00E0, 0x00EE] } else { &[] };
+        Ok(OpcodePattern { value: value, mask: mask, excluded: excluded, source: String::from(pattern.trim()) })
+    }
+
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value && !self.excluded.contains(&opcode)
+    }
+}
+
+fn parse_operand(token: &str, symbols: &SymbolTable) -> Result<u64, String> {
+    if token.len() == 2 && (token.starts_with('V') || token.starts_with('v')) {
+        return u64::from_str_radix(&token[1..], 16).map_err(|_| format!("Invalid register '{}'", token));
+    }
+    parse_number(token).or_else(|error| symbols.resolve(token).map(|address| address as u64).map_err(|_| error))
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum Value {
+    DataRegister(u8),
+    AddressRegister,
+    ProgramCounter,
+    DelayTimer,
+    SoundTimer,
+    Constant(u16),
+}
+
+impl Value {
+    fn parse(token: &str, symbols: &SymbolTable) -> Result<Value, String> {
+        let upper: String = token.chars().map(|c| c.to_ascii_uppercase()).collect();
+        match upper.as_str() {
+            "I" => Ok(Value::AddressRegister),
+            "PC" => Ok(Value::ProgramCounter),
+            "DT" => Ok(Value::DelayTimer),
+            "ST" => Ok(Value::SoundTimer),
+            _ if upper.len() == 2 && upper.starts_with('V') => {
+                u8::from_str_radix(&upper[1..], 16).map(Value::DataRegister).map_err(|_| format!("Invalid register '{}'", token))
+            },
+            _ if symbols.lookup(token.split('+').next().unwrap_or("").trim()).is_some() => symbols.resolve(token).map(Value::Constant),
+            _ => {
+                let number = parse_number(token)?;
+                if number > 0xFFFF {
+                    return Err(format!("Value '{}' is out of range", token));
+                }
+                Ok(Value::Constant(number as u16))
+            },
+        }
+    }
+
+    fn evaluate(&self, chip8: &Chip8) -> u16 {
+        match *self {
+            Value::DataRegister(register) => chip8.get_data_register(register) as u16,
+            Value::AddressRegister => chip8.get_address_register(),
+            Value::ProgramCounter => chip8.get_program_counter(),
+            Value::DelayTimer => chip8.get_delay_timer() as u16,
+            Value::SoundTimer => chip8.get_sound_timer() as u16,
+            Value::Constant(value) => value,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+const COMPARISONS: [(&'static str, Comparison); 6] = [
+    ("==", Comparison::Equal),
+    ("!=", Comparison::NotEqual),
+    ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual),
+    ("<", Comparison::Less),
+    (">", Comparison::Greater),
+];
+
+/// A condition over registers, I, PC and the timers such as `V3 == 0x10 && I > 0x300`, constants
+/// may be symbol names.
+/// `&&` binds stronger than `||`, parentheses are not supported.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Condition {
+    any_of: Vec<Vec<(Value, Comparison, Value)>>,
+    source: String,
+}
+
+impl Condition {
+    pub fn parse(expression: &str) -> Result<Condition, String> {
+        Condition::parse_with_symbols(expression, &SymbolTable::new())
+    }
+
+    pub fn parse_with_symbols(expression: &str, symbols: &SymbolTable) -> Result<Condition, String> {
+        let mut any_of = Vec::new();
+        for alternative in expression.split("||") {
+            let mut all_of = Vec::new();
+            for comparison in alternative.split("&&") {
+                all_of.push(parse_comparison(comparison.trim(), symbols)?);
+            }
+            any_of.push(all_of);
+        }
+        Ok(Condition { any_of: any_of, source: String::from(expression.trim()) })
+    }
+
+    pub fn evaluate(&self, chip8: &Chip8) -> bool {
+        self.any_of.iter().any(|all_of| all_of.iter().all(|&(left, comparison, right)| {
+            let (left, right) = (left.evaluate(chip8), right.evaluate(chip8));
+            match comparison {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::Less => left < right,
+                Comparison::LessOrEqual => left <= right,
+                Comparison::Greater => left > right,
+                Comparison::GreaterOrEqual => left >= right,
+            }
+        }))
+    }
+}
+
+fn parse_comparison(comparison: &str, symbols: &SymbolTable) -> Result<(Value, Comparison, Value), String> {
+    for &(operator, kind) in COMPARISONS.iter() {
+        if let Some(index) = comparison.find(operator) {
+            let left = Value::parse(comparison[..index].trim(), symbols)?;
+            let right = Value::parse(comparison[index + operator.len()..].trim(), symbols)?;
+            return Ok((left, kind, right));
+        }
+    }
+    Err(format!("Expected a comparison, got '{}'", comparison))
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum BreakpointKind {
+    Address(u16),
+    Opcode(OpcodePattern),
+    Condition(Condition),
+}
+
+impl Display for BreakpointKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            BreakpointKind::Address(address) => write!(f, "0x{:03X}", address),
+            BreakpointKind::Opcode(ref pattern) => write!(f, "opcode {}", pattern.source),
+            BreakpointKind::Condition(ref condition) => write!(f, "if {}", condition.source),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Breakpoint {
+    pub id: u32,
+    pub kind: BreakpointKind,
+    pub enabled: bool,
+    pub hits: u64,
+    pub ignore_count: u64,
+}
+
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    next_id: u32,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Default::default()
+    }
+
+    pub fn add(&mut self, kind: BreakpointKind) -> u32 {
+        self.next_id += 1;
+        self.breakpoints.push(Breakpoint { id: self.next_id, kind: kind, enabled: true, hits: 0, ignore_count: 0 });
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        self.breakpoints.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|breakpoint| breakpoint.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Breakpoint> {
+        self.breakpoints.iter_mut().find(|breakpoint| breakpoint.id == id)
+    }
+
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        self.get_mut(id).map(|breakpoint| breakpoint.enabled = enabled).is_some()
+    }
+
+    /// Skips the next `count` hits of the breakpoint before it stops execution.
+    pub fn set_ignore_count(&mut self, id: u32, count: u64) -> bool {
+        self.get_mut(id).map(|breakpoint| breakpoint.ignore_count = count).is_some()
+    }
+
+    pub fn list(&self) -> &[Breakpoint] {
+        self.breakpoints.as_slice()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    /// Counts a hit on every enabled breakpoint matching the instruction about to run at the
+    /// current PC and returns the first one that stops execution.
+    pub fn check(&mut self, chip8: &Chip8, opcode: u16) -> Option<u32> {
+        let pc = chip8.get_program_counter();
+        let mut stop = None;
+        for breakpoint in self.breakpoints.iter_mut().filter(|breakpoint| breakpoint.enabled) {
+            let hit = match breakpoint.kind {
+                BreakpointKind::Address(address) => address == pc,
+                BreakpointKind::Opcode(ref pattern) => pattern.matches(opcode),
+                BreakpointKind::Condition(ref condition) => condition.evaluate(chip8),
+            };
+            if !hit {
+                continue;
+            }
+            breakpoint.hits += 1;
+            if breakpoint.ignore_count > 0 {
+                breakpoint.ignore_count -= 1;
+            } else if stop.is_none() {
+                stop = Some(breakpoint.id);
+            }
+        }
+        stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BreakpointKind, Condition, OpcodePattern};
+    use super::super::{create_test_chip8, Inspect, RunCondition, StopReason, SymbolTable};
+
+    const ROM: [u8; 12] = [
+        0x63, 0x10, // LD V3, 0x10
+        0xA3, 0x20, // LDI 0x320
+        0x22, 0x0A, // CALL 0x20A
+        0xD0, 0x15, // DRW V0, V1, 5
+        0x12, 0x06, // JMP 0x206
+        0x00, 0xEE, // RET
+    ];
+
+    #[test]
+    fn opcode_pattern_mnemonic_only() {
+        let under_test = OpcodePattern::parse("drw").unwrap();
+
+        assert!(under_test.matches(0xD015));
+        assert!(under_test.matches(0xDABF));
+        assert!(!under_test.matches(0xC015));
+    }
+
+    #[test]
+    fn opcode_pattern_with_operands() {
+        let call = OpcodePattern::parse("CALL 0x3A2").unwrap();
+        let se = OpcodePattern::parse("SE V3, *").unwrap();
+
+        assert!(call.matches(0x23A2));
+        assert!(!call.matches(0x23A4));
+        assert!(se.matches(0x3310));
+        assert!(!se.matches(0x3410));
+    }
+
+    #[test]
+    fn opcode_pattern_sys_excludes_cls_and_ret() {
+        let under_test = OpcodePattern::parse("SYS").unwrap();
+
+        assert!(under_test.matches(0x0123));
+        assert!(!under_test.matches(0x00E0));
+        assert!(!under_test.matches(0x00EE));
+    }
+
+    #[test]
+    fn opcode_pattern_errors() {
+        assert!(OpcodePattern::parse("FOO").is_err());
+        assert!(OpcodePattern::parse("CLS 1").is_err());
+        assert!(OpcodePattern::parse("SE V3, 0x100").is_err());
+    }
+
+    #[test]
+    fn patterns_and_conditions_with_symbols() {
+        let symbols = SymbolTable::parse("draw_player = 0x3A2\nsprites = 0x300").unwrap();
+        let mut chip8 = create_test_chip8(10, ROM.to_vec());
+        chip8.set_address_register(0x302);
+
+        let call = OpcodePattern::parse_with_symbols("CALL draw_player", &symbols).unwrap();
+        let condition = Condition::parse_with_symbols("I == sprites+2", &symbols).unwrap();
+
+        assert!(call.matches(0x23A2));
+        assert!(condition.evaluate(&chip8));
+        assert!(OpcodePattern::parse("CALL draw_player").is_err());
+        assert!(Condition::parse_with_symbols("I == missing", &symbols).is_err());
+    }
+
+    #[test]
+    fn condition_and_or() {
+        let mut chip8 = create_test_chip8(10, ROM.to_vec());
+        chip8.set_data_register(0x3, 0x10);
+        chip8.set_address_register(0x320);
+
+        let both = Condition::parse("V3 == 0x10 && I > 0x300").unwrap();
+        let neither = Condition::parse("V3 != 0x10 && I > 0x300 || DT >= 1").unwrap();
+
+        assert!(both.evaluate(&chip8));
+        assert!(!neither.evaluate(&chip8));
+        assert!(Condition::parse("V3 = 1").is_err());
+        assert!(Condition::parse("VG == 1").is_err());
+    }
+
+    #[test]
+    fn run_until_stops_before_address_breakpoint() {
+        let mut under_test = create_test_chip8(10, ROM.to_vec());
+        let id = under_test.breakpoints_mut().add(BreakpointKind::Address(0x20A));
+
+        let result = under_test.run_until(&[RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::BreakpointHit(id));
+        assert!(result.instructions == 3);
+        assert!(under_test.get_program_counter() == 0x20A);
+        assert!(under_test.get_stack() == [0x206]);
+    }
+
+    #[test]
+    fn run_until_continues_from_breakpoint() {
+        let mut under_test = create_test_chip8(10, ROM.to_vec());
+        let id = under_test.breakpoints_mut().add(BreakpointKind::Opcode(OpcodePattern::parse("DRW").unwrap()));
+        under_test.run_until(&[RunCondition::Frames(10)]);
+
+        let result = under_test.run_until(&[RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::BreakpointHit(id));
+        assert!(result.instructions == 2);
+        assert!(under_test.breakpoints().get(id).unwrap().hits == 2);
+    }
+
+    #[test]
+    fn run_until_condition_breakpoint_with_ignore_count() {
+        let mut under_test = create_test_chip8(10, vec![0x70, 0x01, 0x12, 0x00]);
+        let id = under_test.breakpoints_mut().add(BreakpointKind::Condition(Condition::parse("V0 >= 2").unwrap()));
+        under_test.breakpoints_mut().set_ignore_count(id, 1);
+
+        let result = under_test.run_until(&[RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::BreakpointHit(id));
+        assert!(under_test.get_data_register(0x0) == 2);
+        assert!(under_test.get_program_counter() == 0x200);
+    }
+
+    #[test]
+    fn disabled_breakpoint_does_not_stop() {
+        let mut under_test = create_test_chip8(10, ROM.to_vec());
+        let id = under_test.breakpoints_mut().add(BreakpointKind::Address(0x20A));
+        under_test.breakpoints_mut().set_enabled(id, false);
+
+        let result = under_test.run_until(&[RunCondition::Frames(1)]);
+
+        assert!(result.reason == StopReason::Frames);
+        assert!(under_test.breakpoints().get(id).unwrap().hits == 0);
+    }
+
+    #[test]
+    fn breakpoints_survive_reset() {
+        let mut under_test = create_test_chip8(10, ROM.to_vec());
+        let id = under_test.breakpoints_mut().add(BreakpointKind::Address(0x20A));
+
+        under_test.reset();
+
+        let result = under_test.run_until(&[RunCondition::Frames(1)]);
+
+        assert!(under_test.breakpoints().list().len() == 1);
+        assert!(result.reason == StopReason::BreakpointHit(id));
+        assert!(under_test.get_program_counter() == 0x20A);
+    }
+}
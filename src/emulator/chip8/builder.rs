@@ -0,0 +1,521 @@
+use core::convert::TryFrom;
+use core::fmt::{self, Formatter, Display};
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::prelude::*;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{is_program_address, Chip8, RandomSource};
+use super::component::memory::{self, MEM_SIZE};
+
+pub const FONT_SIZE: usize = 80;
+
+pub const FONTSET: [u8; FONT_SIZE] = [
+    0xf0, 0x90, 0x90, 0x90, 0xf0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xf0, 0x10, 0xf0, 0x80, 0xf0, // 2
+    0xf0, 0x10, 0xf0, 0x10, 0xf0, // 3
+    0x90, 0x90, 0xf0, 0x10, 0x10, // 4
+    0xf0, 0x80, 0xf0, 0x10, 0xf0, // 5
+    0xf0, 0x80, 0xf0, 0x90, 0xf0, // 6
+    0xf0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xf0, 0x90, 0xf0, 0x90, 0xf0, // 8
+    0xf0, 0x90, 0xf0, 0x10, 0xf0, // 9
+    0xf0, 0x90, 0xf0, 0x90, 0x90, // A
+    0xe0, 0x90, 0xe0, 0x90, 0xe0, // B
+    0xf0, 0x80, 0x80, 0x80, 0xf0, // C
+    0xe0, 0x90, 0x90, 0x90, 0xe0, // D
+    0xf0, 0x80, 0xf0, 0x80, 0xf0, // E
+    0xf0, 0x80, 0xf0, 0x80, 0x80, // F
+];
+
+/// The machine variants this core implements. Only the original CHIP-8 is supported, SCHIP and
+/// XO-CHIP are not.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Variant {
+    Chip8,
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Variant::Chip8 => "chip8",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name {
+            "chip8" => Some(Variant::Chip8),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Quirks {
+    pub vf_reset: bool,
+    pub load_store_increments_i: bool,
+    pub jump_uses_vx: bool,
+    pub clip_sprites: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            vf_reset: true,
+            load_store_increments_i: false,
+            jump_uses_vx: false,
+            clip_sprites: true,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Config {
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    pub seed: Option<u64>,
+    pub font: Vec<u8>,
+    pub font_address: u16,
+    pub program_address: u16,
+    pub stack_depth: usize,
+    pub log_logo: bool,
+    pub log_instructions: bool,
+}
+
+impl Config {
+    /// Checks that a ROM of `length` bytes fits into memory at the program address without
+    /// overlapping the font.
+    pub fn check_rom(&self, length: usize) -> Result<(), ConfigError> {
+        let program_start = self.program_address as usize;
+        if program_start + length > MEM_SIZE {
+            return Err(ConfigError::RomOutOfMemory(length));
+        }
+        let font_start = self.font_address as usize;
+        if font_start < program_start + length && program_start < font_start + FONT_SIZE {
+            return Err(ConfigError::FontOverlapsProgram);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            variant: Variant::Chip8,
+            quirks: Default::default(),
+            instructions_per_frame: 1,
+            seed: None,
+            font: FONTSET.to_vec(),
+            font_address: memory::FONT_ADDRESS,
+            program_address: memory::PROGRAM_ADDRESS,
+            stack_depth: 16,
+            log_logo: true,
+            log_instructions: true,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ConfigError {
+    InvalidFontSize(usize),
+    FontOutOfMemory(u16),
+    ProgramAddressOutOfMemory(u16),
+    RomOutOfMemory(usize),
+    FontOverlapsProgram,
+    ZeroInstructionsPerFrame,
+    ZeroStackDepth,
+    Parse(usize, String),
+    Io(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::InvalidFontSize(size) => write!(f, "Font must be {} bytes, got {}", FONT_SIZE, size),
+            ConfigError::FontOutOfMemory(address) => write!(f, "Font at 0x{:X} does not fit into memory", address),
+            ConfigError::ProgramAddressOutOfMemory(address) => write!(f, "Program address 0x{:X} is outside of memory", address),
+            ConfigError::RomOutOfMemory(length) => write!(f, "ROM of {} bytes does not fit into memory", length),
+            ConfigError::FontOverlapsProgram => write!(f, "Font overlaps the program"),
+            ConfigError::ZeroInstructionsPerFrame => write!(f, "Instructions per frame must be at least 1"),
+            ConfigError::ZeroStackDepth => write!(f, "Stack depth must be at least 1"),
+            ConfigError::Parse(line, ref message) => write!(f, "Line {}: {}", line, message),
+            ConfigError::Io(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Configures and builds a `Chip8`. Only the original CHIP-8 is supported, see `Variant`.
+#[derive(Default)]
+pub struct Chip8Builder {
+    config: Config,
+    random: Option<Box<RandomSource>>,
+}
+
+impl Chip8Builder {
+    pub fn new() -> Chip8Builder {
+        Chip8Builder { ..Default::default() }
+    }
+
+    pub fn from_config(config: Config) -> Chip8Builder {
+        Chip8Builder { config: config, random: None }
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Chip8Builder {
+        self.config.variant = variant;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Chip8Builder {
+        self.config.quirks = quirks;
+        self
+    }
+
+    pub fn instructions_per_frame(mut self, instructions: u32) -> Chip8Builder {
+        self.config.instructions_per_frame = instructions;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Chip8Builder {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn random_source(mut self, random: Box<RandomSource>) -> Chip8Builder {
+        self.random = Some(random);
+        self
+    }
+
+    pub fn font(mut self, font: &[u8]) -> Chip8Builder {
+        self.config.font = font.to_vec();
+        self
+    }
+
+    pub fn font_address(mut self, address: u16) -> Chip8Builder {
+        self.config.font_address = address;
+        self
+    }
+
+    pub fn program_address(mut self, address: u16) -> Chip8Builder {
+        self.config.program_address = address;
+        self
+    }
+
+    pub fn stack_depth(mut self, depth: usize) -> Chip8Builder {
+        self.config.stack_depth = depth;
+        self
+    }
+
+    pub fn log_logo(mut self, enabled: bool) -> Chip8Builder {
+        self.config.log_logo = enabled;
+        self
+    }
+
+    pub fn log_instructions(mut self, enabled: bool) -> Chip8Builder {
+        self.config.log_instructions = enabled;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let config = &self.config;
+        if config.font.len() != FONT_SIZE {
+            return Err(ConfigError::InvalidFontSize(config.font.len()));
+        }
+        let font_end = config.font_address as usize + FONT_SIZE;
+        if font_end > MEM_SIZE {
+            return Err(ConfigError::FontOutOfMemory(config.font_address));
+        }
+        if !is_program_address(config.program_address) {
+            return Err(ConfigError::ProgramAddressOutOfMemory(config.program_address));
+        }
+        // the first instruction, the ROM is checked by `Config::check_rom` when it is known
+        config.check_rom(2)?;
+        if config.instructions_per_frame == 0 {
+            return Err(ConfigError::ZeroInstructionsPerFrame);
+        }
+        if config.stack_depth == 0 {
+            return Err(ConfigError::ZeroStackDepth);
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Chip8, ConfigError> {
+        self.validate()?;
+        let mut chip8 = Chip8::from_config(self.config);
+        if let Some(random) = self.random {
+            chip8.set_random_source(random);
+        }
+        Ok(chip8)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let config = &self.config;
+        let mut output = String::new();
+        output.push_str(&format!("variant = {}\n", config.variant.name()));
+        output.push_str(&format!("instructions_per_frame = {}\n", config.instructions_per_frame));
+        if let Some(seed) = config.seed {
+            output.push_str(&format!("seed = {}\n", seed));
+        }
+        output.push_str(&format!("font_address = 0x{:X}\n", config.font_address));
+        output.push_str(&format!("program_address = 0x{:X}\n", config.program_address));
+        output.push_str(&format!("stack_depth = {}\n", config.stack_depth));
+        output.push_str(&format!("log_logo = {}\n", config.log_logo));
+        output.push_str(&format!("log_instructions = {}\n", config.log_instructions));
+        output.push_str(&format!("quirks.vf_reset = {}\n", config.quirks.vf_reset));
+        output.push_str(&format!("quirks.load_store_increments_i = {}\n", config.quirks.load_store_increments_i));
+        output.push_str(&format!("quirks.jump_uses_vx = {}\n", config.quirks.jump_uses_vx));
+        output.push_str(&format!("quirks.clip_sprites = {}\n", config.quirks.clip_sprites));
+        let font: Vec<String> = config.font.iter().map(|byte| format!("{:02x}", byte)).collect();
+        output.push_str(&format!("font = {}\n", font.join(" ")));
+        output
+    }
+
+    pub fn from_config_str(input: &str) -> Result<Chip8Builder, ConfigError> {
+        let mut config: Config = Default::default();
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => return Err(ConfigError::Parse(line_number, format!("Expected 'key = value', got '{}'", line))),
+            };
+            apply_setting(&mut config, key, value).map_err(|message| ConfigError::Parse(line_number, message))?;
+        }
+        Ok(Chip8Builder::from_config(config))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_config_file(filename: &str) -> Result<Chip8Builder, ConfigError> {
+        let mut input = String::new();
+        File::open(filename)
+            .and_then(|mut file| file.read_to_string(&mut input))
+            .map_err(|error| ConfigError::Io(format!("Could not read {}: {}", filename, error)))?;
+        Chip8Builder::from_config_str(&input)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save_config_file(&self, filename: &str) -> Result<(), ConfigError> {
+        File::create(filename)
+            .and_then(|mut file| file.write_all(self.to_config_string().as_bytes()))
+            .map_err(|error| ConfigError::Io(format!("Could not write {}: {}", filename, error)))
+    }
+}
+
+fn apply_setting(config: &mut Config, key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "variant" => config.variant = Variant::from_name(value).ok_or(format!("Unknown variant '{}'", value))?,
+        "instructions_per_frame" => config.instructions_per_frame = u32::try_from(parse_number(value)?)
+            .map_err(|_| format!("Instructions per frame '{}' is out of range", value))?,
+        "seed" => config.seed = Some(parse_number(value)?),
+        "font_address" => config.font_address = parse_address(value)?,
+        "program_address" => config.program_address = parse_address(value)?,
+        "stack_depth" => config.stack_depth = usize::try_from(parse_number(value)?)
+            .map_err(|_| format!("Stack depth '{}' is out of range", value))?,
+        "log_logo" => config.log_logo = parse_bool(value)?,
+        "log_instructions" => config.log_instructions = parse_bool(value)?,
+        "quirks.vf_reset" => config.quirks.vf_reset = parse_bool(value)?,
+        "quirks.load_store_increments_i" => config.quirks.load_store_increments_i = parse_bool(value)?,
+        "quirks.jump_uses_vx" => config.quirks.jump_uses_vx = parse_bool(value)?,
+        "quirks.clip_sprites" => config.quirks.clip_sprites = parse_bool(value)?,
+        "font" => {
+            let mut font = Vec::new();
+            for byte in value.split_whitespace() {
+                font.push(u8::from_str_radix(byte, 16).map_err(|_| format!("Invalid font byte '{}'", byte))?);
+            }
+            config.font = font;
+        },
+        _ => return Err(format!("Unknown setting '{}'", key)),
+    }
+    Ok(())
+}
+
+pub fn parse_number(value: &str) -> Result<u64, String> {
+    let result = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    };
+    result.map_err(|_| format!("Invalid number '{}'", value))
+}
+
+fn parse_address(value: &str) -> Result<u16, String> {
+    let address = parse_number(value)?;
+    if address > 0xFFFF {
+        return Err(format!("Address '{}' is out of range", value));
+    }
+    Ok(address as u16)
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("Expected true or false, got '{}'", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_number, Chip8Builder, ConfigError, Quirks, Variant};
+    use super::super::{Inspect, RandomSource};
+    use emulator::Emulator;
+
+    struct FixedRandom;
+
+    impl RandomSource for FixedRandom {
+        fn next_u8(&mut self) -> u8 {
+            0xAA
+        }
+    }
+
+    #[test]
+    fn build_default_success() {
+        let result = Chip8Builder::new().log_logo(false).build();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn build_chip8_variant() {
+        let result = Chip8Builder::new().variant(Variant::Chip8).log_logo(false).build();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn build_zero_instructions_per_frame() {
+        let result = Chip8Builder::new().instructions_per_frame(0).build();
+
+        assert!(result.err() == Some(ConfigError::ZeroInstructionsPerFrame));
+    }
+
+    #[test]
+    fn build_font_overlaps_program() {
+        let result = Chip8Builder::new().font_address(0x1D0).build();
+
+        assert!(result.err() == Some(ConfigError::FontOverlapsProgram));
+    }
+
+    #[test]
+    fn build_font_after_program() {
+        let under_test = Chip8Builder::new().font_address(0xF00).log_logo(false);
+
+        let rom_before_font = under_test.config().check_rom(0xD00);
+        let rom_into_font = under_test.config().check_rom(0xD01);
+        let rom_past_memory = under_test.config().check_rom(0xE01);
+
+        assert!(under_test.build().is_ok());
+        assert!(rom_before_font.is_ok());
+        assert!(rom_into_font.err() == Some(ConfigError::FontOverlapsProgram));
+        assert!(rom_past_memory.err() == Some(ConfigError::RomOutOfMemory(0xE01)));
+    }
+
+    #[test]
+    fn build_program_address_without_room_for_an_opcode() {
+        let result = Chip8Builder::new().program_address(0xFFF).build();
+
+        assert!(result.err() == Some(ConfigError::ProgramAddressOutOfMemory(0xFFF)));
+    }
+
+    #[test]
+    fn parse_number_hex_and_decimal() {
+        assert!(parse_number("0x1F") == Ok(0x1F));
+        assert!(parse_number("0X1f") == Ok(0x1F));
+        assert!(parse_number("31") == Ok(31));
+        assert!(parse_number("0x").is_err());
+    }
+
+    #[test]
+    fn build_invalid_font_size() {
+        let result = Chip8Builder::new().font(&[0xF0, 0x90]).build();
+
+        assert!(result.err() == Some(ConfigError::InvalidFontSize(2)));
+    }
+
+    #[test]
+    fn build_same_seed_same_random_values() {
+        let mut first = Chip8Builder::new().seed(42).log_logo(false).build().unwrap();
+        let mut second = Chip8Builder::new().seed(42).log_logo(false).build().unwrap();
+        let rom = vec![0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF];
+        first.load(rom.clone());
+        second.load(rom);
+
+        for _ in 0..3 {
+            first.update();
+            second.update();
+        }
+
+        for register in 0..3 {
+            assert!(first.get_data_register(register) == second.get_data_register(register));
+        }
+    }
+
+    #[test]
+    fn build_with_random_source() {
+        let mut under_test = Chip8Builder::new().random_source(Box::new(FixedRandom)).log_logo(false).build().unwrap();
+        under_test.load(vec![0xC0, 0x0F]);
+
+        under_test.update();
+
+        assert!(under_test.get_data_register(0x0) == 0x0A);
+    }
+
+    #[test]
+    fn config_string_roundtrip() {
+        let quirks = Quirks { vf_reset: false, load_store_increments_i: true, jump_uses_vx: true, clip_sprites: false };
+        let under_test = Chip8Builder::new().quirks(quirks).seed(7).instructions_per_frame(11).stack_depth(12);
+
+        let result = Chip8Builder::from_config_str(&under_test.to_config_string()).unwrap();
+
+        assert!(result.config() == under_test.config());
+    }
+
+    #[test]
+    fn from_config_str_partial() {
+        let result = Chip8Builder::from_config_str("# per-ROM settings\ninstructions_per_frame = 0x10\n\nquirks.jump_uses_vx = true\n").unwrap();
+
+        assert!(result.config().instructions_per_frame == 16);
+        assert!(result.config().quirks.jump_uses_vx);
+        assert!(result.config().stack_depth == 16);
+    }
+
+    #[test]
+    fn from_config_str_unknown_key() {
+        let result = Chip8Builder::from_config_str("variant = chip8\nspeed = 3\n");
+
+        assert!(result.err() == Some(ConfigError::Parse(2, String::from("Unknown setting 'speed'"))));
+    }
+
+    #[test]
+    fn from_config_str_unknown_variant() {
+        let result = Chip8Builder::from_config_str("variant = xochip\n");
+
+        assert!(result.err() == Some(ConfigError::Parse(1, String::from("Unknown variant 'xochip'"))));
+    }
+
+    #[test]
+    fn from_config_str_instructions_per_frame_out_of_range() {
+        let result = Chip8Builder::from_config_str("instructions_per_frame = 0x100000000\n");
+
+        assert!(result.err() == Some(ConfigError::Parse(1, String::from("Instructions per frame '0x100000000' is out of range"))));
+    }
+
+    #[test]
+    fn from_config_str_invalid_bool() {
+        let result = Chip8Builder::from_config_str("quirks.vf_reset = yes\n");
+
+        assert!(result.is_err());
+    }
+}
@@ -16,6 +16,10 @@ impl Input {
         self.pressed_keys &= !key
     }
 
+    pub fn get_pressed_keys(&self) -> u16 {
+        self.pressed_keys
+    }
+
     pub fn get_any_pressed_key(&self) -> Option<u8> {
         for i in 0..16 {
             if (self.pressed_keys & (1 << i)) > 0 {
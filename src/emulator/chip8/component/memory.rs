@@ -1,14 +1,34 @@
+use core::cell::RefCell;
+use core::mem;
+use alloc::vec::Vec;
+
 pub const MEM_SIZE: usize = 4096;
 pub const FONT_ADDRESS: u16 = 0x100;
 pub const PROGRAM_ADDRESS: u16 = 0x200;
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub address: u16,
+    pub old: u8,
+    pub value: u8,
+}
+
 pub struct Memory {
-    memory: [u8; MEM_SIZE]
+    memory: [u8; MEM_SIZE],
+    recording: bool,
+    accesses: RefCell<Vec<MemoryAccess>>,
 }
 
 impl Default for Memory {
     fn default() -> Memory {
-        Memory { memory: [0; MEM_SIZE] }
+        Memory { memory: [0; MEM_SIZE], recording: false, accesses: RefCell::new(Vec::new()) }
     }
 }
 
@@ -19,36 +39,87 @@ impl Memory {
         for (i, value) in init_vec.into_iter().enumerate() {
             init_memory[i + address] = value;
         }
-        Memory { memory: init_memory }
+        Memory { memory: init_memory, ..Default::default() }
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+        self.accesses.borrow_mut().clear();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn access_count(&self) -> usize {
+        self.accesses.borrow().len()
+    }
+
+    /// The accesses recorded after the first `count`, which stay recorded.
+    pub fn accesses_since(&self, count: usize) -> Vec<MemoryAccess> {
+        self.accesses.borrow()[count..].to_vec()
+    }
+
+    #[allow(dead_code)]
+    pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+        mem::take(&mut *self.accesses.borrow_mut())
+    }
+
+    fn record_read(&self, address: u16, length: usize) {
+        if self.recording {
+            let mut accesses = self.accesses.borrow_mut();
+            for address in address as usize..address as usize + length {
+                let value = self.memory[address];
+                accesses.push(MemoryAccess { kind: AccessKind::Read, address: address as u16, old: value, value: value });
+            }
+        }
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        if self.recording {
+            let access = MemoryAccess { kind: AccessKind::Write, address: address as u16, old: self.memory[address], value: value };
+            self.accesses.borrow_mut().push(access);
+        }
+        self.memory[address] = value;
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.memory
     }
 
     pub fn retrieve_value_from_address(&self, address: u16) -> u8 {
+        self.record_read(address, 1);
         self.memory[address as usize]
     }
 
     pub fn retrieve_range(&self, mem_start: u16, size: u8) -> &[u8] {
         if ((mem_start + size as u16) as usize) < MEM_SIZE {
-            self.memory[mem_start as usize..(mem_start + (size as u16)) as usize].into_iter().as_slice()
+            self.record_read(mem_start, size as usize);
+            &self.memory[mem_start as usize..(mem_start + (size as u16)) as usize]
         } else { panic!("Memory Overflow when retrieving memory range") }
 
     }
 
     pub fn store_binary_representation_of_value(&mut self, value: u8, address: u16) {
-        self.memory[address as usize] = value / 100;
-        self.memory[(address + 1) as usize] = (value / 10) % 10;
-        self.memory[(address + 2) as usize] = value % 10;
+        self.write(address as usize, value / 100);
+        self.write((address + 1) as usize, (value / 10) % 10);
+        self.write((address + 2) as usize, value % 10);
     }
 
     pub fn store_from_address_on(&mut self, value: &[u8], address: u16) {
-        for (index, byte) in value.into_iter().enumerate() {
-            self.memory[(address as usize) + index] = *byte;
+        for (index, byte) in value.iter().enumerate() {
+            self.write((address as usize) + index, *byte);
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Memory;
+    use super::{AccessKind, Memory, MemoryAccess};
 
     #[test]
     fn retrieve_value_from_address_success() {
@@ -154,11 +225,36 @@ mod tests {
         assert!(result1 == 3);
     }
 
+    #[test]
+    fn take_accesses_records_reads_and_writes() {
+        let mut under_test = create_test_memory(0x300);
+        under_test.set_recording(true);
+
+        under_test.retrieve_range(0x301, 2);
+        under_test.store_binary_representation_of_value(123, 0x300);
+        let result = under_test.take_accesses();
+
+        assert!(result.len() == 5);
+        assert!(result[0] == MemoryAccess { kind: AccessKind::Read, address: 0x301, old: 0x1, value: 0x1 });
+        assert!(result[2] == MemoryAccess { kind: AccessKind::Write, address: 0x300, old: 0x0, value: 1 });
+        assert!(under_test.take_accesses().is_empty());
+    }
+
+    #[test]
+    fn take_accesses_not_recording() {
+        let mut under_test = create_test_memory(0x300);
+
+        under_test.store_from_address_on(&[1, 2], 0x300);
+        under_test.retrieve_value_from_address(0x300);
+
+        assert!(under_test.take_accesses().is_empty());
+    }
+
     #[test]
     fn store_from_address_on_success() {
         let mut under_test = create_test_memory(0x200);
 
-        under_test.store_from_address_on(&vec![1, 2, 3, 4], 0x300);
+        under_test.store_from_address_on(&[1, 2, 3, 4], 0x300);
 
         let result0 = under_test.retrieve_value_from_address(0x300);
         let result1 = under_test.retrieve_value_from_address(0x301);
@@ -1,4 +1,4 @@
-use std::fmt::{self, Formatter, Display};
+use core::fmt::{self, Formatter, Display};
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ASM {
@@ -40,6 +40,50 @@ pub enum ASM {
     ERR
 }
 
+impl ASM {
+    /// The name of the instruction without its operands, e.g. `DRW`.
+    pub fn mnemonic(&self) -> &'static str {
+        match *self {
+            ASM::CLS => "CLS",
+            ASM::RET => "RET",
+            ASM::SYS(_) => "SYS",
+            ASM::JMP(_) => "JMP",
+            ASM::CALL(_) => "CALL",
+            ASM::SE(_, _) => "SE",
+            ASM::SNE(_, _) => "SNE",
+            ASM::CPSE(_, _) => "CPSE",
+            ASM::LD(_, _) => "LD",
+            ASM::ADDI(_, _) => "ADDI",
+            ASM::CP(_, _) => "CP",
+            ASM::OR(_, _) => "OR",
+            ASM::AND(_, _) => "AND",
+            ASM::XOR(_, _) => "XOR",
+            ASM::ADD(_, _) => "ADD",
+            ASM::SUB(_, _) => "SUB",
+            ASM::SHR(_) => "SHR",
+            ASM::SUBN(_, _) => "SUBN",
+            ASM::SHL(_) => "SHL",
+            ASM::SNER(_, _) => "SNER",
+            ASM::LDI(_) => "LDI",
+            ASM::RJMP(_) => "RJMP",
+            ASM::RND(_, _) => "RND",
+            ASM::DRW(_, _, _) => "DRW",
+            ASM::SKPK(_) => "SKPK",
+            ASM::SKPNK(_) => "SKPNK",
+            ASM::LDDT(_) => "LDDT",
+            ASM::WLDK(_) => "WLDK",
+            ASM::SDTR(_) => "SDTR",
+            ASM::SSTR(_) => "SSTR",
+            ASM::ADDIR(_) => "ADDIR",
+            ASM::LDSPR(_) => "LDSPR",
+            ASM::BCD(_) => "BCD",
+            ASM::STOR(_) => "STOR",
+            ASM::READ(_) => "READ",
+            ASM::ERR => "ERR",
+        }
+    }
+}
+
 impl Display for ASM {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -62,6 +106,7 @@ impl Opcode {
         opcode
     }
 
+    #[allow(dead_code)]
     pub fn from_asm(command: ASM) -> Opcode {
         match command {
             ASM::CLS =>
@@ -69,21 +114,21 @@ impl Opcode {
             ASM::RET =>
                 Opcode { opcode: 0x00EE, assembler: command },
             ASM::SYS(address) =>
-                Opcode { opcode: 0x0000 | address, assembler: command },
+                Opcode { opcode: address, assembler: command },
             ASM::JMP(address) =>
                 Opcode { opcode: 0x1000 | address, assembler: command },
             ASM::CALL(address) =>
                 Opcode { opcode: 0x2000 | address, assembler: command },
             ASM::SE(register, value) =>
-                Opcode { opcode: 0x3000 | ((register as u16) << 8) + value as u16, assembler: command },
+                Opcode { opcode: 0x3000 | (((register as u16) << 8) + value as u16), assembler: command },
             ASM::SNE(register, value) =>
-                Opcode { opcode: 0x4000 | ((register as u16) << 8) + value as u16, assembler: command },
+                Opcode { opcode: 0x4000 | (((register as u16) << 8) + value as u16), assembler: command },
             ASM::CPSE(register1, register2) =>
                 Opcode { opcode: 0x5000 | (((register1 as u16) << 8) + (register2 << 4) as u16), assembler: command },
             ASM::LD(register, value) =>
-                Opcode { opcode: 0x6000 | ((register as u16) << 8) + value as u16, assembler: command },
+                Opcode { opcode: 0x6000 | (((register as u16) << 8) + value as u16), assembler: command },
             ASM::ADDI(register, value) =>
-                Opcode { opcode: 0x7000 | ((register as u16) << 8) + value as u16, assembler: command },
+                Opcode { opcode: 0x7000 | (((register as u16) << 8) + value as u16), assembler: command },
             ASM::CP(register1, register2) =>
                 Opcode { opcode: 0x8000 | (((register1 as u16) << 8) + (register2 << 4) as u16), assembler: command },
             ASM::OR(register1, register2) =>
@@ -109,7 +154,7 @@ impl Opcode {
             ASM::RJMP(address) =>
                 Opcode { opcode: 0xB000 | address, assembler: command },
             ASM::RND(register, value) =>
-                Opcode { opcode: 0xC000 | ((register as u16) << 8) + value as u16, assembler: command },
+                Opcode { opcode: 0xC000 | (((register as u16) << 8) + value as u16), assembler: command },
             ASM::DRW(register_x, register_y, register_h) =>
                 Opcode { opcode: 0xD000 | (((register_x as u16) << 8) + ((register_y as u16) << 4) + register_h as u16), assembler: command },
             ASM::SKPK(register) =>
@@ -166,8 +211,8 @@ impl Opcode {
         };
 
         match self.assembler {
-            ASM::SHL(_) => self.opcode = self.opcode & 0xFF0F,
-            ASM::SHR(_) => self.opcode = self.opcode & 0xFF0F,
+            ASM::SHL(_) => self.opcode &= 0xFF0F,
+            ASM::SHR(_) => self.opcode &= 0xFF0F,
             _ => {}
         }
     }
@@ -264,9 +309,8 @@ impl Opcode {
         ((self.opcode >> 12) as u8 & 0xF, (self.opcode >> 8) as u8 & 0xF, (self.opcode >> 4) as u8 & 0xF, (self.opcode & 0xF) as u8)
     }
 
-    #[allow(dead_code)]
-    fn as_u16(&self) -> u16 {
-        self.opcode as u16
+    pub fn as_u16(&self) -> u16 {
+        self.opcode
     }
 
     fn as_masked(&self, bitmask: u16) -> u16 {
@@ -286,6 +330,13 @@ mod tests {
     use super::Opcode;
     use super::ASM;
 
+    #[test]
+    fn mnemonic_without_operands() {
+        assert!(Opcode::new(0xD015).as_asm().mnemonic() == "DRW");
+        assert!(Opcode::new(0x00EE).as_asm().mnemonic() == "RET");
+        assert!(ASM::ERR.mnemonic() == "ERR");
+    }
+
     #[test]
     fn as_nibbles_min() {
         let under_test = Opcode::new(0x0000);
@@ -1,6 +1,5 @@
-const REGISTER_COUNT: usize = 16;
+pub const REGISTER_COUNT: usize = 16;
 
-use ::emulator::chip8::component::memory;
 use ::emulator::chip8::component::memory::Memory;
 
 #[derive(Default)]
@@ -49,12 +48,12 @@ impl Registers {
     }
 
     pub fn get_data_registers(&self, start: u8, end: u8) -> &[u8] {
-        self.data_registers[start as usize..(end + 1) as usize].into_iter().as_slice()
+        &self.data_registers[start as usize..(end + 1) as usize]
     }
 
     pub fn store_until_register(&mut self, register: u8, address: u16, memory: &Memory) {
         for i in 0..register + 1 {
-            self.data_registers[i as usize] = memory.retrieve_value_from_address(address as u16 + i as u16);
+            self.data_registers[i as usize] = memory.retrieve_value_from_address(address + i as u16);
         }
     }
 
@@ -97,9 +96,9 @@ impl Registers {
         self.address_register += self.data_registers[register as usize] as u16;
     }
 
-    pub fn set_address_register_to_sprite_from_register(&mut self, register: u8) {
+    pub fn set_address_register_to_sprite_from_register(&mut self, register: u8, font_address: u16) {
         let char = self.get_data_register_value(register) as u16;
-        self.address_register = memory::FONT_ADDRESS + (5 * char);
+        self.address_register = font_address + (5 * char);
     }
 
     fn is_register_valid(&self, register: u8) -> bool {
@@ -384,7 +383,7 @@ mod tests {
         let mut under_test: Registers = Default::default();
         under_test.set_data_register_by_value(1, 4);
 
-        under_test.set_address_register_to_sprite_from_register(1);
+        under_test.set_address_register_to_sprite_from_register(1, memory::FONT_ADDRESS);
 
         let result = under_test.get_address_register_value();
         assert!(result == (memory::FONT_ADDRESS + 20))
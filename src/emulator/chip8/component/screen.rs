@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 const SCREEN_HEIGHT: usize = 32;
 const SCREEN_WIDTH: usize = 64;
 const SCREEN_PIXELS: usize = SCREEN_HEIGHT * SCREEN_WIDTH;
@@ -25,6 +27,11 @@ impl Screen {
         (self.screen_width, self.screen_height)
     }
 
+    pub fn restore(&mut self, state: &[bool]) {
+        assert!(state.len() == self.screen.len());
+        self.screen.copy_from_slice(state);
+    }
+
     pub fn clear(&mut self) {
         for pixel in &mut self.screen {
             *pixel = false;
@@ -33,7 +40,7 @@ impl Screen {
 
     pub fn draw(&mut self, pos_x: u8, pos_y: u8, sprite: &[u8]) -> bool {
         let mut pixel_flipped: bool = false;
-        for (row_index, sprite_row) in sprite.into_iter().enumerate() {
+        for (row_index, sprite_row) in sprite.iter().enumerate() {
             if (row_index + pos_y as usize) >= self.screen_height {
                 break;
             }
@@ -53,6 +60,23 @@ impl Screen {
         pixel_flipped
     }
 
+    pub fn draw_wrapping(&mut self, pos_x: u8, pos_y: u8, sprite: &[u8]) -> bool {
+        let mut pixel_flipped: bool = false;
+        for (row_index, sprite_row) in sprite.iter().enumerate() {
+            let row = (pos_y as usize + row_index) % self.screen_height;
+            let mask: u8 = 0b10000000;
+            for col_index in 0..SPRITE_WIDTH {
+                if (sprite_row & mask >> col_index) > 0 {
+                    let col = (pos_x as usize + col_index) % self.screen_width;
+                    let cur_pos = col + row * self.screen_width;
+                    if self.screen[cur_pos] { pixel_flipped = true }
+                    self.screen[cur_pos] = !self.screen[cur_pos]
+                }
+            }
+        }
+        pixel_flipped
+    }
+
     fn translate_coordinate(&mut self, pos_x: u8, pos_y: u8) -> usize {
         (pos_x as usize) + ((pos_y as usize) * self.screen_width)
     }
@@ -121,7 +145,7 @@ mod tests {
         for (i, res) in result.iter().enumerate() {
             assert!(*res == expected[i]);
         }
-        assert!(switch == false);
+        assert!(!switch);
     }
 
     #[test]
@@ -137,7 +161,7 @@ mod tests {
         for (i, res) in result.iter().enumerate() {
             assert!(*res == expected[i]);
         }
-        assert!(switch == true);
+        assert!(switch);
     }
 
     #[test]
@@ -158,7 +182,7 @@ mod tests {
         for (i, res) in result.iter().enumerate() {
             assert!(*res == expected[i]);
         }
-        assert!(switch == false);
+        assert!(!switch);
     }
 
     #[test]
@@ -180,6 +204,27 @@ mod tests {
         for (i, res) in result.iter().enumerate() {
             assert!(*res == expected[i]);
         }
-        assert!(switch == false);
+        assert!(!switch);
+    }
+
+    #[test]
+    fn draw_wrapping_x_and_y() {
+        let mut under_test: Screen = Screen { screen: create_test_screen(), screen_height: TEST_HEIGHT, screen_width: TEST_WIDTH };
+        let switch = under_test.draw_wrapping(5, 2, &TEST_DATA);
+
+        let result = under_test.retrieve_state();
+
+        let expected = [
+            false, false, false, false, false, false, false, false, true, true,
+            false, false, false, false, false, false, false, false, false, false,
+            true, true, false, false, false, false, true, true, true, true,
+            false, false, false, false, false, false, false, false, true, true,
+            false, false, false, false, false, false, false, false, true, true,
+        ];
+
+        for (i, res) in result.iter().enumerate() {
+            assert!(*res == expected[i]);
+        }
+        assert!(!switch);
     }
 }
@@ -0,0 +1,245 @@
+use core::mem;
+use alloc::vec::Vec;
+
+use super::{Chip8, MEM_SIZE, retrieve_op};
+use super::breakpoint::Breakpoints;
+use super::watchpoint::WatchpointHit;
+use super::component::opcode::ASM;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RunCondition {
+    ProgramCounter(u16),
+    MemoryChanged(u16),
+    Frames(u64),
+    Instructions(u64),
+    NextDraw,
+    KeyWait,
+    Halt,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StopReason {
+    ProgramCounter(u16),
+    MemoryChanged { address: u16, old: u8, new: u8 },
+    Frames,
+    Instructions,
+    Draw,
+    KeyWait,
+    Halt,
+    BreakpointHit(u32),
+    WatchpointHit(WatchpointHit),
+    /// Nothing was executed, the conditions had no `Frames` or `Instructions` budget or a
+    /// `MemoryChanged` address is outside of memory.
+    InvalidCondition,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct RunResult {
+    pub reason: StopReason,
+    pub instructions: u64,
+    pub frames: u64,
+}
+
+impl Chip8 {
+    /// Executes instructions until one of `conditions` is met, checking them after every
+    /// instruction. An enabled breakpoint stops the run before its instruction executes, except on
+    /// the first instruction so a run can continue from it. An enabled watchpoint stops the run
+    /// after the instruction that accessed the watched memory. A run needs a `Frames` or
+    /// `Instructions` condition as its budget, so a program that never meets the other conditions
+    /// cannot hang the caller. Without a budget, or with a `MemoryChanged` address outside of
+    /// memory, nothing runs and the reason is `StopReason::InvalidCondition`.
+    pub fn run_until(&mut self, conditions: &[RunCondition]) -> RunResult {
+        let valid = conditions.iter().all(|condition| match *condition {
+            RunCondition::MemoryChanged(address) => (address as usize) < MEM_SIZE,
+            _ => true,
+        });
+        let budget = conditions.iter().any(|condition| matches!(*condition, RunCondition::Frames(_) | RunCondition::Instructions(_)));
+        if !budget || !valid {
+            return RunResult { reason: StopReason::InvalidCondition, instructions: 0, frames: 0 };
+        }
+        let watched: Vec<(u16, u8)> = conditions.iter()
+            .filter_map(|condition| match *condition {
+                RunCondition::MemoryChanged(address) => Some((address, self.memory.as_slice()[address as usize])),
+                _ => None,
+            })
+            .collect();
+        let (mut instructions, mut frames) = (0, 0);
+        loop {
+            let pc = self.pc;
+            let opcode = retrieve_op(&self.memory, pc);
+            if instructions > 0 {
+                if let Some(id) = self.check_breakpoints() {
+                    return RunResult { reason: StopReason::BreakpointHit(id), instructions: instructions, frames: frames };
+                }
+            }
+            let asm = opcode.as_asm();
+            if self.step() { frames += 1 }
+            instructions += 1;
+
+            if let Some(hit) = self.watchpoint_hit {
+                return RunResult { reason: StopReason::WatchpointHit(hit), instructions: instructions, frames: frames };
+            }
+
+            for condition in conditions {
+                let reason = match *condition {
+                    RunCondition::ProgramCounter(address) if self.pc == address => Some(StopReason::ProgramCounter(address)),
+                    RunCondition::Frames(count) if frames >= count => Some(StopReason::Frames),
+                    RunCondition::Instructions(count) if instructions >= count => Some(StopReason::Instructions),
+                    RunCondition::NextDraw => match asm {
+                        ASM::DRW(_, _, _) => Some(StopReason::Draw),
+                        _ => None,
+                    },
+                    RunCondition::KeyWait => match asm {
+                        ASM::WLDK(_) if self.pc == pc => Some(StopReason::KeyWait),
+                        _ => None,
+                    },
+                    RunCondition::Halt if self.halted => Some(StopReason::Halt),
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    return RunResult { reason: reason, instructions: instructions, frames: frames };
+                }
+            }
+            for &(address, old) in &watched {
+                let new = self.memory.as_slice()[address as usize];
+                if new != old {
+                    let reason = StopReason::MemoryChanged { address: address, old: old, new: new };
+                    return RunResult { reason: reason, instructions: instructions, frames: frames };
+                }
+            }
+        }
+    }
+
+    /// Checks the enabled breakpoints against the instruction at PC, counting a hit for each match.
+    /// Lets a run that was split into several `run_until` calls stop on the first instruction of
+    /// every call but the first.
+    pub fn check_breakpoints(&mut self) -> Option<u32> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        let opcode = retrieve_op(&self.memory, self.pc);
+        let mut breakpoints = mem::replace(&mut self.breakpoints, Breakpoints::new());
+        let hit = breakpoints.check(self, opcode.as_u16());
+        self.breakpoints = breakpoints;
+        hit
+    }
+
+    pub fn get_instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunCondition, RunResult, StopReason};
+    use super::super::{create_test_chip8, Inspect};
+    use emulator::Emulator;
+    use emulator::keypad::HexKey;
+
+    #[test]
+    fn run_until_program_counter() {
+        let mut under_test = create_test_chip8(1, vec![0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x06]);
+
+        let result = under_test.run_until(&[RunCondition::ProgramCounter(0x204), RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::ProgramCounter(0x204));
+        assert!(result.instructions == 2);
+        assert!(result.frames == 2);
+    }
+
+    #[test]
+    fn run_until_memory_changed() {
+        let mut under_test = create_test_chip8(4, vec![0x60, 0x7B, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x06]);
+
+        let result = under_test.run_until(&[RunCondition::MemoryChanged(0x301), RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::MemoryChanged { address: 0x301, old: 0, new: 2 });
+        assert!(result.instructions == 3);
+        assert!(result.frames == 0);
+    }
+
+    #[test]
+    fn run_until_invalid_conditions() {
+        let mut under_test = create_test_chip8(1, vec![0x70, 0x01, 0x12, 0x00]);
+
+        let empty = under_test.run_until(&[]);
+        let without_budget = under_test.run_until(&[RunCondition::ProgramCounter(0x204), RunCondition::Halt]);
+        let outside = under_test.run_until(&[RunCondition::Frames(1), RunCondition::MemoryChanged(0x1000)]);
+
+        assert!(empty == RunResult { reason: StopReason::InvalidCondition, instructions: 0, frames: 0 });
+        assert!(without_budget.reason == StopReason::InvalidCondition);
+        assert!(outside.reason == StopReason::InvalidCondition);
+        assert!(under_test.get_instruction_count() == 0);
+    }
+
+    #[test]
+    fn run_until_frames_and_instructions() {
+        let mut under_test = create_test_chip8(3, vec![0x70, 0x01, 0x12, 0x00]);
+
+        let frames = under_test.run_until(&[RunCondition::Frames(2)]);
+        let instructions = under_test.run_until(&[RunCondition::Instructions(4), RunCondition::Frames(5)]);
+
+        assert!(frames.reason == StopReason::Frames);
+        assert!(frames.instructions == 6);
+        assert!(instructions.reason == StopReason::Instructions);
+        assert!(under_test.get_instruction_count() == 10);
+        assert!(under_test.get_frame_count() == 3);
+    }
+
+    #[test]
+    fn run_until_stops_at_budget_when_other_conditions_never_fire() {
+        let mut under_test = create_test_chip8(10, vec![0x60, 0x00, 0xF1, 0x0A, 0x12, 0x04]);
+
+        let result = under_test.run_until(&[RunCondition::ProgramCounter(0x204), RunCondition::MemoryChanged(0x300), RunCondition::Frames(3)]);
+
+        assert!(result.reason == StopReason::Frames);
+        assert!(result.frames == 3);
+        assert!(under_test.get_program_counter() == 0x202);
+    }
+
+    #[test]
+    fn run_until_next_draw() {
+        let mut under_test = create_test_chip8(10, vec![0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06]);
+
+        let result = under_test.run_until(&[RunCondition::NextDraw, RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::Draw);
+        assert!(result.instructions == 3);
+        assert!(under_test.get_program_counter() == 0x206);
+    }
+
+    #[test]
+    fn run_until_key_wait() {
+        let mut under_test = create_test_chip8(10, vec![0x60, 0x00, 0xF1, 0x0A, 0x12, 0x04]);
+
+        let result = under_test.run_until(&[RunCondition::KeyWait, RunCondition::Halt, RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::KeyWait);
+        assert!(under_test.get_program_counter() == 0x202);
+    }
+
+    #[test]
+    fn run_until_key_wait_skipped_when_key_pressed() {
+        let mut under_test = create_test_chip8(10, vec![0x60, 0x00, 0xF1, 0x0A, 0x12, 0x04]);
+        under_test.press_key(HexKey::Key7);
+
+        let result = under_test.run_until(&[RunCondition::KeyWait, RunCondition::Halt, RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::Halt);
+        assert!(under_test.get_data_register(0x1) == 0x7);
+    }
+
+    #[test]
+    fn run_until_halt() {
+        let mut under_test = create_test_chip8(1, vec![0x60, 0x01, 0x12, 0x02]);
+
+        let result = under_test.run_until(&[RunCondition::Halt, RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::Halt);
+        assert!(result.instructions == 2);
+    }
+}
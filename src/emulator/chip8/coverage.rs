@@ -0,0 +1,284 @@
+use core::any::Any;
+use core::cmp;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Chip8, disassemble_with_symbols};
+use super::component::memory::{AccessKind, MEM_SIZE};
+use super::component::opcode::{Opcode, ASM};
+use super::observer::{Step, StepObserver};
+
+pub const EXECUTED: u8 = 0x01;
+pub const SPRITE: u8 = 0x02;
+pub const LOADED: u8 = 0x04;
+pub const WRITTEN: u8 = 0x08;
+
+const INSTRUCTION_START: u8 = 0x10;
+const BYTES_PER_LINE: usize = 8;
+
+/// What a memory region was used for, the first of executed, drawn by DXYN, loaded by FX65 and
+/// written that applies to all of its bytes.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RegionKind {
+    Code,
+    Sprite,
+    Data,
+    Written,
+    Unused,
+}
+
+impl RegionKind {
+    fn from_usage(usage: u8) -> RegionKind {
+        if usage & EXECUTED != 0 {
+            RegionKind::Code
+        } else if usage & SPRITE != 0 {
+            RegionKind::Sprite
+        } else if usage & LOADED != 0 {
+            RegionKind::Data
+        } else if usage & WRITTEN != 0 {
+            RegionKind::Written
+        } else {
+            RegionKind::Unused
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RegionKind::Code => "code",
+            RegionKind::Sprite => "sprite",
+            RegionKind::Data => "data",
+            RegionKind::Written => "written",
+            RegionKind::Unused => "unused",
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Region {
+    pub start: u16,
+    pub end: u16,
+    pub kind: RegionKind,
+}
+
+/// Tags every memory byte with how it was used while it observes the machine: fetched as part of
+/// an opcode, read as sprite data by DXYN, read by FX65 or written.
+pub struct Coverage {
+    usage: Vec<u8>,
+    rom_start: u16,
+    rom_end: u16,
+    instructions: u64,
+    frames: u64,
+}
+
+impl Coverage {
+    pub fn new(chip8: &Chip8) -> Coverage {
+        let rom_start = chip8.config.program_address;
+        Coverage {
+            usage: vec![0; MEM_SIZE],
+            rom_start: rom_start,
+            rom_end: cmp::min(rom_start as usize + chip8.rom.len(), MEM_SIZE) as u16,
+            instructions: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// The combination of `EXECUTED`, `SPRITE`, `LOADED` and `WRITTEN` recorded for `address`.
+    pub fn usage(&self, address: u16) -> u8 {
+        self.usage.get(address as usize).map(|usage| usage & !INSTRUCTION_START).unwrap_or(0)
+    }
+
+    /// Consecutive bytes of the same kind. Covers the whole ROM, and the bytes outside of it that
+    /// were used.
+    pub fn regions(&self) -> Vec<Region> {
+        let mut regions: Vec<Region> = Vec::new();
+        for address in 0..MEM_SIZE as u16 {
+            let kind = RegionKind::from_usage(self.usage(address));
+            let in_rom = address >= self.rom_start && address < self.rom_end;
+            if kind == RegionKind::Unused && !in_rom {
+                continue;
+            }
+            match regions.last_mut() {
+                Some(ref mut region) if region.kind == kind && region.end + 1 == address => {
+                    region.end = address;
+                    continue;
+                },
+                _ => (),
+            }
+            regions.push(Region { start: address, end: address, kind: kind });
+        }
+        regions
+    }
+
+    /// The code/data map, one `start-end kind` line per region, e.g. `0x200-0x23F code`.
+    pub fn map(&self) -> String {
+        let mut map = String::new();
+        for region in self.regions() {
+            map.push_str(&format!("0x{:03X}-0x{:03X} {}\n", region.start, region.end, region.kind.name()));
+        }
+        map
+    }
+
+    /// A summary of the ROM followed by a listing of it. Executed instructions are disassembled,
+    /// other bytes are shown in hex. Each line starts with its usage, `X` for executed, `S` for
+    /// sprite, `L` for loaded and `W` for written.
+    pub fn report(&self, chip8: &Chip8) -> String {
+        let (start, end) = (self.rom_start as usize, self.rom_end as usize);
+        let count = |flag: u8| self.usage[start..end].iter().filter(|usage| *usage & flag != 0).count();
+        let total = end - start;
+        let executed = count(EXECUTED);
+        let untouched = self.usage[start..end].iter().filter(|usage| **usage == 0).count();
+        let percent = if total > 0 { executed as f64 * 100.0 / total as f64 } else { 0.0 };
+        let mut lines = vec![
+            format!("{} instructions in {} frames", self.instructions, self.frames),
+            format!("ROM 0x{:03X}-0x{:03X}: {} of {} bytes executed ({:.1}%), {} drawn as sprites, {} loaded, {} written, {} untouched",
+                start, end.saturating_sub(1), executed, total, percent, count(SPRITE), count(LOADED), count(WRITTEN), untouched),
+            String::new(),
+        ];
+
+        let memory = chip8.memory.as_slice();
+        let symbols = chip8.symbols();
+        let mut address = start;
+        while address < end {
+            let label = match symbols.symbol_at(address as u16) {
+                Some(symbol) => format!("<{}>", symbol.name),
+                None => String::new(),
+            };
+            if self.usage[address] & INSTRUCTION_START != 0 && address + 1 < MEM_SIZE {
+                let opcode = ((memory[address] as u16) << 8) + memory[address + 1] as u16;
+                let usage = self.usage(address as u16) | self.usage(address as u16 + 1);
+                lines.push(format!("{} 0x{:03X} {:<16} {}", usage_flags(usage), address, label, disassemble_with_symbols(opcode, symbols)));
+                address += 2;
+                continue;
+            }
+            let usage = self.usage(address as u16);
+            let mut line_end = address + 1;
+            while line_end < end && line_end - address < BYTES_PER_LINE && self.usage[line_end] == usage
+                && symbols.symbol_at(line_end as u16).is_none() {
+                line_end += 1;
+            }
+            let bytes: Vec<String> = memory[address..line_end].iter().map(|byte| format!("{:02X}", byte)).collect();
+            lines.push(format!("{} 0x{:03X} {:<16} {}", usage_flags(usage), address, label, bytes.join(" ")));
+            address = line_end;
+        }
+        lines.join("\n")
+    }
+}
+
+impl StepObserver for Coverage {
+    fn observe(&mut self, _: &Chip8, step: &Step) {
+        let pc = step.pc as usize;
+        let asm = Opcode::new(step.opcode).as_asm();
+        self.usage[pc] |= EXECUTED | INSTRUCTION_START;
+        self.usage[pc + 1] |= EXECUTED;
+        for access in step.accesses {
+            self.usage[access.address as usize] |= match (access.kind, asm) {
+                (AccessKind::Write, _) => WRITTEN,
+                (AccessKind::Read, ASM::DRW(_, _, _)) => SPRITE,
+                (AccessKind::Read, _) => LOADED,
+            };
+        }
+        self.instructions += 1;
+        if step.end_of_frame {
+            self.frames += 1;
+        }
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
+
+fn usage_flags(usage: u8) -> String {
+    [(EXECUTED, 'X'), (SPRITE, 'S'), (LOADED, 'L'), (WRITTEN, 'W')].iter()
+        .map(|&(flag, name)| if usage & flag != 0 { name } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Coverage, Region, RegionKind, EXECUTED, LOADED, SPRITE, WRITTEN};
+    use super::super::{create_test_chip8, Chip8, RunCondition};
+    use super::super::symbols::SymbolTable;
+
+    // LDI 0x210; DRW V0, V1, 2; LDI 0x212; READ V1; LDI 0x214; BCD V0; JMP 0x20C; an unused word,
+    // two sprite rows, two loaded bytes, three bytes written by BCD and one unused byte
+    fn create_chip8() -> Chip8 {
+        create_test_chip8(1, vec![0xA2, 0x10, 0xD0, 0x12, 0xA2, 0x12, 0xF1, 0x65, 0xA2, 0x14, 0xF0, 0x33, 0x12, 0x0C, 0x00, 0x00,
+                        0xFF, 0x81, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00])
+    }
+
+    fn run(chip8: &mut Chip8, instructions: u64) -> u32 {
+        let coverage = Coverage::new(chip8);
+        let id = chip8.observers_mut().add(Box::new(coverage));
+        chip8.run_until(&[RunCondition::Instructions(instructions)]);
+        id
+    }
+
+    #[test]
+    fn tags_bytes_by_use() {
+        let mut chip8 = create_chip8();
+
+        let id = run(&mut chip8, 8);
+        let under_test = chip8.observers().get::<Coverage>(id).unwrap();
+
+        assert!(under_test.instructions() == 8);
+        assert!(under_test.usage(0x20D) == EXECUTED);
+        assert!(under_test.usage(0x20E) == 0);
+        assert!(under_test.usage(0x211) == SPRITE);
+        assert!(under_test.usage(0x213) == LOADED);
+        assert!(under_test.usage(0x216) == WRITTEN);
+    }
+
+    #[test]
+    fn code_data_map() {
+        let mut chip8 = create_chip8();
+
+        let id = run(&mut chip8, 8);
+        let under_test = chip8.observers().get::<Coverage>(id).unwrap();
+
+        assert!(under_test.regions()[0] == Region { start: 0x200, end: 0x20D, kind: RegionKind::Code });
+        assert!(under_test.map() == "0x200-0x20D code\n0x20E-0x20F unused\n0x210-0x211 sprite\n0x212-0x213 data\n0x214-0x216 written\n0x217-0x217 unused\n");
+    }
+
+    #[test]
+    fn map_includes_used_bytes_outside_the_rom() {
+        // LDI 0x300; BCD V0; JMP 0x204
+        let mut chip8 = create_test_chip8(1, vec![0xA3, 0x00, 0xF0, 0x33, 0x12, 0x04]);
+
+        let id = run(&mut chip8, 3);
+        let under_test = chip8.observers().get::<Coverage>(id).unwrap();
+
+        assert!(under_test.map() == "0x200-0x205 code\n0x300-0x302 written\n");
+    }
+
+    #[test]
+    fn annotated_report() {
+        let mut chip8 = create_chip8();
+        *chip8.symbols_mut() = SymbolTable::parse("main = 0x200\nball = 0x210 data").unwrap();
+        let id = run(&mut chip8, 8);
+        let under_test = chip8.observers().get::<Coverage>(id).unwrap();
+
+        let result = under_test.report(&chip8);
+
+        let lines: Vec<&str> = result.lines().collect();
+        assert!(lines[1] == "ROM 0x200-0x217: 14 of 24 bytes executed (58.3%), 2 drawn as sprites, 2 loaded, 3 written, 3 untouched");
+        assert!(lines[3] == "X... 0x200 <main>           0xA210: LDI ball");
+        assert!(lines[10] == ".... 0x20E                  00 00");
+        assert!(lines[11] == ".S.. 0x210 <ball>           FF 81");
+        assert!(lines[12] == "..L. 0x212                  01 02");
+        assert!(lines[13] == "...W 0x214                  00 00 01");
+        assert!(lines.len() == 15);
+    }
+}
@@ -0,0 +1,915 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use emulator::Emulator;
+use emulator::json::Json;
+use emulator::scheduler::FRAMES_PER_SECOND;
+use super::{Chip8, Inspect, MEM_SIZE, REGISTER_COUNT};
+use super::breakpoint::BreakpointKind;
+use super::builder::parse_number;
+use super::control::{RunCondition, RunResult, StopReason};
+use super::reverse::{DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS};
+use super::sourcemap::SourceMap;
+use super::symbols::{symbolize_asm, SymbolTable};
+
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const TIMERS_REFERENCE: u64 = 2;
+const STACK_REFERENCE: u64 = 3;
+const STEP_FRAMES: u64 = 3600;
+const CONTENT_LENGTH: &'static str = "Content-Length:";
+const MAX_CONTENT_LENGTH: usize = 0x100000;
+const HISTORY_START: &'static str = "Reached the start of the recorded history";
+const BASE64: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Splits the bytes coming from the editor into Debug Adapter Protocol messages, each a JSON body
+/// after a `Content-Length` header. A body longer than `MAX_CONTENT_LENGTH` is an error and drops
+/// everything buffered, the stream cannot be split after it.
+#[derive(Clone, Debug, Default)]
+pub struct MessageDecoder {
+    buffer: Vec<u8>,
+}
+
+impl MessageDecoder {
+    pub fn new() -> MessageDecoder {
+        Default::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<Json, String>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut messages = Vec::new();
+        loop {
+            let header_end = match self.buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                Some(end) => end,
+                None => return messages,
+            };
+            let header = String::from_utf8_lossy(&self.buffer[..header_end]).into_owned();
+            let length = header.lines()
+                .filter(|line| line.starts_with(CONTENT_LENGTH))
+                .filter_map(|line| line[CONTENT_LENGTH.len()..].trim().parse::<usize>().ok())
+                .next();
+            let length = match length {
+                Some(length) => length,
+                None => {
+                    self.buffer.drain(..header_end + 4);
+                    messages.push(Err(format!("Missing {} in '{}'", CONTENT_LENGTH, header)));
+                    continue;
+                }
+            };
+            let body_end = match (header_end + 4).checked_add(length) {
+                Some(end) if length <= MAX_CONTENT_LENGTH => end,
+                _ => {
+                    self.buffer.clear();
+                    messages.push(Err(format!("{} {} is larger than {}", CONTENT_LENGTH, length, MAX_CONTENT_LENGTH)));
+                    return messages;
+                }
+            };
+            if self.buffer.len() < body_end {
+                return messages;
+            }
+            let body = String::from_utf8_lossy(&self.buffer[header_end + 4..body_end]).into_owned();
+            self.buffer.drain(..body_end);
+            messages.push(Json::parse(&body));
+        }
+    }
+}
+
+pub fn encode_message(message: &Json) -> Vec<u8> {
+    let body = format!("{}", message);
+    format!("{} {}\r\n\r\n{}", CONTENT_LENGTH, body.len(), body).into_bytes()
+}
+
+/// Answers Debug Adapter Protocol requests for a `Chip8`, so editors like VS Code can debug ROMs.
+/// There is one thread, the machine. Breakpoints set by the editor are added to the machine's own
+/// list, source line breakpoints need a source map.
+pub struct DapServer {
+    chip8: Chip8,
+    source_map: SourceMap,
+    source_root: String,
+    breakpoints: Vec<(String, u32)>,
+    seq: u64,
+    running: bool,
+    first: bool,
+    stop_on_entry: bool,
+    terminated: bool,
+}
+
+impl DapServer {
+    pub fn new(mut chip8: Chip8) -> DapServer {
+        if chip8.history().is_none() {
+            chip8.enable_history(DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS);
+        }
+        DapServer {
+            chip8: chip8,
+            source_map: SourceMap::new(),
+            source_root: String::new(),
+            breakpoints: Vec::new(),
+            seq: 0,
+            running: false,
+            first: false,
+            stop_on_entry: false,
+            terminated: false,
+        }
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    pub fn source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.source_map
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Answers one request. Returns the response followed by the events it caused.
+    pub fn handle(&mut self, request: &Json) -> Vec<Json> {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let request_seq = request.get("seq").and_then(Json::as_u64).unwrap_or(0);
+        let arguments = request.get("arguments").cloned().unwrap_or(Json::Object(Vec::new()));
+        let mut events = Vec::new();
+        let result = match command {
+            "initialize" => {
+                events.push(("initialized", Json::Null));
+                Ok(capabilities())
+            },
+            "launch" => self.launch(&arguments),
+            "setBreakpoints" => self.set_source_breakpoints(&arguments),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(&arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(&arguments),
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    events.push(("stopped", stopped_body("entry", None, None)));
+                } else {
+                    self.start();
+                }
+                Ok(Json::Null)
+            },
+            "threads" => Ok(object(vec![("threads", Json::Array(vec![object(vec![("id", number(THREAD_ID)), ("name", string("CHIP-8"))])]))])),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(scopes()),
+            "variables" => self.variables(&arguments),
+            "readMemory" => self.read_memory(&arguments),
+            "disassemble" => self.disassemble(&arguments),
+            "continue" => {
+                self.start();
+                Ok(object(vec![("allThreadsContinued", Json::Bool(true))]))
+            },
+            "pause" => {
+                if self.running {
+                    self.running = false;
+                    events.push(("stopped", stopped_body("pause", None, None)));
+                }
+                Ok(Json::Null)
+            },
+            "next" | "stepIn" | "stepOut" | "stepBack" | "reverseContinue" if self.running => Err(String::from("The program is running")),
+            "next" => self.step_over().map(|result| {
+                events.push(("stopped", self.stop_body(&result)));
+                Json::Null
+            }),
+            "stepIn" => {
+                let result = self.chip8.run_until(&[RunCondition::Instructions(1)]);
+                events.push(("stopped", self.stop_body(&result)));
+                Ok(Json::Null)
+            },
+            "stepOut" => self.step_out().map(|result| {
+                events.push(("stopped", self.stop_body(&result)));
+                Json::Null
+            }),
+            "stepBack" => {
+                let description = if self.chip8.step_back() { None } else { Some(HISTORY_START) };
+                events.push(("stopped", stopped_body("step", description, None)));
+                Ok(Json::Null)
+            },
+            "reverseContinue" => {
+                let body = match self.chip8.reverse_continue() {
+                    Some(reason) => self.stop_body(&RunResult { reason: reason, instructions: 0, frames: 0 }),
+                    None => stopped_body("step", Some(HISTORY_START), None),
+                };
+                events.push(("stopped", body));
+                Ok(Json::Null)
+            },
+            "disconnect" | "terminate" => {
+                self.running = false;
+                self.terminated = true;
+                events.push(("terminated", Json::Null));
+                Ok(Json::Null)
+            },
+            _ => Err(format!("Unsupported request '{}'", command)),
+        };
+
+        let mut messages = vec![self.response(request_seq, command, result)];
+        for (event, body) in events {
+            messages.push(self.event(event, body));
+        }
+        messages
+    }
+
+    /// Runs one frame while the program is running. Returns the stopped event once it stops.
+    pub fn resume(&mut self) -> Option<Json> {
+        if !self.running {
+            return None;
+        }
+        let first = self.first;
+        self.first = false;
+        if !first {
+            if let Some(id) = self.chip8.check_breakpoints() {
+                self.running = false;
+                let body = self.stop_body(&RunResult { reason: StopReason::BreakpointHit(id), instructions: 0, frames: 0 });
+                return Some(self.event("stopped", body));
+            }
+        }
+        let result = self.chip8.run_until(&[RunCondition::Frames(1), RunCondition::Halt]);
+        match result.reason {
+            StopReason::Frames => None,
+            _ => {
+                self.running = false;
+                let body = self.stop_body(&result);
+                Some(self.event("stopped", body))
+            },
+        }
+    }
+
+    /// Serves one session until the editor disconnects or closes the connection. Requests are
+    /// read on a separate thread so a running program can be paused.
+    pub fn serve<R: Read + Send + 'static, W: Write>(&mut self, mut reader: R, mut writer: W) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut decoder = MessageDecoder::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let count = match reader.read(&mut buffer) {
+                    Ok(0) | Err(_) => return,
+                    Ok(count) => count,
+                };
+                for message in decoder.push(&buffer[..count]) {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        let frame = Duration::new(0, 1_000_000_000 / FRAMES_PER_SECOND);
+        while !self.terminated {
+            let message = if self.running {
+                match receiver.try_recv() {
+                    Ok(message) => Some(message),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match receiver.recv() {
+                    Ok(message) => Some(message),
+                    Err(_) => return Ok(()),
+                }
+            };
+            let replies = match message {
+                Some(Ok(request)) => {
+                    debug!("dap: {}", request);
+                    self.handle(&request)
+                },
+                Some(Err(error)) => {
+                    warn!("dap: invalid message: {}", error);
+                    Vec::new()
+                },
+                None => {
+                    let stopped = self.resume();
+                    if stopped.is_none() {
+                        thread::sleep(frame);
+                    }
+                    stopped.into_iter().collect()
+                },
+            };
+            for reply in replies {
+                writer.write_all(&encode_message(&reply))?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn start(&mut self) {
+        self.running = true;
+        self.first = true;
+    }
+
+    fn response(&mut self, request_seq: u64, command: &str, result: Result<Json, String>) -> Json {
+        self.seq += 1;
+        let mut members = vec![
+            (String::from("seq"), number(self.seq)),
+            (String::from("type"), string("response")),
+            (String::from("request_seq"), number(request_seq)),
+            (String::from("success"), Json::Bool(result.is_ok())),
+            (String::from("command"), string(command)),
+        ];
+        match result {
+            Ok(Json::Null) => (),
+            Ok(body) => members.push((String::from("body"), body)),
+            Err(message) => members.push((String::from("message"), Json::String(message))),
+        }
+        Json::Object(members)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> Json {
+        self.seq += 1;
+        let mut members = vec![
+            (String::from("seq"), number(self.seq)),
+            (String::from("type"), string("event")),
+            (String::from("event"), string(event)),
+        ];
+        if body != Json::Null {
+            members.push((String::from("body"), body));
+        }
+        Json::Object(members)
+    }
+
+    fn launch(&mut self, arguments: &Json) -> Result<Json, String> {
+        if let Some(program) = arguments.get("program").and_then(Json::as_str) {
+            let mut rom = Vec::new();
+            File::open(program)
+                .and_then(|mut file| file.read_to_end(&mut rom))
+                .map_err(|error| format!("Could not read {}: {}", program, error))?;
+            self.chip8.load(rom);
+            self.chip8.restart_history();
+        }
+        if let Some(filename) = arguments.get("symbols").and_then(Json::as_str) {
+            *self.chip8.symbols_mut() = SymbolTable::from_file(filename)?;
+        }
+        if let Some(filename) = arguments.get("sourceMap").and_then(Json::as_str) {
+            let text = read_text(filename)?;
+            self.source_map = SourceMap::parse(&text).map_err(|error| format!("{}: {}", filename, error))?;
+            self.source_root = Path::new(filename).parent().map(|parent| parent.to_string_lossy().into_owned()).unwrap_or_default();
+        }
+        self.stop_on_entry = arguments.get("stopOnEntry").and_then(Json::as_bool).unwrap_or(false);
+        Ok(Json::Null)
+    }
+
+    fn set_source_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        let path = arguments.get("source").and_then(|source| source.get("path")).and_then(Json::as_str).ok_or("Missing source path")?;
+        let lines: Vec<u64> = arguments.get("breakpoints").and_then(Json::as_array).unwrap_or(&[]).iter()
+            .filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_u64))
+            .collect();
+        let group = format!("source:{}", path);
+        self.clear_breakpoints(&group);
+        let mut breakpoints = Vec::new();
+        for line in lines {
+            let breakpoint = match self.source_map.resolve(path, line as u32) {
+                Some((line, address)) => {
+                    let mut breakpoint = self.add_breakpoint(&group, address);
+                    breakpoint.push((String::from("line"), number(line as u64)));
+                    Json::Object(breakpoint)
+                },
+                None => unverified(if self.source_map.is_empty() { "No source map" } else { "No code at this line" }),
+            };
+            breakpoints.push(breakpoint);
+        }
+        Ok(object(vec![("breakpoints", Json::Array(breakpoints))]))
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        self.clear_breakpoints("function");
+        let mut breakpoints = Vec::new();
+        for breakpoint in arguments.get("breakpoints").and_then(Json::as_array).unwrap_or(&[]) {
+            let name = breakpoint.get("name").and_then(Json::as_str).unwrap_or("");
+            let breakpoint = match self.chip8.symbols().resolve(name) {
+                Ok(address) => Json::Object(self.add_breakpoint("function", address)),
+                Err(error) => unverified(&error),
+            };
+            breakpoints.push(breakpoint);
+        }
+        Ok(object(vec![("breakpoints", Json::Array(breakpoints))]))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        self.clear_breakpoints("instruction");
+        let mut breakpoints = Vec::new();
+        for breakpoint in arguments.get("breakpoints").and_then(Json::as_array).unwrap_or(&[]) {
+            let reference = breakpoint.get("instructionReference").and_then(Json::as_str).unwrap_or("");
+            let offset = breakpoint.get("offset").and_then(Json::as_i64).unwrap_or(0);
+            let breakpoint = match memory_address(reference, offset) {
+                Ok(address) => Json::Object(self.add_breakpoint("instruction", address)),
+                Err(error) => unverified(&error),
+            };
+            breakpoints.push(breakpoint);
+        }
+        Ok(object(vec![("breakpoints", Json::Array(breakpoints))]))
+    }
+
+    fn clear_breakpoints(&mut self, group: &str) {
+        let removed: Vec<u32> = self.breakpoints.iter().filter(|breakpoint| breakpoint.0 == group).map(|breakpoint| breakpoint.1).collect();
+        self.breakpoints.retain(|breakpoint| breakpoint.0 != group);
+        for id in removed {
+            self.chip8.breakpoints_mut().remove(id);
+        }
+    }
+
+    fn add_breakpoint(&mut self, group: &str, address: u16) -> Vec<(String, Json)> {
+        let id = self.chip8.breakpoints_mut().add(BreakpointKind::Address(address));
+        self.breakpoints.push((String::from(group), id));
+        vec![
+            (String::from("id"), number(id as u64)),
+            (String::from("verified"), Json::Bool(true)),
+            (String::from("instructionReference"), Json::String(format!("0x{:03X}", address))),
+        ]
+    }
+
+    fn step_over(&mut self) -> Result<RunResult, String> {
+        let pc = self.chip8.get_program_counter();
+        if self.chip8.read_memory(pc, 1).first().map(|byte| byte >> 4) != Some(0x2) {
+            return Ok(self.chip8.run_until(&[RunCondition::Instructions(1)]));
+        }
+        let depth = self.chip8.get_stack().len();
+        Ok(self.run_to_return(pc + 2, depth))
+    }
+
+    fn step_out(&mut self) -> Result<RunResult, String> {
+        let depth = self.chip8.get_stack().len();
+        let address = *self.chip8.get_stack().last().ok_or("Not in a subroutine")?;
+        Ok(self.run_to_return(address, depth - 1))
+    }
+
+    fn run_to_return(&mut self, address: u16, depth: usize) -> RunResult {
+        loop {
+            let result = self.chip8.run_until(&[RunCondition::ProgramCounter(address), RunCondition::Frames(STEP_FRAMES), RunCondition::KeyWait, RunCondition::Halt]);
+            if result.reason != StopReason::ProgramCounter(address) || self.chip8.get_stack().len() == depth {
+                return result;
+            }
+        }
+    }
+
+    fn stop_body(&self, result: &RunResult) -> Json {
+        match result.reason {
+            StopReason::BreakpointHit(id) => stopped_body("breakpoint", None, Some(id)),
+            StopReason::WatchpointHit(hit) => stopped_body("data breakpoint", Some(&format!("Watchpoint {} at 0x{:03X}", hit.id, hit.access.address)), None),
+            StopReason::KeyWait => stopped_body("pause", Some("Waiting for a key press"), None),
+            StopReason::Halt => stopped_body("pause", Some("Halted"), None),
+            _ => stopped_body("step", None, None),
+        }
+    }
+
+    /// The current instruction, then the CALL of every subroutine on the stack, innermost first.
+    fn stack_trace(&self) -> Json {
+        let mut addresses = vec![self.chip8.get_program_counter()];
+        addresses.extend(self.chip8.get_stack().iter().rev().map(|address| address.wrapping_sub(2)));
+        let frames: Vec<Json> = addresses.iter().enumerate().map(|(index, address)| {
+            let name = self.chip8.symbols().describe(*address).unwrap_or_else(|| format!("0x{:03X}", address));
+            let mut frame = vec![
+                (String::from("id"), number(index as u64)),
+                (String::from("name"), Json::String(name)),
+                (String::from("instructionPointerReference"), Json::String(format!("0x{:03X}", address))),
+                (String::from("column"), number(0)),
+            ];
+            match self.source_map.location(*address) {
+                Some(location) => {
+                    frame.push((String::from("line"), number(location.line as u64)));
+                    frame.push((String::from("source"), self.source(&location.file)));
+                },
+                None => frame.push((String::from("line"), number(0))),
+            }
+            Json::Object(frame)
+        }).collect();
+        let total = frames.len() as u64;
+        object(vec![("stackFrames", Json::Array(frames)), ("totalFrames", number(total))])
+    }
+
+    fn source(&self, file: &str) -> Json {
+        let path = Path::new(&self.source_root).join(file);
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| String::from(file));
+        object(vec![("name", Json::String(name)), ("path", Json::String(path.to_string_lossy().into_owned()))])
+    }
+
+    fn variables(&self, arguments: &Json) -> Result<Json, String> {
+        let chip8 = &self.chip8;
+        let variables = match arguments.get("variablesReference").and_then(Json::as_u64) {
+            Some(REGISTERS_REFERENCE) => {
+                let mut variables: Vec<Json> = (0..REGISTER_COUNT as u8)
+                    .map(|register| variable(&format!("V{:X}", register), format!("0x{:02X}", chip8.get_data_register(register)), None))
+                    .collect();
+                let i = chip8.get_address_register();
+                variables.push(variable("I", format!("0x{:03X}", i), Some(i)));
+                variables.push(variable("PC", format!("0x{:03X}", chip8.get_program_counter()), Some(chip8.get_program_counter())));
+                variables
+            },
+            Some(TIMERS_REFERENCE) => vec![
+                variable("DT", format!("{}", chip8.get_delay_timer()), None),
+                variable("ST", format!("{}", chip8.get_sound_timer()), None),
+            ],
+            Some(STACK_REFERENCE) => chip8.get_stack().iter().rev().enumerate()
+                .map(|(index, address)| {
+                    let value = match chip8.symbols().describe(*address) {
+                        Some(name) => format!("0x{:03X} <{}>", address, name),
+                        None => format!("0x{:03X}", address),
+                    };
+                    variable(&format!("#{}", index), value, Some(*address))
+                })
+                .collect(),
+            _ => return Err(String::from("Unknown variables reference")),
+        };
+        Ok(object(vec![("variables", Json::Array(variables))]))
+    }
+
+    fn read_memory(&self, arguments: &Json) -> Result<Json, String> {
+        let reference = arguments.get("memoryReference").and_then(Json::as_str).ok_or("Missing memory reference")?;
+        let address = memory_address(reference, arguments.get("offset").and_then(Json::as_i64).unwrap_or(0))?;
+        let count = arguments.get("count").and_then(Json::as_u64).unwrap_or(0) as usize;
+        let bytes = self.chip8.read_memory(address, count);
+        Ok(object(vec![
+            ("address", Json::String(format!("0x{:03X}", address))),
+            ("data", Json::String(base64(bytes))),
+            ("unreadableBytes", number((count - bytes.len()) as u64)),
+        ]))
+    }
+
+    fn disassemble(&self, arguments: &Json) -> Result<Json, String> {
+        let reference = arguments.get("memoryReference").and_then(Json::as_str).ok_or("Missing memory reference")?;
+        let offset = arguments.get("offset").and_then(Json::as_i64).unwrap_or(0)
+            + arguments.get("instructionOffset").and_then(Json::as_i64).unwrap_or(0) * 2;
+        let start = parse_number(reference)? as i64 + offset;
+        let count = arguments.get("instructionCount").and_then(Json::as_u64).unwrap_or(0) as i64;
+        let symbols = self.chip8.symbols();
+        let instructions: Vec<Json> = (0..count).map(|index| {
+            let address = start + index * 2;
+            if address < 0 || address as usize + 1 >= MEM_SIZE {
+                return object(vec![("address", Json::String(format!("0x{:03X}", address.max(0)))), ("instruction", string("")), ("presentationHint", string("invalid"))]);
+            }
+            let address = address as u16;
+            let bytes = self.chip8.read_memory(address, 2);
+            let opcode = ((bytes[0] as u16) << 8) + bytes[1] as u16;
+            let mut instruction = vec![
+                (String::from("address"), Json::String(format!("0x{:03X}", address))),
+                (String::from("instructionBytes"), Json::String(format!("{:02X} {:02X}", bytes[0], bytes[1]))),
+                (String::from("instruction"), Json::String(symbolize_asm(opcode, symbols))),
+            ];
+            if let Some(symbol) = symbols.symbol_at(address) {
+                instruction.push((String::from("symbol"), Json::String(symbol.name.clone())));
+            }
+            if let Some(location) = self.source_map.location(address) {
+                instruction.push((String::from("location"), self.source(&location.file)));
+                instruction.push((String::from("line"), number(location.line as u64)));
+            }
+            Json::Object(instruction)
+        }).collect();
+        Ok(object(vec![("instructions", Json::Array(instructions))]))
+    }
+}
+
+fn capabilities() -> Json {
+    let supported = [
+        "supportsConfigurationDoneRequest",
+        "supportsFunctionBreakpoints",
+        "supportsInstructionBreakpoints",
+        "supportsReadMemoryRequest",
+        "supportsDisassembleRequest",
+        "supportsStepBack",
+        "supportsTerminateRequest",
+    ];
+    Json::Object(supported.iter().map(|capability| (String::from(*capability), Json::Bool(true))).collect())
+}
+
+fn scopes() -> Json {
+    let scopes = [("Registers", REGISTERS_REFERENCE), ("Timers", TIMERS_REFERENCE), ("Stack", STACK_REFERENCE)].iter()
+        .map(|&(name, reference)| object(vec![("name", string(name)), ("variablesReference", number(reference)), ("expensive", Json::Bool(false))]))
+        .collect();
+    object(vec![("scopes", Json::Array(scopes))])
+}
+
+fn stopped_body(reason: &str, description: Option<&str>, breakpoint: Option<u32>) -> Json {
+    let mut body = vec![("reason", string(reason))];
+    if let Some(description) = description {
+        body.push(("description", string(description)));
+    }
+    body.push(("threadId", number(THREAD_ID)));
+    body.push(("allThreadsStopped", Json::Bool(true)));
+    if let Some(id) = breakpoint {
+        body.push(("hitBreakpointIds", Json::Array(vec![number(id as u64)])));
+    }
+    object(body)
+}
+
+fn unverified(message: &str) -> Json {
+    object(vec![("verified", Json::Bool(false)), ("message", string(message))])
+}
+
+fn variable(name: &str, value: String, memory_reference: Option<u16>) -> Json {
+    let mut members = vec![("name", string(name)), ("value", Json::String(value)), ("variablesReference", number(0))];
+    if let Some(address) = memory_reference {
+        members.push(("memoryReference", Json::String(format!("0x{:03X}", address))));
+    }
+    object(members)
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+}
+
+fn string(value: &str) -> Json {
+    Json::String(String::from(value))
+}
+
+fn number(value: u64) -> Json {
+    Json::Number(value as f64)
+}
+
+fn memory_address(reference: &str, offset: i64) -> Result<u16, String> {
+    let address = parse_number(reference)? as i64 + offset;
+    if address < 0 || address as usize >= MEM_SIZE {
+        return Err(format!("Address {} is out of range", address));
+    }
+    Ok(address as u16)
+}
+
+fn read_text(filename: &str) -> Result<String, String> {
+    let mut text = String::new();
+    File::open(filename)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|error| format!("Could not read {}: {}", filename, error))?;
+    Ok(text)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let value = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[(value >> (18 - index * 6)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::io::{Cursor, Write};
+
+    use super::{base64, encode_message, DapServer, MessageDecoder};
+    use super::super::{create_test_chip8, Inspect};
+    use super::super::sourcemap::SourceMap;
+    use emulator::json::Json;
+
+    // 0x200: LD V0, 5; CALL 0x208; LD V1, 1; JMP 0x206
+    // 0x208: LDI 0x300; BCD V0; RET
+    const ROM: [u8; 14] = [0x60, 0x05, 0x22, 0x08, 0x61, 0x01, 0x12, 0x06, 0xA3, 0x00, 0xF0, 0x33, 0x00, 0xEE];
+    const SOURCE_MAP: &'static str = "0x200 game.8o:1\n0x202 game.8o:2\n0x204 game.8o:3\n0x206 game.8o:4\n0x208 game.8o:7\n0x20A game.8o:8\n0x20C game.8o:9\n";
+
+    fn create_server() -> DapServer {
+        let mut server = DapServer::new(create_test_chip8(4, ROM.to_vec()));
+        *server.source_map_mut() = SourceMap::parse(SOURCE_MAP).unwrap();
+        server
+    }
+
+    /// Sends each request and returns everything the server answered, in order.
+    fn session(server: &mut DapServer, requests: &[&str]) -> Vec<Json> {
+        let mut messages = Vec::new();
+        for (index, request) in requests.iter().enumerate() {
+            let request = Json::parse(&format!(r#"{{"seq":{},"type":"request",{}}}"#, index + 1, request)).unwrap();
+            messages.extend(server.handle(&request));
+        }
+        messages
+    }
+
+    fn run_until_stopped(server: &mut DapServer) -> Json {
+        for _ in 0..100 {
+            if let Some(event) = server.resume() {
+                return event;
+            }
+        }
+        panic!("the program did not stop");
+    }
+
+    fn text(message: &Json, path: &[&str]) -> String {
+        let mut value = message;
+        for key in path {
+            value = match key.parse::<usize>() {
+                Ok(index) => &value.as_array().unwrap()[index],
+                Err(_) => value.get(key).unwrap(),
+            };
+        }
+        format!("{}", value)
+    }
+
+    #[test]
+    fn decode_messages_split_across_reads() {
+        let mut under_test = MessageDecoder::new();
+        let encoded = encode_message(&Json::parse(r#"{"seq":1,"command":"threads"}"#).unwrap());
+
+        let first = under_test.push(&encoded[..10]);
+        let second = under_test.push(&[&encoded[10..], &b"Content-Length: 2\r\n\r\n{}Content-Length: 1"[..]].concat());
+        let third = under_test.push(b"\r\n\r\n[");
+
+        assert!(&encoded[..21] == b"Content-Length: 29\r\n\r");
+        assert!(first.is_empty());
+        assert!(second.len() == 2);
+        assert!(second[0].as_ref().unwrap().get("command").and_then(Json::as_str) == Some("threads"));
+        assert!(second[1] == Ok(Json::Object(Vec::new())));
+        assert!(third.len() == 1 && third[0].is_err());
+    }
+
+    #[test]
+    fn oversized_content_length_rejected() {
+        let mut under_test = MessageDecoder::new();
+
+        let first = under_test.push(b"Content-Length: 18446744073709551615\r\n\r\n{}");
+        let second = under_test.push(b"Content-Length: 2\r\n\r\n{}");
+
+        assert!(first.len() == 1 && first[0].is_err());
+        assert!(second == vec![Ok(Json::Object(Vec::new()))]);
+    }
+
+    #[test]
+    fn initialize_and_configure() {
+        let mut under_test = create_server();
+
+        let result = session(&mut under_test, &[
+            r#""command":"initialize","arguments":{"adapterID":"rust8"}"#,
+            r#""command":"launch","arguments":{"stopOnEntry":true}"#,
+            r#""command":"configurationDone""#,
+            r#""command":"threads""#,
+        ]);
+
+        assert!(result.len() == 6);
+        assert!(text(&result[0], &["body", "supportsStepBack"]) == "true");
+        assert!(text(&result[1], &["event"]) == "\"initialized\"");
+        assert!(text(&result[3], &["type"]) == "\"response\"" && text(&result[3], &["request_seq"]) == "3");
+        assert!(text(&result[4], &["body"]) == r#"{"reason":"entry","threadId":1,"allThreadsStopped":true}"#);
+        assert!(text(&result[5], &["body", "threads"]) == r#"[{"id":1,"name":"CHIP-8"}]"#);
+        assert!(!under_test.is_running());
+    }
+
+    #[test]
+    fn source_line_breakpoint_and_stack_trace() {
+        let mut under_test = create_server();
+
+        let result = session(&mut under_test, &[
+            r#""command":"setBreakpoints","arguments":{"source":{"path":"/work/game.8o"},"breakpoints":[{"line":8},{"line":5},{"line":20}]}"#,
+            r#""command":"configurationDone""#,
+        ]);
+        let stopped = run_until_stopped(&mut under_test);
+        let trace = session(&mut under_test, &[r#""command":"stackTrace","arguments":{"threadId":1}"#]);
+
+        let breakpoints = text(&result[0], &["body", "breakpoints"]);
+        assert!(breakpoints == r#"[{"id":1,"verified":true,"instructionReference":"0x20A","line":8},{"id":2,"verified":true,"instructionReference":"0x208","line":7},{"verified":false,"message":"No code at this line"}]"#);
+        assert!(text(&stopped, &["body"]) == r#"{"reason":"breakpoint","threadId":1,"allThreadsStopped":true,"hitBreakpointIds":[2]}"#);
+        assert!(under_test.chip8().get_program_counter() == 0x208);
+        assert!(text(&trace[0], &["body", "stackFrames", "0", "line"]) == "7");
+        assert!(text(&trace[0], &["body", "stackFrames", "1"]) == r#"{"id":1,"name":"0x202","instructionPointerReference":"0x202","column":0,"line":2,"source":{"name":"game.8o","path":"game.8o"}}"#);
+        assert!(text(&trace[0], &["body", "totalFrames"]) == "2");
+    }
+
+    #[test]
+    fn function_and_instruction_breakpoints() {
+        let mut under_test = create_server();
+        under_test.chip8_mut().symbols_mut().add("update", 0x208, None);
+
+        let result = session(&mut under_test, &[
+            r#""command":"setFunctionBreakpoints","arguments":{"breakpoints":[{"name":"update"},{"name":"missing"}]}"#,
+            r#""command":"setInstructionBreakpoints","arguments":{"breakpoints":[{"instructionReference":"0x200","offset":4}]}"#,
+            r#""command":"setFunctionBreakpoints","arguments":{"breakpoints":[]}"#,
+            r#""command":"continue","arguments":{"threadId":1}"#,
+        ]);
+        let stopped = run_until_stopped(&mut under_test);
+
+        assert!(text(&result[0], &["body", "breakpoints", "0", "verified"]) == "true");
+        assert!(text(&result[0], &["body", "breakpoints", "1", "verified"]) == "false");
+        assert!(text(&result[1], &["body", "breakpoints", "0", "instructionReference"]) == "\"0x204\"");
+        assert!(under_test.chip8().breakpoints().list().len() == 1);
+        assert!(text(&stopped, &["body", "hitBreakpointIds"]) == "[2]");
+        assert!(under_test.chip8().get_program_counter() == 0x204);
+    }
+
+    #[test]
+    fn stepping_forwards_and_backwards() {
+        let mut under_test = create_server();
+        let step = |server: &mut DapServer, command: &str| session(server, &[&format!(r#""command":"{}","arguments":{{"threadId":1}}"#, command)]);
+
+        step(&mut under_test, "next");
+        step(&mut under_test, "stepIn");
+        let into = under_test.chip8().get_program_counter();
+        let out = step(&mut under_test, "stepOut");
+        let returned = under_test.chip8().get_program_counter();
+        let failed = step(&mut under_test, "stepOut");
+        step(&mut under_test, "stepBack");
+        let back = under_test.chip8().get_program_counter();
+        let start = step(&mut under_test, "reverseContinue");
+        let at_start = under_test.chip8().get_program_counter();
+        step(&mut under_test, "next");
+        step(&mut under_test, "next");
+
+        assert!(into == 0x208);
+        assert!(text(&out[1], &["body", "reason"]) == "\"step\"");
+        assert!(returned == 0x204);
+        assert!(text(&failed[0], &["success"]) == "false" && text(&failed[0], &["message"]) == "\"Not in a subroutine\"");
+        assert!(back == 0x20C);
+        assert!(text(&start[1], &["body", "description"]) == "\"Reached the start of the recorded history\"");
+        assert!(at_start == 0x200);
+        assert!(under_test.chip8().get_program_counter() == 0x204);
+        assert!(under_test.chip8().get_stack().is_empty());
+    }
+
+    #[test]
+    fn variables_memory_and_disassembly() {
+        let mut under_test = create_server();
+        under_test.chip8_mut().symbols_mut().add("update", 0x208, None);
+        session(&mut under_test, &[r#""command":"stepIn""#, r#""command":"stepIn""#, r#""command":"stepIn""#]);
+
+        let result = session(&mut under_test, &[
+            r#""command":"scopes","arguments":{"frameId":0}"#,
+            r#""command":"variables","arguments":{"variablesReference":1}"#,
+            r#""command":"variables","arguments":{"variablesReference":3}"#,
+            r#""command":"readMemory","arguments":{"memoryReference":"0x200","offset":2,"count":5}"#,
+            r#""command":"readMemory","arguments":{"memoryReference":"0xFFE","count":4}"#,
+            r#""command":"disassemble","arguments":{"memoryReference":"0x208","instructionOffset":-1,"instructionCount":2}"#,
+        ]);
+
+        assert!(text(&result[0], &["body", "scopes", "2", "name"]) == "\"Stack\"");
+        assert!(text(&result[1], &["body", "variables", "0"]) == r#"{"name":"V0","value":"0x05","variablesReference":0}"#);
+        assert!(text(&result[1], &["body", "variables", "16"]) == r#"{"name":"I","value":"0x300","variablesReference":0,"memoryReference":"0x300"}"#);
+        assert!(text(&result[2], &["body", "variables"]) == r##"[{"name":"#0","value":"0x204","variablesReference":0,"memoryReference":"0x204"}]"##);
+        assert!(text(&result[3], &["body"]) == r#"{"address":"0x202","data":"IghhARI=","unreadableBytes":0}"#);
+        assert!(text(&result[4], &["body", "unreadableBytes"]) == "2");
+        assert!(text(&result[5], &["body", "instructions", "0", "instruction"]) == "\"JMP(518)\"");
+        assert!(text(&result[5], &["body", "instructions", "1"]) == r#"{"address":"0x208","instructionBytes":"A3 00","instruction":"LDI(768)","symbol":"update","location":{"name":"game.8o","path":"game.8o"},"line":7}"#);
+    }
+
+    #[test]
+    fn launch_loads_rom_symbols_and_source_map() {
+        let directory = env::temp_dir().join(format!("rust8-dap-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&directory).unwrap();
+        File::create(directory.join("game.ch8")).unwrap().write_all(&ROM).unwrap();
+        File::create(directory.join("game.sym")).unwrap().write_all(b"update = 0x208\n").unwrap();
+        File::create(directory.join("game.map")).unwrap().write_all(SOURCE_MAP.as_bytes()).unwrap();
+        let mut under_test = DapServer::new(create_test_chip8(1, Vec::new()));
+        let arguments = format!(r#"{{"program":"{0}/game.ch8","symbols":"{0}/game.sym","sourceMap":"{0}/game.map"}}"#, directory.to_string_lossy());
+
+        let launched = session(&mut under_test, &[&format!(r#""command":"launch","arguments":{}"#, arguments)]);
+        let missing = session(&mut under_test, &[r#""command":"launch","arguments":{"program":"/nonexistent/game.ch8"}"#]);
+        let trace = session(&mut under_test, &[r#""command":"next""#, r#""command":"stepIn""#, r#""command":"stackTrace""#]);
+        ::std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(text(&launched[0], &["success"]) == "true");
+        assert!(text(&missing[0], &["success"]) == "false");
+        assert!(text(&trace[4], &["body", "stackFrames", "0", "name"]) == "\"update\"");
+        let path = text(&trace[4], &["body", "stackFrames", "0", "source", "path"]);
+        assert!(path == format!("{}", Json::String(directory.join("game.8o").to_string_lossy().into_owned())));
+    }
+
+    #[test]
+    fn pause_and_disconnect() {
+        let mut under_test = create_server();
+
+        let result = session(&mut under_test, &[r#""command":"continue""#, r#""command":"next""#, r#""command":"pause""#, r#""command":"disconnect""#]);
+
+        assert!(text(&result[1], &["success"]) == "false");
+        assert!(text(&result[3], &["body", "reason"]) == "\"pause\"");
+        assert!(text(&result[5], &["event"]) == "\"terminated\"");
+        assert!(under_test.is_terminated());
+    }
+
+    #[test]
+    fn serve_scripted_session() {
+        let mut input = Vec::new();
+        for request in &[r#"{"seq":1,"type":"request","command":"initialize"}"#, "{broken", r#"{"seq":2,"type":"request","command":"disconnect"}"#] {
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", request.len(), request).into_bytes());
+        }
+        let mut output = Vec::new();
+        let mut under_test = create_server();
+
+        under_test.serve(Cursor::new(input), &mut output).unwrap();
+
+        let mut decoder = MessageDecoder::new();
+        let messages: Vec<Json> = decoder.push(&output).into_iter().map(Result::unwrap).collect();
+        assert!(messages.len() == 4);
+        assert!(text(&messages[1], &["event"]) == "\"initialized\"");
+        assert!(text(&messages[2], &["command"]) == "\"disconnect\"");
+        assert!(text(&messages[3], &["event"]) == "\"terminated\"");
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert!(base64(b"").is_empty());
+        assert!(base64(b"M") == "TQ==");
+        assert!(base64(b"Ma") == "TWE=");
+        assert!(base64(b"Man") == "TWFu");
+    }
+}
@@ -0,0 +1,692 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use emulator::Emulator;
+use emulator::keypad::HexKey;
+use super::{disassemble_with_symbols, Chip8, Inspect, MEM_SIZE, REGISTER_COUNT};
+use super::breakpoint::{BreakpointKind, Condition, OpcodePattern};
+use super::builder::parse_number;
+use super::control::{RunCondition, RunResult, StopReason};
+use super::draws::{calls_at, DrawCall};
+use super::reverse::{DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS};
+use super::sprites::{render_sheet, sprite_text, SpriteFormat};
+use super::symbols::SymbolTable;
+use super::component::memory::AccessKind;
+use super::watchpoint::WatchKind;
+
+const DEFAULT_CONTINUE_FRAMES: u64 = 3600;
+const DEFAULT_DUMP_LENGTH: usize = 64;
+const DEFAULT_DISASSEMBLY_LINES: u16 = 9;
+const DEFAULT_SPRITE_ROWS: usize = 8;
+const SPRITE_COLUMNS: usize = 16;
+const SPRITE_SCALE: usize = 4;
+const HISTORY_START: &'static str = "Reached the start of the recorded history";
+
+const HELP: &'static str = "\
+step [n]                      execute n instructions (s)
+next                          step over a CALL (n)
+finish                        run until the current subroutine returns
+continue [frames]             run until a breakpoint, watchpoint, key wait or halt (c)
+reverse-step [n]              undo n instructions (rs)
+reverse-continue              run backwards to the previous breakpoint or watchpoint hit (rc)
+break <address>               break at an address or symbol (b)
+break opcode <pattern>        break on an opcode, e.g. DRW or CALL draw_player
+break if <condition>          break on a condition, e.g. V3 == 0x10 && I > 0x300
+watch [read|write|change] <start> [end]
+delete|enable|disable <id>    manage breakpoints
+unwatch <id>                  remove a watchpoint
+breakpoints, watchpoints      list breakpoints or watchpoints
+registers                     show V0-VF, I, PC, timers and keys (r)
+set <V0-VF|I|PC|DT|ST> <value>
+stack                         show the call stack
+memory <address> [length]     hex dump (x)
+poke <address> <byte>...      write bytes
+disassemble [address] [lines] disassemble around PC (d)
+screen                        print the screen
+sprites [address] [length] [rows|schip]
+                              show memory as sprites, marking I and the last draw
+sprites save <file> <address> [length] [rows|schip]
+                              export the sprites as a PPM image
+draws [x y]                   list the CLS and DXYN calls of the last two frames, or those that
+                              flipped a pixel
+press|release <key>           press or release a hex key
+save|load <file>              save or load a state file
+symbols [file]                load a symbol file or list the symbols
+reset                         reset the machine
+history, !<n>                 show or repeat a command, an empty line repeats the last one
+quit                          exit (q)";
+
+/// Command interpreter behind `rust8dbg`. Each call to `execute` runs one command line and
+/// returns the text to print.
+pub struct Debugger {
+    chip8: Chip8,
+    history: Vec<String>,
+}
+
+impl Debugger {
+    pub fn new(mut chip8: Chip8) -> Debugger {
+        if chip8.history().is_none() {
+            chip8.enable_history(DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS);
+        }
+        chip8.enable_draw_log();
+        Debugger { chip8: chip8, history: Vec::new() }
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    pub fn history(&self) -> &[String] {
+        self.history.as_slice()
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let line = if line.is_empty() {
+            match self.history.last() {
+                Some(last) => last.clone(),
+                None => return Ok(String::new()),
+            }
+        } else if let Some(entry) = line.strip_prefix('!') {
+            let index = entry.parse::<usize>().map_err(|_| format!("Invalid history entry '{}'", line))?;
+            self.history.get(index.wrapping_sub(1)).cloned().ok_or(format!("No history entry {}", index))?
+        } else {
+            String::from(line)
+        };
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.run_command(&line)
+    }
+
+    fn run_command(&mut self, line: &str) -> Result<String, String> {
+        let arguments: Vec<&str> = line.split_whitespace().collect();
+        let rest = line[arguments[0].len()..].trim();
+        match arguments[0] {
+            "help" | "h" | "?" => Ok(String::from(HELP)),
+            "step" | "s" => {
+                let count = optional_number(arguments.get(1), 1)?;
+                let result = self.chip8.run_until(&[RunCondition::Instructions(count)]);
+                Ok(self.describe_stop(&result))
+            },
+            "next" | "n" => self.next(),
+            "finish" => self.finish(),
+            "continue" | "c" => {
+                let frames = optional_number(arguments.get(1), DEFAULT_CONTINUE_FRAMES)?;
+                let result = self.chip8.run_until(&[RunCondition::Frames(frames), RunCondition::KeyWait, RunCondition::Halt]);
+                Ok(self.describe_stop(&result))
+            },
+            "reverse-step" | "rs" => {
+                let count = optional_number(arguments.get(1), 1)?;
+                for _ in 0..count {
+                    if !self.chip8.step_back() {
+                        return Ok(format!("{}\n{}", HISTORY_START, self.current_instruction()));
+                    }
+                }
+                Ok(self.current_instruction())
+            },
+            "reverse-continue" | "rc" => match self.chip8.reverse_continue() {
+                Some(reason) => Ok(self.describe_stop(&RunResult { reason: reason, instructions: 0, frames: 0 })),
+                None => Ok(format!("{}\n{}", HISTORY_START, self.current_instruction())),
+            },
+            "break" | "b" => self.add_breakpoint(rest),
+            "delete" => {
+                let id = required_number(arguments.get(1), "breakpoint id")? as u32;
+                check_id(self.chip8.breakpoints_mut().remove(id), id)
+            },
+            "enable" | "disable" => {
+                let id = required_number(arguments.get(1), "breakpoint id")? as u32;
+                check_id(self.chip8.breakpoints_mut().set_enabled(id, arguments[0] == "enable"), id)
+            },
+            "breakpoints" => Ok(self.list_breakpoints()),
+            "watch" | "w" => self.add_watchpoint(&arguments[1..]),
+            "unwatch" => {
+                let id = required_number(arguments.get(1), "watchpoint id")? as u32;
+                check_id(self.chip8.watchpoints_mut().remove(id), id)
+            },
+            "watchpoints" => Ok(self.list_watchpoints()),
+            "registers" | "r" => Ok(self.registers()),
+            "set" => self.set_register(&arguments[1..]),
+            "stack" => Ok(self.stack()),
+            "memory" | "x" => {
+                let address = parse_address(arguments.get(1).ok_or("Missing address")?, self.chip8.symbols())?;
+                let length = optional_number(arguments.get(2), DEFAULT_DUMP_LENGTH as u64)? as usize;
+                Ok(hex_dump(self.chip8.read_memory(address, length), address))
+            },
+            "poke" => {
+                let address = parse_address(arguments.get(1).ok_or("Missing address")?, self.chip8.symbols())?;
+                let bytes = arguments[2..].iter().map(|byte| parse_byte(byte)).collect::<Result<Vec<u8>, String>>()?;
+                self.chip8.write_memory(address, &bytes);
+                Ok(hex_dump(self.chip8.read_memory(address, bytes.len()), address))
+            },
+            "disassemble" | "d" => {
+                let pc = self.chip8.get_program_counter();
+                let lines = optional_number(arguments.get(2), DEFAULT_DISASSEMBLY_LINES as u64)? as u16;
+                let start = match arguments.get(1) {
+                    Some(address) => parse_address(address, self.chip8.symbols())?,
+                    None => pc.saturating_sub(lines / 2 * 2),
+                };
+                Ok(self.disassemble_lines(start, lines))
+            },
+            "screen" => Ok(self.screen()),
+            "sprites" => self.sprites(&arguments[1..]),
+            "draws" => self.draws(&arguments[1..]),
+            "press" | "release" => {
+                let key = parse_hex_key(arguments.get(1))?;
+                if arguments[0] == "press" { self.chip8.press_key(key) } else { self.chip8.release_key(key) }
+                Ok(String::new())
+            },
+            "save" => {
+                let filename = arguments.get(1).ok_or("Missing file name")?;
+                File::create(filename)
+                    .and_then(|mut file| file.write_all(&self.chip8.save_state()))
+                    .map_err(|error| format!("Could not write {}: {}", filename, error))?;
+                Ok(format!("Saved state to {}", filename))
+            },
+            "load" => {
+                let filename = arguments.get(1).ok_or("Missing file name")?;
+                let mut state = Vec::new();
+                File::open(filename)
+                    .and_then(|mut file| file.read_to_end(&mut state))
+                    .map_err(|error| format!("Could not read {}: {}", filename, error))?;
+                self.chip8.load_state(&state).map_err(|error| format!("{}", error))?;
+                self.chip8.restart_history();
+                Ok(format!("Loaded state from {}\n{}", filename, self.current_instruction()))
+            },
+            "symbols" => match arguments.get(1) {
+                Some(filename) => {
+                    *self.chip8.symbols_mut() = SymbolTable::from_file(filename)?;
+                    Ok(format!("Loaded {} symbols from {}", self.chip8.symbols().list().len(), filename))
+                },
+                None => Ok(self.chip8.symbols().list().iter()
+                    .map(|symbol| format!("0x{:03X}  {}", symbol.address, symbol.name))
+                    .collect::<Vec<String>>()
+                    .join("\n")),
+            },
+            "reset" => {
+                self.chip8.reset();
+                Ok(self.current_instruction())
+            },
+            "history" => Ok(self.history.iter().enumerate().map(|(index, line)| format!("{:4}  {}", index + 1, line)).collect::<Vec<String>>().join("\n")),
+            command => Err(format!("Unknown command '{}', try help", command)),
+        }
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let pc = self.chip8.get_program_counter();
+        if self.chip8.read_memory(pc, 1).first().map(|byte| byte >> 4) != Some(0x2) {
+            let result = self.chip8.run_until(&[RunCondition::Instructions(1)]);
+            return Ok(self.describe_stop(&result));
+        }
+        let depth = self.chip8.get_stack().len();
+        self.run_to_return(pc + 2, depth)
+    }
+
+    fn finish(&mut self) -> Result<String, String> {
+        let depth = self.chip8.get_stack().len();
+        let address = *self.chip8.get_stack().last().ok_or("Not in a subroutine")?;
+        self.run_to_return(address, depth - 1)
+    }
+
+    fn run_to_return(&mut self, address: u16, depth: usize) -> Result<String, String> {
+        loop {
+            let result = self.chip8.run_until(&[RunCondition::ProgramCounter(address), RunCondition::Frames(DEFAULT_CONTINUE_FRAMES), RunCondition::KeyWait, RunCondition::Halt]);
+            let returned = self.chip8.get_stack().len() == depth;
+            if result.reason != StopReason::ProgramCounter(address) || returned {
+                return Ok(self.describe_stop(&result));
+            }
+        }
+    }
+
+    fn add_breakpoint(&mut self, arguments: &str) -> Result<String, String> {
+        let kind = if let Some(pattern) = arguments.strip_prefix("opcode ") {
+            BreakpointKind::Opcode(OpcodePattern::parse_with_symbols(pattern, self.chip8.symbols())?)
+        } else if let Some(condition) = arguments.strip_prefix("if ") {
+            BreakpointKind::Condition(Condition::parse_with_symbols(condition, self.chip8.symbols())?)
+        } else if arguments.is_empty() {
+            BreakpointKind::Address(self.chip8.get_program_counter())
+        } else {
+            BreakpointKind::Address(parse_address(arguments, self.chip8.symbols())?)
+        };
+        let description = match kind {
+            BreakpointKind::Address(address) => format!("{}{}", kind, self.location(address)),
+            _ => format!("{}", kind),
+        };
+        let id = self.chip8.breakpoints_mut().add(kind);
+        Ok(format!("Breakpoint {} at {}", id, description))
+    }
+
+    fn list_breakpoints(&self) -> String {
+        let lines: Vec<String> = self.chip8.breakpoints().list().iter()
+            .map(|breakpoint| format!("{:3}  {:8}  hits {:<6}  {}", breakpoint.id, if breakpoint.enabled { "enabled" } else { "disabled" }, breakpoint.hits, breakpoint.kind))
+            .collect();
+        if lines.is_empty() { String::from("No breakpoints") } else { lines.join("\n") }
+    }
+
+    fn add_watchpoint(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let (kind, arguments) = match arguments.first() {
+            Some(&"read") => (WatchKind::Read, &arguments[1..]),
+            Some(&"write") => (WatchKind::Write, &arguments[1..]),
+            Some(&"change") => (WatchKind::Change, &arguments[1..]),
+            _ => (WatchKind::Write, arguments),
+        };
+        let start = parse_address(arguments.first().ok_or("Missing address")?, self.chip8.symbols())?;
+        let end = match arguments.get(1) {
+            Some(end) => parse_address(end, self.chip8.symbols())?,
+            None => start,
+        };
+        if end < start {
+            return Err(String::from("End address is before start address"));
+        }
+        let id = self.chip8.watchpoints_mut().add(start, end, kind);
+        Ok(format!("Watchpoint {}: {}", id, self.chip8.watchpoints().get(id).unwrap()))
+    }
+
+    fn list_watchpoints(&self) -> String {
+        let lines: Vec<String> = self.chip8.watchpoints().list().iter()
+            .map(|watchpoint| format!("{:3}  {:8}  hits {:<6}  {}", watchpoint.id, if watchpoint.enabled { "enabled" } else { "disabled" }, watchpoint.hits, watchpoint))
+            .collect();
+        if lines.is_empty() { String::from("No watchpoints") } else { lines.join("\n") }
+    }
+
+    fn registers(&self) -> String {
+        let mut output = String::new();
+        for register in 0..REGISTER_COUNT as u8 {
+            output.push_str(&format!("V{:X}={:02X}{}", register, self.chip8.get_data_register(register), if register % 8 == 7 { "\n" } else { " " }));
+        }
+        output.push_str(&format!("I={:03X} PC={:03X} DT={:02X} ST={:02X} keys={:016b}",
+            self.chip8.get_address_register(), self.chip8.get_program_counter(),
+            self.chip8.get_delay_timer(), self.chip8.get_sound_timer(), self.chip8.get_pressed_keys()));
+        output
+    }
+
+    fn set_register(&mut self, arguments: &[&str]) -> Result<String, String> {
+        let register = arguments.first().ok_or("Missing register")?.to_uppercase();
+        let value = self.chip8.symbols().resolve(arguments.get(1).ok_or("Missing value")?)?;
+        match register.as_str() {
+            "I" => self.chip8.set_address_register(value),
+            "PC" => self.chip8.set_program_counter(value)?,
+            "DT" => self.chip8.set_delay_timer(value as u8),
+            "ST" => self.chip8.set_sound_timer(value as u8),
+            _ if register.len() == 2 && register.starts_with('V') => {
+                let index = u8::from_str_radix(&register[1..], 16).map_err(|_| format!("Unknown register '{}'", register))?;
+                self.chip8.set_data_register(index, value as u8)
+            },
+            _ => return Err(format!("Unknown register '{}'", register)),
+        }
+        Ok(self.registers())
+    }
+
+    fn stack(&self) -> String {
+        let stack = self.chip8.get_stack();
+        if stack.is_empty() {
+            return String::from("Stack is empty");
+        }
+        stack.iter().enumerate().rev()
+            .map(|(depth, address)| format!("#{}  return to 0x{:03X}{}", depth, address, self.location(*address)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn disassemble_lines(&self, start: u16, lines: u16) -> String {
+        let pc = self.chip8.get_program_counter();
+        (0..lines)
+            .map(|line| start.wrapping_add(line * 2))
+            .filter(|address| (*address as usize) + 1 < MEM_SIZE)
+            .map(|address| self.disassemble_line(address, address == pc))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn disassemble_line(&self, address: u16, current: bool) -> String {
+        let bytes = self.chip8.read_memory(address, 2);
+        let opcode = ((bytes[0] as u16) << 8) + bytes[1] as u16;
+        let marker = if current { "=>" } else { "  " };
+        let breakpoint = self.chip8.breakpoints().list().iter().any(|breakpoint| breakpoint.kind == BreakpointKind::Address(address));
+        format!("{}{} 0x{:03X}{}  {}", marker, if breakpoint { "*" } else { " " }, address, self.location(address), disassemble_with_symbols(opcode, self.chip8.symbols()))
+    }
+
+    fn location(&self, address: u16) -> String {
+        match self.chip8.symbols().describe(address) {
+            Some(name) => format!(" <{}>", name),
+            None => String::new(),
+        }
+    }
+
+    fn current_instruction(&self) -> String {
+        self.disassemble_line(self.chip8.get_program_counter(), true)
+    }
+
+    fn screen(&self) -> String {
+        let (width, _) = self.chip8.retrieve_screen_size();
+        self.chip8.retrieve_screen_pixels()
+            .chunks(width)
+            .map(|row| row.iter().map(|pixel| if *pixel { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn sprites(&self, arguments: &[&str]) -> Result<String, String> {
+        let (filename, arguments) = match arguments.first() {
+            Some(&"save") => (Some(*arguments.get(1).ok_or("Missing file name")?), &arguments[2..]),
+            _ => (None, arguments),
+        };
+        let start = match arguments.first() {
+            Some(address) => parse_address(address, self.chip8.symbols())?,
+            None => self.chip8.get_address_register(),
+        };
+        let format = match arguments.get(2) {
+            Some(&"schip") => SpriteFormat::SuperChip,
+            rows => SpriteFormat::Chip8(optional_number(rows, DEFAULT_SPRITE_ROWS as u64)? as usize),
+        };
+        let length = optional_number(arguments.get(1), format.bytes_per_sprite() as u64)? as usize;
+        let memory = self.chip8.read_memory(0, MEM_SIZE);
+        let highlights = self.chip8.sprite_highlights(format);
+        match filename {
+            Some(filename) => {
+                let image = render_sheet(memory, start, length, format, SPRITE_COLUMNS, SPRITE_SCALE, &highlights);
+                File::create(filename)
+                    .and_then(|mut file| file.write_all(&image.to_ppm()))
+                    .map_err(|error| format!("Could not write {}: {}", filename, error))?;
+                Ok(format!("Saved {}x{} sprite sheet to {}", image.width, image.height, filename))
+            },
+            None => Ok(sprite_text(memory, start, length, format, &highlights).join("\n")),
+        }
+    }
+
+    fn draws(&self, arguments: &[&str]) -> Result<String, String> {
+        let calls = self.chip8.get_draw_calls();
+        let describe = |calls: &[&DrawCall]| calls.iter()
+            .map(|call| format!("frame {}  0x{:03X}{}  {}", call.frame, call.pc, self.location(call.pc), call))
+            .collect::<Vec<String>>();
+        if arguments.is_empty() {
+            let lines = describe(&calls.iter().collect::<Vec<&DrawCall>>());
+            return Ok(if lines.is_empty() { String::from("No draw calls in the last two frames") } else { lines.join("\n") });
+        }
+        let (x, y) = (required_number(arguments.first(), "x")?, required_number(arguments.get(1), "y")?);
+        let (width, height) = self.chip8.retrieve_screen_size();
+        if x >= width as u64 || y >= height as u64 {
+            return Err(format!("Pixel ({}, {}) is outside the {}x{} screen", x, y, width, height));
+        }
+        let pixel = calls_at(&calls, x as u8, y as u8);
+        let on = self.chip8.retrieve_screen_pixels()[y as usize * width + x as usize];
+        let mut lines = describe(&pixel);
+        lines.push(format!("Pixel ({}, {}) is {}, flipped by {} of {} draw calls", x, y, if on { "on" } else { "off" }, pixel.len(), calls.len()));
+        Ok(lines.join("\n"))
+    }
+
+    fn describe_stop(&self, result: &RunResult) -> String {
+        let reason = match result.reason {
+            StopReason::BreakpointHit(id) => format!("Breakpoint {}", id),
+            StopReason::WatchpointHit(hit) => {
+                let access = hit.access;
+                match access.kind {
+                    AccessKind::Read => format!("Watchpoint {}: read 0x{:03X} = 0x{:02X} by 0x{:03X}  {}", hit.id, access.address, access.value, hit.pc, disassemble_with_symbols(hit.opcode, self.chip8.symbols())),
+                    AccessKind::Write => format!("Watchpoint {}: write 0x{:03X} = 0x{:02X} (was 0x{:02X}) by 0x{:03X}  {}", hit.id, access.address, access.value, access.old, hit.pc, disassemble_with_symbols(hit.opcode, self.chip8.symbols())),
+                }
+            },
+            StopReason::KeyWait => String::from("Waiting for a key press"),
+            StopReason::Halt => String::from("Halted"),
+            StopReason::Frames => format!("Stopped after {} frames", result.frames),
+            StopReason::InvalidCondition => String::from("Invalid run condition"),
+            _ => String::new(),
+        };
+        let location = self.current_instruction();
+        if reason.is_empty() { location } else { format!("{}\n{}", reason, location) }
+    }
+}
+
+fn check_id(found: bool, id: u32) -> Result<String, String> {
+    if found { Ok(String::new()) } else { Err(format!("No breakpoint or watchpoint {}", id)) }
+}
+
+fn required_number(argument: Option<&&str>, name: &str) -> Result<u64, String> {
+    parse_number(argument.ok_or(format!("Missing {}", name))?)
+}
+
+fn optional_number(argument: Option<&&str>, default: u64) -> Result<u64, String> {
+    argument.map(|argument| parse_number(argument)).unwrap_or(Ok(default))
+}
+
+fn parse_address(argument: &str, symbols: &SymbolTable) -> Result<u16, String> {
+    let address = symbols.resolve(argument)?;
+    if address as usize >= MEM_SIZE {
+        return Err(format!("Address '{}' is out of memory", argument));
+    }
+    Ok(address)
+}
+
+fn parse_byte(argument: &str) -> Result<u8, String> {
+    let value = parse_number(argument)?;
+    if value > 0xFF {
+        return Err(format!("Byte '{}' is out of range", argument));
+    }
+    Ok(value as u8)
+}
+
+fn parse_hex_key(argument: Option<&&str>) -> Result<HexKey, String> {
+    let argument = argument.ok_or("Missing key")?;
+    u8::from_str_radix(argument, 16).ok()
+        .and_then(HexKey::from_index)
+        .ok_or(format!("Invalid key '{}', expected 0-F", argument))
+}
+
+pub fn hex_dump(bytes: &[u8], address: u16) -> String {
+    bytes.chunks(16).enumerate()
+        .map(|(line, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+            let ascii: String = chunk.iter().map(|byte| if *byte >= 0x20 && *byte < 0x7F { *byte as char } else { '.' }).collect();
+            format!("0x{:03X}  {:<47}  {}", address as usize + line * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Debugger;
+    use super::super::{create_test_chip8, Inspect, SymbolTable};
+
+    const ROM: [u8; 14] = [
+        0x60, 0x05, // LD V0, 5
+        0x22, 0x08, // CALL 0x208
+        0x61, 0x01, // LD V1, 1
+        0x12, 0x06, // JMP 0x206
+        0xA3, 0x00, // LDI 0x300
+        0xF0, 0x33, // BCD V0
+        0x00, 0xEE, // RET
+    ];
+
+    fn create_debugger() -> Debugger {
+        Debugger::new(create_test_chip8(10, ROM.to_vec()))
+    }
+
+    #[test]
+    fn step_and_next_over_call() {
+        let mut under_test = create_debugger();
+
+        under_test.execute("step").unwrap();
+        let result = under_test.execute("next").unwrap();
+
+        assert!(result.contains("=>  0x204"));
+        assert!(under_test.chip8().get_address_register() == 0x300);
+        assert!(under_test.chip8().get_stack().is_empty());
+    }
+
+    #[test]
+    fn finish_returns_from_subroutine() {
+        let mut under_test = create_debugger();
+        under_test.execute("step 2").unwrap();
+
+        under_test.execute("finish").unwrap();
+
+        assert!(under_test.chip8().get_program_counter() == 0x204);
+        assert!(under_test.execute("finish") == Err(String::from("Not in a subroutine")));
+    }
+
+    #[test]
+    fn continue_to_breakpoint() {
+        let mut under_test = create_debugger();
+        let added = under_test.execute("break 0x20A").unwrap();
+
+        let result = under_test.execute("c").unwrap();
+
+        assert!(added == "Breakpoint 1 at 0x20A");
+        assert!(result.starts_with("Breakpoint 1\n=>* 0x20A"));
+    }
+
+    #[test]
+    fn reverse_step_and_continue() {
+        let mut under_test = create_debugger();
+        under_test.execute("step 5").unwrap();
+        under_test.execute("watch 0x301").unwrap();
+
+        let watch = under_test.execute("rc").unwrap();
+        let count = under_test.chip8().get_instruction_count();
+        let step = under_test.execute("rs 2").unwrap();
+        let start = under_test.execute("reverse-continue").unwrap();
+
+        assert!(watch == "Watchpoint 1: write 0x301 = 0x00 (was 0x00) by 0x20A  0xF033: BCD(0)\n=>  0x20C  0x00EE: RET");
+        assert!(count == 4);
+        assert!(step == "=>  0x208  0xA300: LDI(768)");
+        assert!(start.starts_with("Reached the start of the recorded history\n=>  0x200"));
+    }
+
+    #[test]
+    fn sprites_at_address_register() {
+        let mut under_test = create_debugger();
+        under_test.execute("step 3").unwrap();
+        under_test.chip8_mut().write_memory(0x300, &[0x81, 0x42]);
+
+        let result = under_test.execute("sprites").unwrap();
+        let rows = under_test.execute("sprites 0x300 4 2").unwrap();
+
+        assert!(result.lines().count() == 8);
+        assert!(result.starts_with("0x300  #......#  I\n0x301  .#....#.  I\n"));
+        assert!(rows == "0x300  #......#  I\n0x301  .#....#.  I\n\n0x302  ........\n0x303  ........");
+    }
+
+    #[test]
+    fn continue_to_watchpoint() {
+        let mut under_test = create_debugger();
+        under_test.execute("watch change 0x300 0x302").unwrap();
+
+        let result = under_test.execute("continue").unwrap();
+
+        assert!(result.starts_with("Watchpoint 1: write 0x302 = 0x05 (was 0x00) by 0x20A"));
+    }
+
+    #[test]
+    fn continue_until_halt() {
+        let mut under_test = create_debugger();
+
+        let result = under_test.execute("continue").unwrap();
+
+        assert!(result.starts_with("Halted"));
+    }
+
+    #[test]
+    fn poke_and_memory_dump() {
+        let mut under_test = create_debugger();
+
+        under_test.execute("poke 0x300 0x41 0x42").unwrap();
+        let result = under_test.execute("x 0x300 4").unwrap();
+
+        assert!(result == "0x300  41 42 00 00                                      AB..");
+    }
+
+    #[test]
+    fn set_register_and_show() {
+        let mut under_test = create_debugger();
+
+        let result = under_test.execute("set va 0x7f").unwrap();
+
+        assert!(under_test.chip8().get_data_register(0xA) == 0x7F);
+        assert!(result.contains("VA=7F"));
+        assert!(under_test.execute("set X 1").is_err());
+    }
+
+    #[test]
+    fn set_program_counter_outside_memory() {
+        let mut under_test = create_debugger();
+
+        let result = under_test.execute("set PC 0xFFF");
+
+        assert!(result == Err(String::from("PC 0xFFF is outside of memory")));
+        assert!(under_test.execute("set pc 0xFFE").is_ok());
+        assert!(under_test.chip8().get_program_counter() == 0xFFE);
+    }
+
+    #[test]
+    fn empty_line_and_history_repeat() {
+        let mut under_test = create_debugger();
+
+        under_test.execute("step").unwrap();
+        under_test.execute("").unwrap();
+        under_test.execute("registers").unwrap();
+        under_test.execute("!1").unwrap();
+
+        assert!(under_test.chip8().get_program_counter() == 0x20A);
+        assert!(under_test.history() == [String::from("step"), String::from("registers"), String::from("step")]);
+    }
+
+    #[test]
+    fn disassemble_around_pc() {
+        let mut under_test = create_debugger();
+        under_test.execute("step").unwrap();
+
+        let result = under_test.execute("disassemble 0x200 3").unwrap();
+
+        assert!(result == "    0x200  0x6005: LD(0, 5)\n=>  0x202  0x2208: CALL(520)\n    0x204  0x6101: LD(1, 1)");
+    }
+
+    #[test]
+    fn symbols_in_commands_and_disassembly() {
+        let mut under_test = create_debugger();
+        *under_test.chip8_mut().symbols_mut() = SymbolTable::parse("main = 0x200\nscore = 0x208\ndigits = 0x300 data").unwrap();
+
+        let added = under_test.execute("break score+2").unwrap();
+        under_test.execute("watch digits digits+2").unwrap();
+        under_test.execute("step 3").unwrap();
+        let stack = under_test.execute("stack").unwrap();
+        let disassembly = under_test.execute("disassemble main 3").unwrap();
+
+        assert!(added == "Breakpoint 1 at 0x20A <score+0x2>");
+        assert!(stack == "#0  return to 0x204 <main+0x4>");
+        assert!(disassembly == "    0x200 <main>  0x6005: LD(0, 5)\n    0x202 <main+0x2>  0x2208: CALL score\n    0x204 <main+0x4>  0x6101: LD(1, 1)");
+        assert!(under_test.execute("break nowhere").is_err());
+    }
+
+    #[test]
+    fn draws_of_last_frames_and_pixel() {
+        // LDI 0x20A; DRW V0, V0, 1; CLS; DRW V0, V0, 1; JMP 0x208; one sprite row
+        let mut chip8 = create_test_chip8(10, vec![0xA2, 0x0A, 0xD0, 0x01, 0x00, 0xE0, 0xD0, 0x01, 0x12, 0x08, 0x80]);
+        *chip8.symbols_mut() = SymbolTable::parse("main = 0x200").unwrap();
+        let mut under_test = Debugger::new(chip8);
+        let empty = under_test.execute("draws").unwrap();
+        under_test.execute("step 4").unwrap();
+
+        let result = under_test.execute("draws").unwrap();
+        let pixel = under_test.execute("draws 0 0").unwrap();
+
+        assert!(empty == "No draw calls in the last two frames");
+        assert!(result.lines().count() == 3);
+        assert!(result.starts_with("frame 0  0x202 <main+0x2>  DRW I=0x20A 1 rows at (0, 0), no collision, 1 pixels toggled\n"));
+        assert!(pixel.ends_with("frame 0  0x204 <main+0x4>  CLS, 1 pixels cleared\nframe 0  0x206 <main+0x6>  DRW I=0x20A 1 rows at (0, 0), no collision, 1 pixels toggled\nPixel (0, 0) is on, flipped by 3 of 3 draw calls"));
+        assert!(under_test.execute("draws 1 0").unwrap() == "Pixel (1, 0) is off, flipped by 0 of 3 draw calls");
+        assert!(under_test.execute("draws 64 0") == Err(String::from("Pixel (64, 0) is outside the 64x32 screen")));
+    }
+
+    #[test]
+    fn unknown_command() {
+        let mut under_test = create_debugger();
+
+        let result = under_test.execute("jump");
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+
+use super::trace::{register_name, TraceRecord};
+
+/// How records of two traces are paired up: by instruction count, or by frame and position
+/// within the frame when the runs execute a different number of instructions per frame.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Alignment {
+    Instruction,
+    Frame,
+}
+
+/// The first pair of records that differ. An index equal to the length of its trace means that
+/// trace has no record at this point.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Divergence {
+    pub left: usize,
+    pub right: usize,
+    pub differences: Vec<String>,
+}
+
+pub fn first_divergence(left: &[TraceRecord], right: &[TraceRecord], alignment: Alignment) -> Option<Divergence> {
+    let (left_keys, right_keys) = (alignment_keys(left, alignment), alignment_keys(right, alignment));
+    let (mut l, mut r) = (0, 0);
+    while l < left.len() || r < right.len() {
+        let order = match (left_keys.get(l), right_keys.get(r)) {
+            (Some(left_key), Some(right_key)) => left_key.cmp(right_key),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        let differences = match order {
+            Ordering::Equal => compare_records(&left[l], &right[r]),
+            Ordering::Less => vec![format!("{} only in left trace", describe_key(left_keys[l], alignment))],
+            Ordering::Greater => vec![format!("{} only in right trace", describe_key(right_keys[r], alignment))],
+        };
+        if !differences.is_empty() {
+            return Some(Divergence {
+                left: l,
+                right: r,
+                differences: differences,
+            });
+        }
+        l += 1;
+        r += 1;
+    }
+    None
+}
+
+fn alignment_keys(records: &[TraceRecord], alignment: Alignment) -> Vec<(u64, u64)> {
+    let mut keys = Vec::with_capacity(records.len());
+    let mut ordinal = 0;
+    for (index, record) in records.iter().enumerate() {
+        match alignment {
+            Alignment::Instruction => keys.push((record.cycle, 0)),
+            Alignment::Frame => {
+                ordinal = if index > 0 && records[index - 1].frame == record.frame { ordinal + 1 } else { 0 };
+                keys.push((record.frame, ordinal));
+            },
+        }
+    }
+    keys
+}
+
+fn describe_key(key: (u64, u64), alignment: Alignment) -> String {
+    match alignment {
+        Alignment::Instruction => format!("Instruction {}", key.0),
+        Alignment::Frame => format!("Instruction {} of frame {}", key.1, key.0),
+    }
+}
+
+/// Lists what differs between two records, ignoring `cycle` and `frame` which the alignment
+/// already accounts for.
+pub fn compare_records(left: &TraceRecord, right: &TraceRecord) -> Vec<String> {
+    let mut differences = Vec::new();
+    if left.pc != right.pc {
+        differences.push(format!("PC 0x{:03X} != 0x{:03X}", left.pc, right.pc));
+    }
+    if left.opcode != right.opcode {
+        differences.push(format!("opcode 0x{:04X} ({}) != 0x{:04X} ({})", left.opcode, left.asm, right.opcode, right.asm));
+    }
+    let mut registers: Vec<u8> = left.registers.iter().chain(right.registers.iter()).map(|register| register.0).collect();
+    registers.sort();
+    registers.dedup();
+    for register in registers {
+        let (left_value, right_value) = (register_value(left, register), register_value(right, register));
+        if left_value != right_value {
+            differences.push(format!("{} {} != {}", register_name(register), describe_change(left_value), describe_change(right_value)));
+        }
+    }
+    if left.i != right.i {
+        differences.push(format!("I 0x{:03X} != 0x{:03X}", left.i, right.i));
+    }
+    if left.vf != right.vf {
+        differences.push(format!("VF result 0x{:02X} != 0x{:02X}", left.vf, right.vf));
+    }
+    if left.writes != right.writes {
+        differences.push(format!("writes {} != {}", describe_writes(&left.writes), describe_writes(&right.writes)));
+    }
+    differences
+}
+
+fn register_value(record: &TraceRecord, register: u8) -> Option<u8> {
+    record.registers.iter().find(|change| change.0 == register).map(|change| change.1)
+}
+
+fn describe_change(value: Option<u8>) -> String {
+    match value {
+        Some(value) => format!("0x{:02X}", value),
+        None => String::from("unchanged"),
+    }
+}
+
+fn describe_writes(writes: &[(u16, u8)]) -> String {
+    if writes.is_empty() {
+        return String::from("none");
+    }
+    writes.iter().map(|&(address, value)| format!("[0x{:03X}]=0x{:02X}", address, value)).collect::<Vec<String>>().join(" ")
+}
+
+/// One line per record, as shown in divergence reports.
+pub fn describe_record(record: &TraceRecord) -> String {
+    let mut line = format!("{:>8} {:>6}  0x{:03X}  0x{:04X}  {:<16} I=0x{:03X} VF=0x{:02X}",
+        record.cycle, record.frame, record.pc, record.opcode, record.asm, record.i, record.vf);
+    for &(register, value) in &record.registers {
+        line.push_str(&format!(" {}=0x{:02X}", register_name(register), value));
+    }
+    if !record.writes.is_empty() {
+        line.push_str(&format!(" {}", describe_writes(&record.writes)));
+    }
+    line
+}
+
+/// Formats a divergence with up to `context` records of the left trace leading up to it.
+pub fn format_divergence(left: &[TraceRecord], right: &[TraceRecord], divergence: &Divergence, context: usize) -> String {
+    let mut lines = Vec::new();
+    let first = match (left.get(divergence.left), right.get(divergence.right)) {
+        (Some(left_record), Some(right_record)) if right_record.cycle < left_record.cycle => Some(right_record),
+        (Some(record), _) | (None, Some(record)) => Some(record),
+        (None, None) => None,
+    };
+    match first {
+        Some(record) =>
+            lines.push(format!("First divergence at instruction {} (frame {}, PC 0x{:03X})", record.cycle, record.frame, record.pc)),
+        None => lines.push(String::from("First divergence at the end of both traces")),
+    }
+    for difference in &divergence.differences {
+        lines.push(format!("  {}", difference));
+    }
+    lines.push(format!("  {:>8} {:>6}  PC     opcode", "cycle", "frame"));
+    for record in &left[divergence.left.saturating_sub(context)..divergence.left] {
+        lines.push(format!("  {}", describe_record(record)));
+    }
+    match left.get(divergence.left) {
+        Some(record) => lines.push(format!("< {}", describe_record(record))),
+        None => lines.push(String::from("< (end of trace)")),
+    }
+    match right.get(divergence.right) {
+        Some(record) => lines.push(format!("> {}", describe_record(record))),
+        None => lines.push(String::from("> (end of trace)")),
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_divergence, format_divergence, Alignment, Divergence};
+    use super::super::{create_test_chip8, Quirks, RunCondition};
+    use super::super::trace::{read_trace, TraceFormat, TraceRecord, TraceRecorder};
+
+    const ROM: [u8; 14] = [
+        0x61, 0x0F, // LD V1, 15
+        0x6F, 0x05, // LD VF, 5
+        0x80, 0x11, // OR V0, V1
+        0xA3, 0x00, // LDI 0x300
+        0xF0, 0x55, // STOR V0
+        0xF0, 0x15, // LD DT, V0
+        0x12, 0x0C, // JMP 0x20C
+    ];
+
+    fn record(quirks: Quirks) -> Vec<TraceRecord> {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        chip8.config.quirks = quirks;
+        let recorder = TraceRecorder::new(Vec::new(), TraceFormat::Jsonl).unwrap();
+        let id = chip8.observers_mut().add(Box::new(recorder));
+        chip8.run_until(&[RunCondition::Instructions(20), RunCondition::Halt]);
+        read_trace(chip8.observers().get::<TraceRecorder<Vec<u8>>>(id).unwrap().get_ref()).unwrap()
+    }
+
+    #[test]
+    fn identical_traces() {
+        let left = record(Quirks::default());
+        let right = record(Quirks::default());
+
+        assert!(first_divergence(&left, &right, Alignment::Instruction).is_none());
+    }
+
+    #[test]
+    fn vf_reset_quirk_diverges_at_or() {
+        let quirks = Quirks { vf_reset: false, ..Default::default() };
+        let left = record(Quirks::default());
+        let right = record(quirks);
+
+        let result = first_divergence(&left, &right, Alignment::Instruction).unwrap();
+
+        assert!(result.left == 2 && result.right == 2);
+        assert!(left[2].pc == 0x204);
+        assert!(result.differences == vec![String::from("VF 0x00 != unchanged"), String::from("VF result 0x00 != 0x05")]);
+    }
+
+    #[test]
+    fn frame_alignment_ignores_cycle_numbers() {
+        let left = record(Quirks::default());
+        let mut right = left.clone();
+        for record in right.iter_mut() {
+            record.cycle += 100;
+        }
+        right[4].writes.push((0x301, 1));
+
+        let by_instruction = first_divergence(&left, &right, Alignment::Instruction).unwrap();
+        let by_frame = first_divergence(&left, &right, Alignment::Frame).unwrap();
+
+        assert!(by_instruction.differences == vec![String::from("Instruction 0 only in left trace")]);
+        assert!(by_frame.left == 4);
+        assert!(by_frame.differences == vec![String::from("writes [0x300]=0x0F != [0x300]=0x0F [0x301]=0x01")]);
+    }
+
+    #[test]
+    fn shorter_trace_diverges_at_end() {
+        let left = record(Quirks::default());
+        let right = left[..3].to_vec();
+
+        let result = first_divergence(&left, &right, Alignment::Instruction).unwrap();
+
+        assert!(result == Divergence { left: 3, right: 3, differences: vec![String::from("Instruction 3 only in left trace")] });
+        assert!(format_divergence(&left, &right, &result, 2).ends_with("> (end of trace)"));
+    }
+
+    #[test]
+    fn format_divergence_shows_context() {
+        let left = record(Quirks::default());
+        let mut right = left.clone();
+        right[3].i = 0x301;
+        let divergence = first_divergence(&left, &right, Alignment::Instruction).unwrap();
+
+        let result = format_divergence(&left, &right, &divergence, 2);
+        let lines: Vec<&str> = result.lines().collect();
+
+        assert!(lines[0] == "First divergence at instruction 3 (frame 1, PC 0x206)");
+        assert!(lines[1] == "  I 0x300 != 0x301");
+        assert!(lines.len() == 7);
+        assert!(lines[3].contains("0x202"));
+        assert!(lines[5].starts_with("< "));
+        assert!(lines[6].starts_with("> ") && lines[6].contains("I=0x301"));
+    }
+}
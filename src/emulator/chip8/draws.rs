@@ -0,0 +1,190 @@
+use core::fmt;
+use core::mem;
+use alloc::vec::Vec;
+
+use super::Chip8;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum DrawOp {
+    Clear,
+    /// DXYN with the sprite at `address`, `height` bytes of it and the coordinates taken from
+    /// VX and VY. `collision` is the value VF was set to.
+    Sprite { address: u16, height: u8, x: u8, y: u8, collision: bool },
+}
+
+/// One CLS or DXYN and the pixels it flipped, as `(x, y)`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct DrawCall {
+    pub frame: u64,
+    pub pc: u16,
+    pub op: DrawOp,
+    pub toggled: Vec<(u8, u8)>,
+}
+
+impl DrawCall {
+    pub fn toggles(&self, x: u8, y: u8) -> bool {
+        self.toggled.contains(&(x, y))
+    }
+}
+
+impl fmt::Display for DrawCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.op {
+            DrawOp::Clear => write!(f, "CLS, {} pixels cleared", self.toggled.len()),
+            DrawOp::Sprite { address, height, x, y, collision } => write!(f, "DRW I=0x{:03X} {} rows at ({}, {}), {}, {} pixels toggled",
+                address, height, x, y, if collision { "collision" } else { "no collision" }, self.toggled.len()),
+        }
+    }
+}
+
+/// The draw calls of the frame being executed and of the frame before it. XOR drawing makes a
+/// pixel that is toggled twice within a frame flicker, the calls that touch a pixel show where.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct DrawLog {
+    current: Vec<DrawCall>,
+    previous: Vec<DrawCall>,
+}
+
+impl DrawLog {
+    pub fn new() -> DrawLog {
+        Default::default()
+    }
+
+    /// Records a call from the screen before and after it, `width` pixels per row.
+    pub fn record(&mut self, frame: u64, pc: u16, op: DrawOp, before: &[bool], after: &[bool], width: usize) {
+        let toggled = before.iter().zip(after.iter()).enumerate()
+            .filter(|&(_, (before, after))| before != after)
+            .map(|(index, _)| ((index % width) as u8, (index / width) as u8))
+            .collect();
+        self.current.push(DrawCall { frame: frame, pc: pc, op: op, toggled: toggled });
+    }
+
+    pub fn next_frame(&mut self) {
+        self.previous = mem::take(&mut self.current);
+    }
+
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.previous.clear();
+    }
+
+    pub fn current(&self) -> &[DrawCall] {
+        &self.current
+    }
+
+    pub fn previous(&self) -> &[DrawCall] {
+        &self.previous
+    }
+
+    /// The calls of both frames, oldest first.
+    pub fn calls(&self) -> Vec<DrawCall> {
+        self.previous.iter().chain(self.current.iter()).cloned().collect()
+    }
+}
+
+/// The calls of `calls` that flipped the pixel at `x`, `y`.
+pub fn calls_at(calls: &[DrawCall], x: u8, y: u8) -> Vec<&DrawCall> {
+    calls.iter().filter(|call| call.toggles(x, y)).collect()
+}
+
+impl Chip8 {
+    /// Starts recording the CLS and DXYN calls of the current and the previous frame.
+    pub fn enable_draw_log(&mut self) {
+        if self.draw_log.is_none() {
+            self.draw_log = Some(DrawLog::new());
+        }
+    }
+
+    pub fn disable_draw_log(&mut self) {
+        self.draw_log = None;
+    }
+
+    pub fn draw_log(&self) -> Option<&DrawLog> {
+        self.draw_log.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calls_at, DrawOp};
+    use super::super::{create_test_chip8, Chip8, Inspect};
+    use emulator::Emulator;
+
+    // CLS; LDI 0x20C; DRW V0, V1, 2; DRW V0, V1, 1; JMP 0x208; an unused word; two sprite rows
+    fn create_chip8() -> Chip8 {
+        let mut chip8 = create_test_chip8(3, vec![0x00, 0xE0, 0xA2, 0x0C, 0xD0, 0x12, 0xD0, 0x11, 0x12, 0x08, 0x12, 0x0A, 0xC0, 0x80]);
+        chip8.enable_draw_log();
+        chip8
+    }
+
+    #[test]
+    fn keeps_current_and_previous_frame() {
+        let mut under_test = create_chip8();
+
+        under_test.update();
+        let first = under_test.draw_log().unwrap().clone();
+        under_test.update();
+        let second = under_test.draw_log().unwrap();
+
+        assert!(first.current().is_empty());
+        assert!(first.previous().iter().map(|call| call.pc).collect::<Vec<u16>>() == vec![0x200, 0x204]);
+        assert!(second.current().is_empty());
+        assert!(second.previous().iter().map(|call| call.pc).collect::<Vec<u16>>() == vec![0x206]);
+    }
+
+    #[test]
+    fn records_pixels_and_collisions() {
+        let mut under_test = create_chip8();
+
+        for _ in 0..4 {
+            under_test.step();
+        }
+
+        let log = under_test.draw_log().unwrap();
+        assert!(log.previous().len() == 2);
+        assert!(log.current().len() == 1);
+        assert!(log.previous()[0].op == DrawOp::Clear);
+        assert!(log.previous()[0].toggled.is_empty());
+        let first = &log.previous()[1];
+        assert!(first.pc == 0x204 && first.frame == 0);
+        assert!(first.op == DrawOp::Sprite { address: 0x20C, height: 2, x: 0, y: 0, collision: false });
+        assert!(first.toggled == vec![(0, 0), (1, 0), (0, 1)]);
+        let second = &log.current()[0];
+        assert!(second.frame == 1);
+        assert!(second.op == DrawOp::Sprite { address: 0x20C, height: 1, x: 0, y: 0, collision: true });
+        assert!(second.toggled == vec![(0, 0), (1, 0)]);
+        assert!(format!("{}", second) == "DRW I=0x20C 1 rows at (0, 0), collision, 2 pixels toggled");
+    }
+
+    #[test]
+    fn calls_affecting_a_pixel() {
+        let mut under_test = create_chip8();
+        for _ in 0..4 {
+            under_test.step();
+        }
+
+        let calls = under_test.draw_log().unwrap().calls();
+
+        assert!(calls_at(&calls, 0, 0).len() == 2);
+        assert!(calls_at(&calls, 0, 1).iter().map(|call| call.pc).collect::<Vec<u16>>() == vec![0x204]);
+        assert!(calls_at(&calls, 5, 5).is_empty());
+    }
+
+    #[test]
+    fn clear_lists_lit_pixels_and_reset_keeps_log() {
+        let mut under_test = create_chip8();
+        for _ in 0..3 {
+            under_test.step();
+        }
+        under_test.set_program_counter(0x200).unwrap();
+
+        under_test.step();
+
+        let clear = &under_test.draw_log().unwrap().current()[0];
+        assert!(clear.op == DrawOp::Clear);
+        assert!(clear.toggled == vec![(0, 0), (1, 0), (0, 1)]);
+        assert!(format!("{}", clear) == "CLS, 3 pixels cleared");
+        under_test.reset();
+        assert!(under_test.draw_log().map(|log| log.calls().len()) == Some(0));
+    }
+}
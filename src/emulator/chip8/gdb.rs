@@ -0,0 +1,759 @@
+use std::io;
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use emulator::scheduler::FRAMES_PER_SECOND;
+use super::{Chip8, Inspect, REGISTER_COUNT};
+use super::breakpoint::BreakpointKind;
+use super::control::{RunCondition, RunResult, StopReason};
+use super::reverse::{DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS};
+use super::component::memory::AccessKind;
+use super::watchpoint::WatchKind;
+
+const INTERRUPT: u8 = 0x03;
+const PACKET_SIZE: usize = 0x1000;
+
+// Register numbers: V0-VF are 0-15, followed by I, PC, SP, DT and ST.
+const I_REGISTER: usize = REGISTER_COUNT;
+const PC_REGISTER: usize = REGISTER_COUNT + 1;
+const SP_REGISTER: usize = REGISTER_COUNT + 2;
+const DT_REGISTER: usize = REGISTER_COUNT + 3;
+const ST_REGISTER: usize = REGISTER_COUNT + 4;
+const REGISTER_NUMBERS: usize = REGISTER_COUNT + 5;
+
+const TARGET_XML: &'static str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.rust8.chip8">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// Something read from the debugger connection.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Input {
+    Packet(String),
+    BadChecksum,
+    Interrupt,
+}
+
+/// Splits the bytes coming from GDB into packets. Acknowledgements are dropped, the caller
+/// answers each packet with `+` or `-`. A packet longer than `PACKET_SIZE` is dropped and reported
+/// as `BadChecksum`.
+#[derive(Clone, Debug, Default)]
+pub struct PacketDecoder {
+    buffer: Vec<u8>,
+}
+
+impl PacketDecoder {
+    pub fn new() -> PacketDecoder {
+        Default::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Input> {
+        self.buffer.extend_from_slice(bytes);
+        let mut inputs = Vec::new();
+        loop {
+            let start = match self.buffer.iter().position(|byte| *byte == b'$' || *byte == INTERRUPT) {
+                Some(start) => start,
+                None => {
+                    self.buffer.clear();
+                    return inputs;
+                }
+            };
+            if self.buffer[start] == INTERRUPT {
+                self.buffer.drain(..start + 1);
+                inputs.push(Input::Interrupt);
+                continue;
+            }
+            let end = match self.buffer[start..].iter().position(|byte| *byte == b'#') {
+                Some(end) if start + end + 2 < self.buffer.len() => start + end,
+                _ => {
+                    self.buffer.drain(..start);
+                    if self.buffer.len() > PACKET_SIZE {
+                        self.buffer.clear();
+                        inputs.push(Input::BadChecksum);
+                    }
+                    return inputs;
+                }
+            };
+            let data = unescape(&self.buffer[start + 1..end]);
+            let expected = checksum(&data);
+            let received = String::from_utf8_lossy(&self.buffer[end + 1..end + 3]).into_owned();
+            self.buffer.drain(..end + 3);
+            match (u8::from_str_radix(&received, 16), String::from_utf8(data)) {
+                (Ok(received), Ok(packet)) if received == expected => inputs.push(Input::Packet(packet)),
+                _ => inputs.push(Input::BadChecksum),
+            }
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut escaped = false;
+    for byte in data {
+        if escaped {
+            result.push(byte ^ 0x20);
+            escaped = false;
+        } else if *byte == b'}' {
+            escaped = true;
+        } else {
+            result.push(*byte);
+        }
+    }
+    result
+}
+
+/// Frames `data` as `$data#checksum`, escaping the characters that have a meaning in the protocol.
+pub fn encode_packet(data: &str) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for byte in data.bytes() {
+        match byte {
+            b'$' | b'#' | b'}' | b'*' => {
+                escaped.push(b'}');
+                escaped.push(byte ^ 0x20);
+            },
+            _ => escaped.push(byte),
+        }
+    }
+    let mut packet = vec![b'$'];
+    packet.extend_from_slice(&escaped);
+    packet.extend_from_slice(format!("#{:02x}", checksum(&escaped)).as_bytes());
+    packet
+}
+
+/// What the connection should do after a packet.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Response {
+    Reply(String),
+    Continue,
+    Detach,
+    Kill,
+}
+
+/// Answers GDB remote serial protocol packets for a `Chip8`. Breakpoints and watchpoints set by
+/// GDB are added to the machine's own lists.
+pub struct GdbStub {
+    chip8: Chip8,
+    breakpoints: Vec<(u16, u32)>,
+    watchpoints: Vec<(u8, u16, u16, Vec<u32>)>,
+}
+
+impl GdbStub {
+    pub fn new(mut chip8: Chip8) -> GdbStub {
+        if chip8.history().is_none() {
+            chip8.enable_history(DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_MAX_CHECKPOINTS);
+        }
+        GdbStub { chip8: chip8, breakpoints: Vec::new(), watchpoints: Vec::new() }
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    pub fn handle_packet(&mut self, packet: &str) -> Response {
+        let (command, arguments) = packet.split_at(packet.chars().next().map(|c| c.len_utf8()).unwrap_or(0));
+        let reply = match command {
+            "?" => String::from("S05"),
+            "g" => (0..REGISTER_NUMBERS).map(|register| self.read_register(register)).collect(),
+            "G" => self.write_registers(arguments),
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(register) if register < REGISTER_NUMBERS => self.read_register(register),
+                _ => String::from("E00"),
+            },
+            "P" => self.write_register_packet(arguments),
+            "m" => self.read_memory_packet(arguments),
+            "M" => self.write_memory_packet(arguments),
+            "s" => {
+                if !self.set_resume_address(arguments) {
+                    return Response::Reply(String::from("E00"));
+                }
+                let result = self.chip8.run_until(&[RunCondition::Instructions(1)]);
+                self.stop_reply(&result)
+            },
+            "c" => {
+                if !self.set_resume_address(arguments) {
+                    return Response::Reply(String::from("E00"));
+                }
+                return Response::Continue;
+            },
+            "b" => self.reverse(arguments),
+            "Z" => self.insert_point(arguments),
+            "z" => self.remove_point(arguments),
+            "D" => return Response::Detach,
+            "k" => return Response::Kill,
+            "H" => String::from("OK"),
+            "q" => self.query(arguments),
+            _ => String::new(),
+        };
+        Response::Reply(reply)
+    }
+
+    /// Runs one frame of a `c` packet. Returns the stop reply once the run stops, `first` is set
+    /// for the frame that starts the run so it can continue from a breakpoint.
+    pub fn resume(&mut self, first: bool) -> Option<String> {
+        if !first {
+            if let Some(id) = self.chip8.check_breakpoints() {
+                return Some(self.stop_reply(&RunResult { reason: StopReason::BreakpointHit(id), instructions: 0, frames: 0 }));
+            }
+        }
+        let result = self.chip8.run_until(&[RunCondition::Frames(1), RunCondition::Halt]);
+        match result.reason {
+            StopReason::Frames => None,
+            _ => Some(self.stop_reply(&result)),
+        }
+    }
+
+    pub fn stop_reply(&self, result: &RunResult) -> String {
+        match result.reason {
+            StopReason::WatchpointHit(hit) => {
+                let kind = self.watchpoints.iter()
+                    .find(|watchpoint| watchpoint.3.contains(&hit.id))
+                    .map(|watchpoint| watchpoint.0)
+                    .unwrap_or(if hit.access.kind == AccessKind::Read { b'3' } else { b'2' });
+                let name = match kind {
+                    b'3' => "rwatch",
+                    b'4' => "awatch",
+                    _ => "watch",
+                };
+                format!("T05{}:{:x};", name, hit.access.address)
+            },
+            StopReason::Halt => String::from("S04"),
+            _ => String::from("S05"),
+        }
+    }
+
+    /// Serves one debugger connection until GDB detaches, kills the target or disconnects.
+    /// Returns true if the target was killed.
+    pub fn serve(&mut self, mut stream: TcpStream) -> io::Result<bool> {
+        let mut decoder = PacketDecoder::new();
+        let mut buffer = [0u8; PACKET_SIZE];
+        loop {
+            let count = stream.read(&mut buffer)?;
+            if count == 0 {
+                return Ok(false);
+            }
+            for input in decoder.push(&buffer[..count]) {
+                let packet = match input {
+                    Input::Packet(packet) => packet,
+                    Input::BadChecksum => {
+                        stream.write_all(b"-")?;
+                        continue;
+                    },
+                    Input::Interrupt => {
+                        stream.write_all(&encode_packet("S02"))?;
+                        continue;
+                    },
+                };
+                stream.write_all(b"+")?;
+                debug!("gdb: {}", packet);
+                let reply = match self.handle_packet(&packet) {
+                    Response::Reply(reply) => reply,
+                    Response::Continue => self.run(&mut stream, &mut decoder)?,
+                    Response::Detach => {
+                        stream.write_all(&encode_packet("OK"))?;
+                        return Ok(false);
+                    },
+                    Response::Kill => return Ok(true),
+                };
+                stream.write_all(&encode_packet(&reply))?;
+            }
+        }
+    }
+
+    fn run(&mut self, stream: &mut TcpStream, decoder: &mut PacketDecoder) -> io::Result<String> {
+        let frame = Duration::new(0, 1_000_000_000 / FRAMES_PER_SECOND);
+        let mut buffer = [0u8; PACKET_SIZE];
+        let mut first = true;
+        stream.set_nonblocking(true)?;
+        let reply = loop {
+            if let Some(reply) = self.resume(first) {
+                break reply;
+            }
+            first = false;
+            match stream.read(&mut buffer) {
+                Ok(0) => break String::from("S02"),
+                Ok(count) => {
+                    if decoder.push(&buffer[..count]).contains(&Input::Interrupt) {
+                        break String::from("S02");
+                    }
+                },
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => (),
+                Err(error) => {
+                    stream.set_nonblocking(false)?;
+                    return Err(error);
+                },
+            }
+            thread::sleep(frame);
+        };
+        stream.set_nonblocking(false)?;
+        Ok(reply)
+    }
+
+    fn read_register(&self, register: usize) -> String {
+        match register {
+            I_REGISTER => hex_u16(self.chip8.get_address_register()),
+            PC_REGISTER => hex_u16(self.chip8.get_program_counter()),
+            SP_REGISTER => format!("{:02x}", self.chip8.get_stack().len()),
+            DT_REGISTER => format!("{:02x}", self.chip8.get_delay_timer()),
+            ST_REGISTER => format!("{:02x}", self.chip8.get_sound_timer()),
+            _ => format!("{:02x}", self.chip8.get_data_register(register as u8)),
+        }
+    }
+
+    fn write_register(&mut self, register: usize, value: &[u8]) -> bool {
+        let wide = register == I_REGISTER || register == PC_REGISTER;
+        if register >= REGISTER_NUMBERS || value.len() != if wide { 2 } else { 1 } {
+            return false;
+        }
+        match register {
+            I_REGISTER => self.chip8.set_address_register(value[0] as u16 | (value[1] as u16) << 8),
+            PC_REGISTER => return self.chip8.set_program_counter(value[0] as u16 | (value[1] as u16) << 8).is_ok(),
+            SP_REGISTER => {
+                let mut stack = self.chip8.get_stack().to_vec();
+                stack.resize(value[0] as usize, 0);
+                self.chip8.set_stack(&stack);
+            },
+            DT_REGISTER => self.chip8.set_delay_timer(value[0]),
+            ST_REGISTER => self.chip8.set_sound_timer(value[0]),
+            _ => self.chip8.set_data_register(register as u8, value[0]),
+        }
+        true
+    }
+
+    fn write_registers(&mut self, arguments: &str) -> String {
+        let bytes = match decode_hex(arguments) {
+            Some(ref bytes) if bytes.len() == REGISTER_COUNT + 7 => bytes.clone(),
+            _ => return String::from("E00"),
+        };
+        // PC first, so that a packet with an invalid PC changes nothing
+        let pc_offset = REGISTER_COUNT + 2;
+        if !self.write_register(PC_REGISTER, &bytes[pc_offset..pc_offset + 2]) {
+            return String::from("E00");
+        }
+        let mut offset = 0;
+        for register in 0..REGISTER_NUMBERS {
+            let size = if register == I_REGISTER || register == PC_REGISTER { 2 } else { 1 };
+            self.write_register(register, &bytes[offset..offset + size]);
+            offset += size;
+        }
+        String::from("OK")
+    }
+
+    fn write_register_packet(&mut self, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, '=');
+        let register = parts.next().and_then(|register| usize::from_str_radix(register, 16).ok());
+        let value = parts.next().and_then(decode_hex);
+        match (register, value) {
+            (Some(register), Some(value)) if self.write_register(register, &value) => String::from("OK"),
+            _ => String::from("E00"),
+        }
+    }
+
+    fn read_memory_packet(&self, arguments: &str) -> String {
+        match parse_address_length(arguments) {
+            Some((address, length)) => {
+                let bytes = self.chip8.read_memory(address, length);
+                if bytes.is_empty() && length > 0 {
+                    String::from("E14")
+                } else {
+                    encode_hex(bytes)
+                }
+            },
+            None => String::from("E00"),
+        }
+    }
+
+    fn write_memory_packet(&mut self, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, ':');
+        let range = parts.next().and_then(parse_address_length);
+        let data = parts.next().and_then(decode_hex);
+        match (range, data) {
+            (Some((address, length)), Some(data)) if data.len() == length => {
+                if self.chip8.read_memory(address, length).len() != length {
+                    return String::from("E14");
+                }
+                self.chip8.write_memory(address, &data);
+                String::from("OK")
+            },
+            _ => String::from("E00"),
+        }
+    }
+
+    /// Sets PC to the optional address of `s` and `c` packets. Returns false for an address that
+    /// is invalid or outside of memory.
+    fn set_resume_address(&mut self, arguments: &str) -> bool {
+        if arguments.is_empty() {
+            return true;
+        }
+        match u16::from_str_radix(arguments, 16) {
+            Ok(address) => self.chip8.set_program_counter(address).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn insert_point(&mut self, arguments: &str) -> String {
+        let (kind, address, length) = match parse_point(arguments) {
+            Some(point) => point,
+            None => return String::from("E00"),
+        };
+        let end = address.saturating_add(length.max(1) - 1);
+        match kind {
+            b'0' | b'1' => {
+                if !self.breakpoints.iter().any(|breakpoint| breakpoint.0 == address) {
+                    let id = self.chip8.breakpoints_mut().add(BreakpointKind::Address(address));
+                    self.breakpoints.push((address, id));
+                }
+            },
+            b'2' | b'3' | b'4' => {
+                let kinds: &[WatchKind] = match kind {
+                    b'2' => &[WatchKind::Write],
+                    b'3' => &[WatchKind::Read],
+                    _ => &[WatchKind::Read, WatchKind::Write],
+                };
+                let ids = kinds.iter().map(|watch_kind| self.chip8.watchpoints_mut().add(address, end, *watch_kind)).collect();
+                self.watchpoints.push((kind, address, end, ids));
+            },
+            _ => return String::new(),
+        }
+        String::from("OK")
+    }
+
+    fn remove_point(&mut self, arguments: &str) -> String {
+        let (kind, address, length) = match parse_point(arguments) {
+            Some(point) => point,
+            None => return String::from("E00"),
+        };
+        let end = address.saturating_add(length.max(1) - 1);
+        match kind {
+            b'0' | b'1' => {
+                if let Some(index) = self.breakpoints.iter().position(|breakpoint| breakpoint.0 == address) {
+                    let (_, id) = self.breakpoints.remove(index);
+                    self.chip8.breakpoints_mut().remove(id);
+                }
+            },
+            b'2' | b'3' | b'4' => {
+                if let Some(index) = self.watchpoints.iter().position(|watchpoint| watchpoint.0 == kind && watchpoint.1 == address && watchpoint.2 == end) {
+                    let (_, _, _, ids) = self.watchpoints.remove(index);
+                    for id in ids {
+                        self.chip8.watchpoints_mut().remove(id);
+                    }
+                }
+            },
+            _ => return String::new(),
+        }
+        String::from("OK")
+    }
+
+    fn reverse(&mut self, arguments: &str) -> String {
+        let reason = match arguments {
+            "s" if self.chip8.step_back() => Some(StopReason::Instructions),
+            "s" => None,
+            "c" => self.chip8.reverse_continue(),
+            _ => return String::new(),
+        };
+        match reason {
+            Some(reason) => self.stop_reply(&RunResult { reason: reason, instructions: 0, frames: 0 }),
+            None => String::from("T05replaylog:begin;"),
+        }
+    }
+
+    fn query(&self, arguments: &str) -> String {
+        if arguments.starts_with("Supported") {
+            return format!("PacketSize={:x};qXfer:features:read+;ReverseStep+;ReverseContinue+", PACKET_SIZE);
+        }
+        if let Some(range) = arguments.strip_prefix("Xfer:features:read:target.xml:") {
+            return match parse_address_length(range) {
+                Some((offset, length)) => {
+                    let offset = (offset as usize).min(TARGET_XML.len());
+                    let end = offset.saturating_add(length).min(TARGET_XML.len());
+                    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+                    format!("{}{}", marker, &TARGET_XML[offset..end])
+                },
+                None => String::from("E00"),
+            };
+        }
+        match arguments {
+            "Attached" => String::from("1"),
+            "C" => String::from("QC1"),
+            "fThreadInfo" => String::from("m1"),
+            "sThreadInfo" => String::from("l"),
+            _ => String::new(),
+        }
+    }
+}
+
+fn hex_u16(value: u16) -> String {
+    format!("{:02x}{:02x}", value & 0xFF, value >> 8)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|index| hex.get(index..index + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+fn parse_address_length(arguments: &str) -> Option<(u16, usize)> {
+    let mut parts = arguments.splitn(2, ',');
+    let address = parts.next().and_then(|address| u16::from_str_radix(address, 16).ok())?;
+    let length = parts.next().and_then(|length| usize::from_str_radix(length, 16).ok())?;
+    Some((address, length))
+}
+
+fn parse_point(arguments: &str) -> Option<(u8, u16, u16)> {
+    let mut parts = arguments.splitn(3, ',');
+    let kind = parts.next().and_then(|kind| kind.bytes().next())?;
+    let address = parts.next().and_then(|address| u16::from_str_radix(address, 16).ok())?;
+    let length = parts.next().and_then(|length| u16::from_str_radix(length.split(';').next().unwrap_or(""), 16).ok())?;
+    Some((kind, address, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::{encode_packet, GdbStub, Input, PacketDecoder, Response, PACKET_SIZE};
+    use super::super::{create_test_chip8, Inspect};
+
+    const ROM: [u8; 10] = [
+        0x60, 0x7B, // LD V0, 123
+        0xA3, 0x00, // LDI 0x300
+        0xF0, 0x33, // BCD V0
+        0x70, 0x01, // ADD V0, 1
+        0x12, 0x04, // JMP 0x204
+    ];
+
+    fn create_stub() -> GdbStub {
+        create_stub_with_speed(10)
+    }
+
+    fn create_stub_with_speed(instructions_per_frame: u32) -> GdbStub {
+        GdbStub::new(create_test_chip8(instructions_per_frame, ROM.to_vec()))
+    }
+
+    fn reply(stub: &mut GdbStub, packet: &str) -> String {
+        match stub.handle_packet(packet) {
+            Response::Reply(reply) => reply,
+            response => panic!("expected reply, got {:?}", response),
+        }
+    }
+
+    #[test]
+    fn encode_packet_with_checksum() {
+        assert!(encode_packet("OK") == b"$OK#9a".to_vec());
+        assert!(encode_packet("a#b") == b"$a}\x03b#43".to_vec());
+    }
+
+    #[test]
+    fn decode_packets_and_interrupt() {
+        let mut under_test = PacketDecoder::new();
+
+        let first = under_test.push(b"+$g#67$m2");
+        let second = under_test.push(b"00,2#5d\x03$g#00");
+
+        assert!(first == vec![Input::Packet(String::from("g"))]);
+        assert!(second == vec![Input::Packet(String::from("m200,2")), Input::Interrupt, Input::BadChecksum]);
+    }
+
+    #[test]
+    fn oversized_packet_dropped() {
+        let mut under_test = PacketDecoder::new();
+
+        let first = under_test.push(b"$m");
+        let second = under_test.push(&[b'0'; PACKET_SIZE]);
+        let third = under_test.push(b"#00$g#67");
+
+        assert!(first.is_empty());
+        assert!(second == vec![Input::BadChecksum]);
+        assert!(third == vec![Input::Packet(String::from("g"))]);
+    }
+
+    #[test]
+    fn read_and_write_registers() {
+        let mut under_test = create_stub();
+        under_test.chip8_mut().set_address_register(0x300);
+
+        let registers = reply(&mut under_test, "g");
+        let written = reply(&mut under_test, "P11=0403");
+
+        assert!(registers == "0000000000000000000000000000000000030002000000");
+        assert!(written == "OK");
+        assert!(under_test.chip8().get_program_counter() == 0x304);
+        assert!(reply(&mut under_test, "p11") == "0403");
+    }
+
+    #[test]
+    fn program_counter_outside_memory_rejected() {
+        let mut under_test = create_stub();
+
+        let written = reply(&mut under_test, "P11=ff0f");
+        let all = reply(&mut under_test, "G0000000000000000000000000000000000030010000000");
+        let step = reply(&mut under_test, "sfff");
+        let resume = under_test.handle_packet("c1000");
+
+        assert!(written == "E00" && all == "E00" && step == "E00");
+        assert!(resume == Response::Reply(String::from("E00")));
+        assert!(under_test.chip8().get_program_counter() == 0x200);
+        assert!(reply(&mut under_test, "P11=fe0f") == "OK");
+    }
+
+    #[test]
+    fn read_and_write_memory() {
+        let mut under_test = create_stub();
+
+        let written = reply(&mut under_test, "M300,2:abcd");
+        let read = reply(&mut under_test, "m2fe,4");
+
+        assert!(written == "OK");
+        assert!(read == "0000abcd");
+        assert!(reply(&mut under_test, "m1000,1") == "E14");
+    }
+
+    #[test]
+    fn step_and_breakpoint() {
+        let mut under_test = create_stub();
+
+        let step = reply(&mut under_test, "s");
+        reply(&mut under_test, "Z0,206,2");
+        let first = under_test.resume(true);
+        let second = under_test.resume(true);
+        reply(&mut under_test, "z0,206,2");
+
+        assert!(step == "S05");
+        assert!(first == Some(String::from("S05")));
+        assert!(under_test.chip8().get_program_counter() == 0x206);
+        assert!(second == Some(String::from("S05")));
+        assert!(under_test.chip8().breakpoints().is_empty());
+    }
+
+    #[test]
+    fn reverse_step_and_continue() {
+        let mut under_test = create_stub();
+        reply(&mut under_test, "Z0,206,2");
+        under_test.resume(true);
+        reply(&mut under_test, "z0,206,2");
+        reply(&mut under_test, "Z2,300,1");
+        for _ in 0..4 {
+            reply(&mut under_test, "s");
+        }
+
+        let step = reply(&mut under_test, "bs");
+        let stepped = under_test.chip8().get_instruction_count();
+        let watch = reply(&mut under_test, "bc");
+
+        assert!(step == "S05");
+        assert!(stepped == 6);
+        assert!(watch == "T05watch:300;");
+        assert!(under_test.chip8().get_instruction_count() == 3);
+        let start = reply(&mut under_test, "bc");
+        assert!(start == "T05replaylog:begin;");
+        assert!(under_test.chip8().get_program_counter() == 0x200);
+    }
+
+    #[test]
+    fn resume_stops_at_breakpoint_between_frames() {
+        let mut under_test = create_stub_with_speed(1);
+        under_test.chip8_mut().set_program_counter(0x206).unwrap();
+        reply(&mut under_test, "Z0,208,2");
+
+        let first = under_test.resume(true);
+        let second = under_test.resume(false);
+
+        assert!(first.is_none());
+        assert!(second == Some(String::from("S05")));
+    }
+
+    #[test]
+    fn write_watchpoint_stop_reply() {
+        let mut under_test = create_stub();
+        reply(&mut under_test, "Z2,301,1");
+
+        let result = under_test.resume(true);
+
+        assert!(result == Some(String::from("T05watch:301;")));
+    }
+
+    #[test]
+    fn target_xml_in_chunks() {
+        let mut under_test = create_stub();
+
+        let first = reply(&mut under_test, "qXfer:features:read:target.xml:0,f");
+        let rest = reply(&mut under_test, "qXfer:features:read:target.xml:f,1000");
+
+        assert!(first == "m<?xml version=\"");
+        assert!(rest.starts_with("l1.0\"?>"));
+        assert!(rest.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>"));
+    }
+
+    #[test]
+    fn target_xml_length_past_usize_max() {
+        let mut under_test = create_stub();
+
+        let result = reply(&mut under_test, "qXfer:features:read:target.xml:f,ffffffffffffffff");
+
+        assert!(result.starts_with("l1.0\"?>"));
+    }
+
+    #[test]
+    fn serve_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut stub = create_stub();
+            let (stream, _) = listener.accept().unwrap();
+            stub.serve(stream).unwrap()
+        });
+        let mut client = TcpStream::connect(address).unwrap();
+
+        client.write_all(&encode_packet("m200,2")).unwrap();
+        let mut response = [0u8; 9];
+        client.read_exact(&mut response).unwrap();
+        client.write_all(b"+").unwrap();
+        client.write_all(&encode_packet("k")).unwrap();
+        let killed = server.join().unwrap();
+
+        assert!(&response == b"+$607b#ff");
+        assert!(killed);
+    }
+}
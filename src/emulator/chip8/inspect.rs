@@ -0,0 +1,181 @@
+use core::cmp;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{is_program_address, Chip8};
+use super::component::memory::MEM_SIZE;
+use super::draws::DrawCall;
+
+/// Read and write access to the machine state of a `Chip8`, for debuggers and automation tools.
+///
+/// Register indices must be in `0x0..=0xF`. Memory ranges that reach past the end of RAM are
+/// truncated instead of panicking. PC is only set to addresses with a whole opcode inside memory.
+pub trait Inspect {
+    fn get_data_register(&self, register: u8) -> u8;
+    fn set_data_register(&mut self, register: u8, value: u8);
+    fn get_address_register(&self) -> u16;
+    fn set_address_register(&mut self, value: u16);
+    fn get_program_counter(&self) -> u16;
+    fn set_program_counter(&mut self, address: u16) -> Result<(), String>;
+    fn get_stack(&self) -> &[u16];
+    fn set_stack(&mut self, stack: &[u16]);
+    fn get_delay_timer(&self) -> u8;
+    fn set_delay_timer(&mut self, value: u8);
+    fn get_sound_timer(&self) -> u8;
+    fn set_sound_timer(&mut self, value: u8);
+    fn get_pressed_keys(&self) -> u16;
+    fn read_memory(&self, address: u16, length: usize) -> &[u8];
+    fn write_memory(&mut self, address: u16, data: &[u8]);
+    /// Starts or stops recording draw calls for `get_draw_calls`.
+    fn set_draw_log(&mut self, enabled: bool);
+    /// The CLS and DXYN calls of the previous and the current frame, oldest first.
+    fn get_draw_calls(&self) -> Vec<DrawCall>;
+}
+
+impl Inspect for Chip8 {
+    fn get_data_register(&self, register: u8) -> u8 {
+        self.registers.get_data_register_value(register)
+    }
+
+    fn set_data_register(&mut self, register: u8, value: u8) {
+        self.registers.set_data_register_by_value(register, value)
+    }
+
+    fn get_address_register(&self) -> u16 {
+        self.registers.get_address_register_value()
+    }
+
+    fn set_address_register(&mut self, value: u16) {
+        self.registers.set_address_register_value(value)
+    }
+
+    fn get_program_counter(&self) -> u16 {
+        self.pc
+    }
+
+    fn set_program_counter(&mut self, address: u16) -> Result<(), String> {
+        if !is_program_address(address) {
+            return Err(format!("PC 0x{:X} is outside of memory", address));
+        }
+        self.pc = address;
+        Ok(())
+    }
+
+    fn get_stack(&self) -> &[u16] {
+        self.stack.as_slice()
+    }
+
+    fn set_stack(&mut self, stack: &[u16]) {
+        self.stack = stack.to_vec()
+    }
+
+    fn get_delay_timer(&self) -> u8 {
+        self.delay_timer.get_value()
+    }
+
+    fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer.set_value(value)
+    }
+
+    fn get_sound_timer(&self) -> u8 {
+        self.sound_timer.get_value()
+    }
+
+    fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer.set_value(value)
+    }
+
+    fn get_pressed_keys(&self) -> u16 {
+        self.input.get_pressed_keys()
+    }
+
+    fn read_memory(&self, address: u16, length: usize) -> &[u8] {
+        let (start, end) = clamp_range(address, length);
+        &self.memory.as_slice()[start..end]
+    }
+
+    fn write_memory(&mut self, address: u16, data: &[u8]) {
+        let (start, end) = clamp_range(address, data.len());
+        self.memory.as_mut_slice()[start..end].copy_from_slice(&data[..end - start]);
+    }
+
+    fn set_draw_log(&mut self, enabled: bool) {
+        if enabled { self.enable_draw_log() } else { self.disable_draw_log() }
+    }
+
+    fn get_draw_calls(&self) -> Vec<DrawCall> {
+        self.draw_log.as_ref().map(|draw_log| draw_log.calls()).unwrap_or_default()
+    }
+}
+
+fn clamp_range(address: u16, length: usize) -> (usize, usize) {
+    let start = cmp::min(address as usize, MEM_SIZE);
+    (start, cmp::min(start.saturating_add(length), MEM_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Inspect;
+    use super::super::Chip8;
+    use super::super::component::memory::MEM_SIZE;
+
+    #[test]
+    fn set_get_data_register_success() {
+        let mut under_test = Chip8::new();
+        under_test.set_data_register(0xA, 0x42);
+
+        let result = under_test.get_data_register(0xA);
+
+        assert!(result == 0x42);
+    }
+
+    #[test]
+    fn set_program_counter_rejects_addresses_outside_memory() {
+        let mut under_test = Chip8::new();
+
+        let result = under_test.set_program_counter((MEM_SIZE - 1) as u16);
+
+        assert!(result.is_err());
+        assert!(under_test.set_program_counter((MEM_SIZE - 2) as u16).is_ok());
+        assert!(under_test.get_program_counter() == (MEM_SIZE - 2) as u16);
+    }
+
+    #[test]
+    fn set_stack_success() {
+        let mut under_test = Chip8::new();
+        under_test.set_stack(&[0x202, 0x310]);
+
+        let result = under_test.get_stack();
+
+        assert!(result == [0x202, 0x310]);
+    }
+
+    #[test]
+    fn write_read_memory_success() {
+        let mut under_test = Chip8::new();
+        under_test.write_memory(0x300, &[1, 2, 3]);
+
+        let result = under_test.read_memory(0x300, 3);
+
+        assert!(result == [1, 2, 3]);
+    }
+
+    #[test]
+    fn read_memory_truncated_at_end() {
+        let under_test = Chip8::new();
+
+        let result = under_test.read_memory((MEM_SIZE - 2) as u16, 16);
+
+        assert!(result.len() == 2);
+    }
+
+    #[test]
+    fn write_memory_truncated_at_end() {
+        let mut under_test = Chip8::new();
+        under_test.write_memory((MEM_SIZE - 1) as u16, &[7, 8, 9]);
+
+        let result = under_test.read_memory((MEM_SIZE - 1) as u16, 1);
+
+        assert!(result == [7]);
+    }
+}
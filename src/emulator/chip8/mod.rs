@@ -1,38 +1,65 @@
 mod component;
+pub mod breakpoint;
+pub mod builder;
+pub mod control;
+pub mod coverage;
+#[cfg(feature = "std")]
+pub mod dap;
+#[cfg(feature = "std")]
+pub mod debugger;
+#[cfg(feature = "std")]
+pub mod diff;
+pub mod draws;
+#[cfg(feature = "std")]
+pub mod gdb;
+pub mod inspect;
+pub mod observer;
+pub mod profile;
+pub mod random;
+pub mod reverse;
+pub mod snapshot;
+pub mod sourcemap;
+pub mod sprites;
+pub mod symbols;
+#[cfg(feature = "std")]
+pub mod trace;
+pub mod watchpoint;
 
-use std::string::String;
-use std::vec::Vec;
-use rand::Rng;
-use rand::StdRng;
+use core::mem;
+use core::time::Duration;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 use self::component::timer::Timer;
 use self::component::input::Input;
 use self::component::screen::Screen;
 use self::component::registers::Registers;
-use self::component::memory;
 use self::component::memory::Memory;
 use self::component::opcode::Opcode;
 use self::component::opcode::ASM::*;
+use self::breakpoint::Breakpoints;
+use self::draws::{DrawLog, DrawOp};
+use self::observer::{Observers, Step};
+use self::random::XorShiftRandom;
+use self::reverse::ExecutionHistory;
+use self::symbols::symbolize_asm;
+use self::watchpoint::Watchpoints;
 use emulator::Emulator;
+use emulator::keypad::HexKey;
+use emulator::scheduler::FrameScheduler;
 
-const FONTSET: [u8; 80] = [
-    0xf0, 0x90, 0x90, 0x90, 0xf0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xf0, 0x10, 0xf0, 0x80, 0xf0, // 2
-    0xf0, 0x10, 0xf0, 0x10, 0xf0, // 3
-    0x90, 0x90, 0xf0, 0x10, 0x10, // 4
-    0xf0, 0x80, 0xf0, 0x10, 0xf0, // 5
-    0xf0, 0x80, 0xf0, 0x90, 0xf0, // 6
-    0xf0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xf0, 0x90, 0xf0, 0x90, 0xf0, // 8
-    0xf0, 0x90, 0xf0, 0x10, 0xf0, // 9
-    0xf0, 0x90, 0xf0, 0x90, 0x90, // A
-    0xe0, 0x90, 0xe0, 0x90, 0xe0, // B
-    0xf0, 0x80, 0x80, 0x80, 0xf0, // C
-    0xe0, 0x90, 0x90, 0x90, 0xe0, // D
-    0xf0, 0x80, 0xf0, 0x80, 0xf0, // E
-    0xf0, 0x80, 0xf0, 0x80, 0x80, // F
-];
+pub use self::breakpoint::{Breakpoint, BreakpointKind, Condition, OpcodePattern};
+pub use self::builder::{Chip8Builder, Config, ConfigError, Quirks, Variant};
+pub use self::control::{RunCondition, RunResult, StopReason};
+pub use self::component::memory::{AccessKind, MemoryAccess, MEM_SIZE, FONT_ADDRESS, PROGRAM_ADDRESS};
+pub use self::component::registers::REGISTER_COUNT;
+pub use self::inspect::Inspect;
+pub use self::observer::StepObserver;
+pub use self::random::RandomSource;
+pub use self::snapshot::StateError;
+pub use self::symbols::{Symbol, SymbolKind, SymbolTable};
+pub use self::watchpoint::{WatchKind, Watchpoint, WatchpointHit};
 
 pub struct Chip8 {
     memory: Memory,
@@ -44,33 +71,60 @@ pub struct Chip8 {
     screen: Screen,
     pc: u16,
     title: String,
-    rng: StdRng,
+    random: Box<RandomSource>,
+    custom_random: bool,
     need_redraw: bool,
     should_beep: bool,
+    config: Config,
+    instructions_in_frame: u32,
+    instruction_count: u64,
+    frame_count: u64,
+    scheduler: FrameScheduler,
+    breakpoints: Breakpoints,
+    watchpoints: Watchpoints,
+    watchpoint_hit: Option<WatchpointHit>,
+    observers: Observers,
+    symbols: SymbolTable,
+    history: Option<ExecutionHistory>,
+    draw_log: Option<DrawLog>,
+    last_sprite: Option<(u16, usize)>,
+    rom: Vec<u8>,
+    halted: bool,
+}
+
+/// Whether PC may be set to `address`, the whole opcode at it has to be inside memory.
+fn is_program_address(address: u16) -> bool {
+    address as usize <= MEM_SIZE - 2
 }
 
 fn retrieve_op(memory: &Memory, address: u16) -> Opcode {
-    Opcode::new(((memory.retrieve_value_from_address(address) as u16) << 8) + memory.retrieve_value_from_address(address + 1) as u16)
+    let memory = memory.as_slice();
+    Opcode::new(((memory[address as usize] as u16) << 8) + memory[address as usize + 1] as u16)
 
 }
 
+/// A seeded machine without the logo and with `rom` loaded, for tests.
+#[cfg(test)]
+pub fn create_test_chip8(instructions_per_frame: u32, rom: Vec<u8>) -> Chip8 {
+    let mut chip8 = Chip8Builder::new().log_logo(false).seed(1).instructions_per_frame(instructions_per_frame).build().unwrap();
+    chip8.load(rom);
+    chip8
+}
+
+/// V0 to VF, DT and ST.
+fn register_values(chip8: &Chip8) -> [u8; REGISTER_COUNT + 2] {
+    let mut values = [0; REGISTER_COUNT + 2];
+    for (register, value) in values.iter_mut().take(REGISTER_COUNT).enumerate() {
+        *value = chip8.get_data_register(register as u8);
+    }
+    values[REGISTER_COUNT] = chip8.get_delay_timer();
+    values[REGISTER_COUNT + 1] = chip8.get_sound_timer();
+    values
+}
+
 impl Default for Chip8 {
     fn default() -> Chip8 {
-        log_logo();
-        Chip8 {
-            pc: memory::PROGRAM_ADDRESS,
-            screen: Default::default(),
-            input: Default::default(),
-            sound_timer: Default::default(),
-            delay_timer: Default::default(),
-            registers: Default::default(),
-            memory: Default::default(),
-            stack: Vec::new(),
-            title: String::from("Chip 8"),
-            rng: StdRng::new().unwrap(),
-            need_redraw: false,
-            should_beep: false,
-        }
+        Chip8::from_config(Default::default())
     }
 }
 
@@ -84,12 +138,23 @@ impl Emulator for Chip8 {
     }
 
     fn update(&mut self) {
-        if self.sound_timer.get_value() == 1 { self.beep() }
-        self.delay_timer.tick_down();
-        self.sound_timer.tick_down();
-        let mut opcode = retrieve_op(&self.memory, self.pc);
-        self.pc += 2;
-        self.execute_op(&mut opcode);
+        while !self.step() {}
+    }
+
+    fn advance(&mut self, elapsed: Duration) -> u32 {
+        let frames = self.scheduler.frames_due(elapsed);
+        for _ in 0..frames {
+            self.update();
+        }
+        frames
+    }
+
+    fn step(&mut self) -> bool {
+        Chip8::step(self)
+    }
+
+    fn reset(&mut self) {
+        Chip8::reset(self)
     }
 
     fn get_name(&self) -> &str {
@@ -97,17 +162,21 @@ impl Emulator for Chip8 {
     }
 
     fn load(&mut self, game_data: Vec<u8>) {
-        self.memory.store_from_address_on(game_data.as_slice(), memory::PROGRAM_ADDRESS);
-        self.memory.store_from_address_on(&FONTSET, memory::FONT_ADDRESS);
+        self.rom = game_data.clone();
+        self.memory.store_from_address_on(game_data.as_slice(), self.config.program_address);
+        self.memory.store_from_address_on(self.config.font.as_slice(), self.config.font_address);
     }
 
-    fn press_key(&mut self, key: u16) {
-        self.input.press_key(key)
+    fn press_key(&mut self, key: HexKey) {
+        self.input.press_key(key.mask());
+        self.record_input();
     }
 
-    fn release_key(&mut self, key: u16) {
-        self.input.release_key(key)
+    fn release_key(&mut self, key: HexKey) {
+        self.input.release_key(key.mask());
+        self.record_input();
     }
+
     fn needs_redraw(&mut self) -> bool {
         let redraw = self.need_redraw;
         self.need_redraw = false;
@@ -119,6 +188,37 @@ impl Emulator for Chip8 {
         self.should_beep = false;
         beep
     }
+
+    fn is_sound_playing(&self) -> bool {
+        self.sound_timer.get_value() > 0
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        Chip8::save_state(self)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        Chip8::load_state(self, state).map_err(|error| format!("{}", error))
+    }
+
+    fn inspect(&self) -> Option<&Inspect> {
+        Some(self)
+    }
+
+    fn inspect_mut(&mut self) -> Option<&mut Inspect> {
+        Some(self)
+    }
+}
+
+/// Formats a single opcode the way the debugging tools show it, e.g. `0xD015: DRW(0, 1, 5)`.
+pub fn disassemble(opcode: u16) -> String {
+    format!("{}", Opcode::new(opcode))
+}
+
+/// Like `disassemble`, but names jump, call and `LDI` targets that have a symbol, e.g.
+/// `0x2208: CALL draw_player`.
+pub fn disassemble_with_symbols(opcode: u16, symbols: &SymbolTable) -> String {
+    format!("0x{:04X}: {}", opcode, symbolize_asm(opcode, symbols))
 }
 
 impl Chip8 {
@@ -126,16 +226,215 @@ impl Chip8 {
         Chip8 { ..Default::default() }
     }
 
+    fn from_config(config: Config) -> Chip8 {
+        if config.log_logo { log_logo(); }
+        let random = match config.seed {
+            Some(seed) => XorShiftRandom::new(seed),
+            None => XorShiftRandom::from_entropy(),
+        };
+        Chip8 {
+            pc: config.program_address,
+            screen: Default::default(),
+            input: Default::default(),
+            sound_timer: Default::default(),
+            delay_timer: Default::default(),
+            registers: Default::default(),
+            memory: Default::default(),
+            stack: Vec::new(),
+            title: String::from("Chip 8"),
+            random: Box::new(random),
+            custom_random: false,
+            need_redraw: false,
+            should_beep: false,
+            config: config,
+            instructions_in_frame: 0,
+            instruction_count: 0,
+            frame_count: 0,
+            scheduler: Default::default(),
+            breakpoints: Breakpoints::new(),
+            watchpoints: Watchpoints::new(),
+            watchpoint_hit: None,
+            observers: Observers::new(),
+            symbols: SymbolTable::new(),
+            history: None,
+            draw_log: None,
+            last_sprite: None,
+            rom: Vec::new(),
+            halted: false,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn set_random_source(&mut self, random: Box<RandomSource>) {
+        self.random = random;
+        self.custom_random = true;
+    }
+
+    pub fn reset(&mut self) {
+        let rom = self.rom.clone();
+        let mut config = self.config.clone();
+        let log_logo = config.log_logo;
+        config.log_logo = false;
+        let random = mem::replace(&mut self.random, Box::new(XorShiftRandom::new(0)));
+        let custom_random = self.custom_random;
+        let breakpoints = mem::replace(&mut self.breakpoints, Breakpoints::new());
+        let watchpoints = mem::replace(&mut self.watchpoints, Watchpoints::new());
+        let observers = mem::replace(&mut self.observers, Observers::new());
+        let symbols = mem::replace(&mut self.symbols, SymbolTable::new());
+        let history = self.history.take();
+        let draw_log = self.draw_log.take().map(|_| DrawLog::new());
+        *self = Chip8::from_config(config);
+        self.draw_log = draw_log;
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        self.observers = observers;
+        self.symbols = symbols;
+        if custom_random {
+            self.set_random_source(random);
+        }
+        self.config.log_logo = log_logo;
+        self.load(rom);
+        if history.is_some() {
+            self.history = history;
+            self.restart_history();
+        }
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+    pub fn watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    pub fn watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.watchpoints
+    }
+
+    pub fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    pub fn observers_mut(&mut self) -> &mut Observers {
+        &mut self.observers
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// Whether the last instruction jumped to itself or was a CALL that would overflow the stack.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Executes one instruction and returns true at the end of a frame. When there are observers
+    /// or watchpoints the memory accesses of the instruction are recorded, the observers are
+    /// notified and the watchpoints checked.
+    pub fn step(&mut self) -> bool {
+        self.watchpoint_hit = None;
+        if self.observers.is_empty() && self.watchpoints.is_empty() {
+            return self.execute_step();
+        }
+        let recording = self.memory.is_recording();
+        if !recording {
+            self.memory.set_recording(true);
+        }
+        let first_access = self.memory.access_count();
+        let (cycle, frame, pc, registers) = (self.instruction_count, self.frame_count, self.pc, register_values(self));
+        let opcode = retrieve_op(&self.memory, pc).as_u16();
+        let end_of_frame = self.execute_step();
+        let accesses = self.memory.accesses_since(first_access);
+        if !recording {
+            self.memory.set_recording(false);
+        }
+        if let Some((id, access)) = self.watchpoints.check(&accesses) {
+            self.watchpoint_hit = Some(WatchpointHit { id: id, pc: pc, opcode: opcode, access: access });
+        }
+        if !self.observers.is_empty() {
+            let step = Step {
+                cycle: cycle,
+                frame: frame,
+                pc: pc,
+                opcode: opcode,
+                registers: registers,
+                accesses: &accesses,
+                end_of_frame: end_of_frame,
+            };
+            let mut observers = mem::replace(&mut self.observers, Observers::new());
+            observers.notify(self, &step);
+            self.observers = observers;
+        }
+        end_of_frame
+    }
+
+    fn execute_step(&mut self) -> bool {
+        self.halted = false;
+        if self.history.is_some() {
+            self.record_history();
+        }
+        if self.instructions_in_frame == 0 {
+            if self.sound_timer.get_value() == 1 { self.beep() }
+            self.delay_timer.tick_down();
+            self.sound_timer.tick_down();
+        }
+        let mut opcode = retrieve_op(&self.memory, self.pc);
+        self.pc += 2;
+        self.execute_op(&mut opcode);
+        self.instructions_in_frame += 1;
+        self.instruction_count += 1;
+        if self.instructions_in_frame >= self.config.instructions_per_frame {
+            self.instructions_in_frame = 0;
+            self.frame_count += 1;
+            if let Some(ref mut draw_log) = self.draw_log {
+                draw_log.next_frame();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn record_history(&mut self) {
+        if let Some(mut history) = self.history.take() {
+            history.record(self);
+            self.history = Some(history);
+        }
+    }
+
+    fn record_input(&mut self) {
+        if let Some(ref mut history) = self.history {
+            history.record_input(self.instruction_count, self.input.get_pressed_keys());
+        }
+    }
+
     fn execute_op(&mut self, opcode: &mut Opcode) {
-        debug!("Executing {}", opcode);
+        if self.config.log_instructions { debug!("Executing {}", opcode); }
         match opcode.as_asm() {
             CLS => {
+                let before = self.screen_before_draw();
                 self.screen.clear();
                 self.need_redraw = true;
+                self.record_draw(DrawOp::Clear, before);
             },
             RET => self.return_from_subroutine(),
             SYS(address) => error!("RCA 1802 subroutine calls are not implemented - opcode {}, Soubroutine called at 0x{:X}", opcode, address),
-            JMP(address) => self.jump_to_address(address),
+            JMP(address) => {
+                self.halted = address == self.pc - 2;
+                self.jump_to_address(address)
+            },
             CALL(address) => self.call_subroutine(address),
             SE(register, value) => self.skip_if_register_equals_value(register, value),
             SNE(register, value) => self.skip_if_register_not_equals_value(register, value),
@@ -148,15 +447,15 @@ impl Chip8 {
             CP(register1, register2) => self.registers.set_data_register_by_register(register1, register2),
             OR(register1, register2) => {
                 self.registers.set_data_register_by_register(register1, register1 | register2);
-                self.registers.reset_vf_to_zero();
+                if self.config.quirks.vf_reset { self.registers.reset_vf_to_zero() }
             },
             AND(register1, register2) => {
                 self.registers.set_data_register_by_register(register1, register1 & register2);
-                self.registers.reset_vf_to_zero();
+                if self.config.quirks.vf_reset { self.registers.reset_vf_to_zero() }
             },
             XOR(register1, register2) => {
                 self.registers.set_data_register_by_register(register1, register1 ^ register2);
-                self.registers.reset_vf_to_zero();
+                if self.config.quirks.vf_reset { self.registers.reset_vf_to_zero() }
             },
             ADD(register1, register2) => {
                 let overflow = self.registers.add_data_register_with_register(register1, register1, register2);
@@ -193,12 +492,16 @@ impl Chip8 {
             SDTR(register) => self.delay_timer.set_value(self.registers.get_data_register_value(register)),
             SSTR(register) => self.sound_timer.set_value(self.registers.get_data_register_value(register)),
             ADDIR(register) => self.registers.add_address_register_with_register(register),
-            LDSPR(register) => self.registers.set_address_register_to_sprite_from_register(register),
+            LDSPR(register) => self.registers.set_address_register_to_sprite_from_register(register, self.config.font_address),
             BCD(register) => self.memory.store_binary_representation_of_value(self.registers.get_data_register_value(register), self.registers.get_address_register_value()),
-            STOR(register) => self.memory.store_from_address_on(self.registers.get_data_registers(0x0, register), self.registers.get_address_register_value()),
+            STOR(register) => {
+                self.memory.store_from_address_on(self.registers.get_data_registers(0x0, register), self.registers.get_address_register_value());
+                self.increment_address_register_after_load_store(register)
+            },
             READ(register) => {
                 let address_value = self.registers.get_address_register_value();
-                self.registers.store_until_register(register, address_value, &self.memory)
+                self.registers.store_until_register(register, address_value, &self.memory);
+                self.increment_address_register_after_load_store(register)
             }
             ERR => {
                 error!("Unknown opcode: {}", opcode);
@@ -212,10 +515,18 @@ impl Chip8 {
     }
 
     fn jump_to_v0_plus_value(&mut self, value: u16) {
-        let address = self.registers.get_data_register_value(0x0) as u16 + value;
+        let register = if self.config.quirks.jump_uses_vx { (value >> 8) as u8 } else { 0x0 };
+        let address = self.registers.get_data_register_value(register) as u16 + value;
         self.jump_to_address(address);
     }
 
+    fn increment_address_register_after_load_store(&mut self, register: u8) {
+        if self.config.quirks.load_store_increments_i {
+            let address = self.registers.get_address_register_value() + register as u16 + 1;
+            self.registers.set_address_register_value(address);
+        }
+    }
+
     fn skip_if_register_equals_value(&mut self, register: u8, value: u8) {
         let is_equal = self.registers.is_equal_to_value(register, value);
         self.skip_next_op_if(is_equal);
@@ -236,6 +547,13 @@ impl Chip8 {
     }
 
     fn call_subroutine(&mut self, to_address: u16) {
+        if self.stack.len() >= self.config.stack_depth {
+            // Stays on the CALL like a jump to itself, so the machine halts instead of the host
+            error!("Stack overflow calling subroutine at 0x{:X} from 0x{:X}, depth is {}", to_address, self.pc - 2, self.config.stack_depth);
+            self.pc -= 2;
+            self.halted = true;
+            return;
+        }
         debug!("Initiate subroutine at 0x{:X}, jumping from 0x{:X}", to_address, self.pc);
         self.stack.push(self.pc);
         self.pc = to_address;
@@ -246,15 +564,35 @@ impl Chip8 {
     }
 
     fn set_data_register_to_random(&mut self, register: u8, value: u8) {
-        let random = (self.rng.next_u32() as u8) & value;
+        let random = self.random.next_u8() & value;
         self.registers.set_data_register_by_value(register, random)
     }
 
     fn draw_sprite_and_set_vf_if_pixel_flipped_to_zero(&mut self, pos_x: u8, pos_y: u8, height: u8) {
+        let before = self.screen_before_draw();
         let sprite = self.memory.retrieve_range(self.registers.get_address_register_value(), height);
-        let pixel_flipped = self.screen.draw(self.registers.get_data_register_value(pos_x), self.registers.get_data_register_value(pos_y), sprite);
+        self.last_sprite = Some((self.registers.get_address_register_value(), sprite.len()));
+        let (pos_x, pos_y) = (self.registers.get_data_register_value(pos_x), self.registers.get_data_register_value(pos_y));
+        let pixel_flipped = if self.config.quirks.clip_sprites {
+            self.screen.draw(pos_x, pos_y, sprite)
+        } else {
+            self.screen.draw_wrapping(pos_x, pos_y, sprite)
+        };
         self.registers.set_data_register_by_value(0xF, if pixel_flipped { 1 } else { 0 });
         self.need_redraw = true;
+        let (address, height) = (self.registers.get_address_register_value(), sprite.len() as u8);
+        self.record_draw(DrawOp::Sprite { address: address, height: height, x: pos_x, y: pos_y, collision: pixel_flipped }, before);
+    }
+
+    fn screen_before_draw(&self) -> Option<Vec<bool>> {
+        self.draw_log.as_ref().map(|_| self.screen.retrieve_state().to_vec())
+    }
+
+    fn record_draw(&mut self, op: DrawOp, before: Option<Vec<bool>>) {
+        if let (Some(draw_log), Some(before)) = (self.draw_log.as_mut(), before) {
+            let (width, _) = self.screen.get_dimensions();
+            draw_log.record(self.frame_count, self.pc - 2, op, &before, self.screen.retrieve_state(), width);
+        }
     }
 
     fn wait_for_key_and_set_register_to_key_value(&mut self, register: u8) {
@@ -282,3 +620,61 @@ fn log_logo() {
     info!("|_____|___|______|_______|__________|_____|______|______|______|______|______|______|______|");
 }
 
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{create_test_chip8, Inspect};
+    use emulator::Emulator;
+
+    #[test]
+    fn is_halted_on_jump_to_self() {
+        let mut under_test = create_test_chip8(1, vec![0x60, 0x01, 0x12, 0x02]);
+
+        under_test.update();
+        let before_jump = under_test.is_halted();
+        under_test.update();
+
+        assert!(!before_jump);
+        assert!(under_test.is_halted());
+    }
+
+    #[test]
+    fn stack_overflow_halts_on_call() {
+        // CALL 0x202; CALL 0x200
+        let mut under_test = create_test_chip8(1, vec![0x22, 0x02, 0x22, 0x00]);
+        under_test.config.stack_depth = 1;
+
+        under_test.update();
+        under_test.update();
+
+        assert!(under_test.is_halted());
+        assert!(under_test.get_program_counter() == 0x202);
+        assert!(under_test.get_stack() == [0x202]);
+    }
+
+    #[test]
+    fn advance_runs_elapsed_frames() {
+        let mut under_test = create_test_chip8(1, vec![0x70, 0x01, 0x12, 0x00]);
+
+        let first = under_test.advance(Duration::from_millis(40));
+        let second = under_test.advance(Duration::from_millis(10));
+
+        assert!(first == 2);
+        assert!(second == 1);
+        assert!(under_test.get_data_register(0x0) == 2);
+    }
+
+    #[test]
+    fn reset_restores_rom_and_state() {
+        let mut under_test = create_test_chip8(1, vec![0x60, 0x01, 0x12, 0x02]);
+        under_test.update();
+        under_test.write_memory(0x200, &[0x00]);
+
+        under_test.reset();
+
+        assert!(under_test.get_program_counter() == 0x200);
+        assert!(under_test.get_data_register(0x0) == 0x00);
+        assert!(under_test.read_memory(0x200, 2) == [0x60, 0x01]);
+    }
+}
@@ -0,0 +1,171 @@
+use core::any::Any;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use super::{Chip8, REGISTER_COUNT};
+use super::component::memory::MemoryAccess;
+
+/// One executed instruction. `cycle`, `frame` and `registers` (V0 to VF, DT and ST) are from
+/// before it executed, `accesses` are the memory reads and writes it made in order.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Step<'a> {
+    pub cycle: u64,
+    pub frame: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub registers: [u8; REGISTER_COUNT + 2],
+    pub accesses: &'a [MemoryAccess],
+    pub end_of_frame: bool,
+}
+
+/// Called by `Chip8::step` after every instruction, whether the machine is driven by `update`,
+/// `run_until`, a debugger or a frontend. Instructions re-executed by `step_back` and
+/// `reverse_continue` are not observed again.
+pub trait StepObserver: Any + Send {
+    fn observe(&mut self, chip8: &Chip8, step: &Step);
+    fn as_any(&self) -> &Any;
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+/// The observers of a machine, each with the id `add` returned for it.
+#[derive(Default)]
+pub struct Observers {
+    observers: Vec<(u32, Box<StepObserver>)>,
+    next_id: u32,
+}
+
+impl Observers {
+    pub fn new() -> Observers {
+        Default::default()
+    }
+
+    pub fn add(&mut self, observer: Box<StepObserver>) -> u32 {
+        self.next_id += 1;
+        self.observers.push((self.next_id, observer));
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let count = self.observers.len();
+        self.observers.retain(|&(observer_id, _)| observer_id != id);
+        self.observers.len() != count
+    }
+
+    /// The observer with `id`, if it is a `T`.
+    pub fn get<T: StepObserver>(&self, id: u32) -> Option<&T> {
+        self.observers.iter()
+            .find(|observer| observer.0 == id)
+            .and_then(|observer| observer.1.as_any().downcast_ref())
+    }
+
+    pub fn get_mut<T: StepObserver>(&mut self, id: u32) -> Option<&mut T> {
+        self.observers.iter_mut()
+            .find(|observer| observer.0 == id)
+            .and_then(|observer| observer.1.as_any_mut().downcast_mut())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    pub fn notify(&mut self, chip8: &Chip8, step: &Step) {
+        for observer in &mut self.observers {
+            observer.1.observe(chip8, step);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::any::Any;
+
+    use super::{Step, StepObserver};
+    use super::super::{create_test_chip8, AccessKind, Chip8, RunCondition, StopReason, WatchKind};
+    use super::super::coverage::{Coverage, EXECUTED, WRITTEN};
+    use super::super::profile::Profiler;
+    use emulator::Emulator;
+
+    // LD V0, 123; LDI 0x300; BCD V0; JMP 0x206
+    const ROM: [u8; 8] = [0x60, 0x7B, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x06];
+
+    #[derive(Default)]
+    struct Steps {
+        pcs: Vec<u16>,
+        writes: usize,
+    }
+
+    impl StepObserver for Steps {
+        fn observe(&mut self, _: &Chip8, step: &Step) {
+            self.pcs.push(step.pc);
+            self.writes += step.accesses.iter().filter(|access| access.kind == AccessKind::Write).count();
+        }
+
+        fn as_any(&self) -> &Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut Any {
+            self
+        }
+    }
+
+    #[test]
+    fn observers_see_every_way_of_stepping() {
+        let mut under_test = create_test_chip8(2, ROM.to_vec());
+        let id = under_test.observers_mut().add(Box::new(Steps::default()));
+
+        under_test.step();
+        under_test.run_until(&[RunCondition::Instructions(2)]);
+        under_test.update();
+
+        let steps = under_test.observers().get::<Steps>(id).unwrap();
+        assert!(steps.pcs == vec![0x200, 0x202, 0x204, 0x206]);
+        assert!(steps.writes == 3);
+    }
+
+    #[test]
+    fn tools_combine_with_each_other_and_watchpoints() {
+        let mut under_test = create_test_chip8(2, ROM.to_vec());
+        let coverage = Coverage::new(&under_test);
+        let profiler = Profiler::new(&under_test);
+        let coverage_id = under_test.observers_mut().add(Box::new(coverage));
+        let profiler_id = under_test.observers_mut().add(Box::new(profiler));
+        let watchpoint = under_test.watchpoints_mut().add(0x301, 0x301, WatchKind::Write);
+
+        let result = under_test.run_until(&[RunCondition::Frames(2)]);
+
+        assert!(match result.reason { StopReason::WatchpointHit(hit) => hit.id == watchpoint, _ => false });
+        assert!(under_test.observers().get::<Coverage>(coverage_id).unwrap().usage(0x302) == WRITTEN);
+        assert!(under_test.observers().get::<Profiler>(profiler_id).unwrap().instructions() == 3);
+        assert!(under_test.observers().get::<Coverage>(profiler_id).is_none());
+    }
+
+    #[test]
+    fn replays_are_not_observed() {
+        let mut under_test = create_test_chip8(2, ROM.to_vec());
+        under_test.enable_history(1, 10);
+        let id = under_test.observers_mut().add(Box::new(Steps::default()));
+        under_test.run_until(&[RunCondition::Instructions(3)]);
+
+        under_test.step_back();
+        under_test.step();
+
+        assert!(under_test.observers().get::<Steps>(id).unwrap().pcs == vec![0x200, 0x202, 0x204, 0x204]);
+    }
+
+    #[test]
+    fn caller_recording_is_kept() {
+        let mut under_test = create_test_chip8(2, ROM.to_vec());
+        let coverage = Coverage::new(&under_test);
+        let id = under_test.observers_mut().add(Box::new(coverage));
+        under_test.memory.set_recording(true);
+
+        under_test.run_until(&[RunCondition::Instructions(3)]);
+
+        assert!(under_test.memory.take_accesses().len() == 3);
+        assert!(under_test.memory.is_recording());
+        assert!(under_test.observers().get::<Coverage>(id).unwrap().usage(0x204) == EXECUTED);
+        assert!(under_test.observers_mut().remove(id));
+        assert!(under_test.observers().is_empty());
+    }
+}
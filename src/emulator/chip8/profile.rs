@@ -0,0 +1,366 @@
+use core::any::Any;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Chip8, disassemble_with_symbols};
+use super::component::memory::MEM_SIZE;
+use super::component::opcode::{Opcode, ASM};
+use super::inspect::Inspect;
+use super::observer::{Step, StepObserver};
+use super::symbols::SymbolTable;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct FunctionProfile {
+    pub address: u16,
+    pub calls: u64,
+    pub inclusive: u64,
+    pub exclusive: u64,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct CallEdge {
+    pub caller: u16,
+    pub callee: u16,
+    pub calls: u64,
+    pub inclusive: u64,
+}
+
+/// A backward jump from `end` to `start` that was taken `iterations` times. `instructions` counts
+/// everything executed at addresses in `start..=end`, including other paths through them.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct LoopProfile {
+    pub start: u16,
+    pub end: u16,
+    pub iterations: u64,
+    pub instructions: u64,
+}
+
+/// Counts executed instructions per address and per instruction type, and follows CALL and RET
+/// to attribute them to subroutines while it observes the machine. Subroutines are identified by
+/// their entry address, the outermost one by the PC when profiling started.
+pub struct Profiler {
+    addresses: Vec<u64>,
+    opcodes: Vec<u16>,
+    mnemonics: BTreeMap<&'static str, u64>,
+    stacks: BTreeMap<Vec<u16>, u64>,
+    edges: BTreeMap<(u16, u16), u64>,
+    loops: BTreeMap<(u16, u16), u64>,
+    stack: Vec<u16>,
+    base_depth: usize,
+    instructions: u64,
+    frames: u64,
+}
+
+impl Profiler {
+    pub fn new(chip8: &Chip8) -> Profiler {
+        Profiler {
+            addresses: vec![0; MEM_SIZE],
+            opcodes: vec![0; MEM_SIZE],
+            mnemonics: BTreeMap::new(),
+            stacks: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            loops: BTreeMap::new(),
+            stack: vec![chip8.get_program_counter()],
+            base_depth: chip8.get_stack().len(),
+            instructions: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn follow_stack(&mut self, depth: usize, asm: ASM) {
+        if depth < self.base_depth {
+            self.base_depth = depth;
+        }
+        let current = self.base_depth + self.stack.len() - 1;
+        match asm {
+            ASM::CALL(target) if depth > current => {
+                let caller = *self.stack.last().unwrap();
+                *self.edges.entry((caller, target)).or_insert(0) += 1;
+                self.stack.push(target);
+            },
+            _ => {
+                let keep = depth.saturating_sub(self.base_depth) + 1;
+                self.stack.truncate(keep);
+            },
+        }
+    }
+
+    pub fn address_count(&self, address: u16) -> u64 {
+        self.addresses.get(address as usize).cloned().unwrap_or(0)
+    }
+
+    /// Executions per instruction type, most frequent first.
+    pub fn mnemonics(&self) -> Vec<(&'static str, u64)> {
+        let mut mnemonics: Vec<(&'static str, u64)> = self.mnemonics.iter().map(|(name, count)| (*name, *count)).collect();
+        mnemonics.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        mnemonics
+    }
+
+    /// Inclusive and exclusive instruction counts per subroutine, highest inclusive count first.
+    /// A recursive subroutine counts each instruction once.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut functions: BTreeMap<u16, FunctionProfile> = BTreeMap::new();
+        for (stack, count) in &self.stacks {
+            for (index, address) in stack.iter().enumerate() {
+                let function = functions.entry(*address)
+                    .or_insert(FunctionProfile { address: *address, calls: 0, inclusive: 0, exclusive: 0 });
+                if !stack[..index].contains(address) {
+                    function.inclusive += count;
+                }
+                if index == stack.len() - 1 {
+                    function.exclusive += count;
+                }
+            }
+        }
+        for (&(_, callee), calls) in &self.edges {
+            if let Some(function) = functions.get_mut(&callee) {
+                function.calls += calls;
+            }
+        }
+        let mut functions: Vec<FunctionProfile> = functions.values().cloned().collect();
+        functions.sort_by(|a, b| b.inclusive.cmp(&a.inclusive).then(a.address.cmp(&b.address)));
+        functions
+    }
+
+    /// Caller and callee pairs with the number of calls and the instructions executed inside the
+    /// callee on behalf of the caller.
+    pub fn call_graph(&self) -> Vec<CallEdge> {
+        let mut edges: Vec<CallEdge> = self.edges.iter()
+            .map(|(&(caller, callee), &calls)| CallEdge { caller: caller, callee: callee, calls: calls, inclusive: 0 })
+            .collect();
+        for (stack, count) in &self.stacks {
+            let mut seen: Vec<(u16, u16)> = Vec::new();
+            for pair in stack.windows(2) {
+                let key = (pair[0], pair[1]);
+                if seen.contains(&key) {
+                    continue;
+                }
+                seen.push(key);
+                if let Some(edge) = edges.iter_mut().find(|edge| (edge.caller, edge.callee) == key) {
+                    edge.inclusive += count;
+                }
+            }
+        }
+        edges.sort_by(|a, b| b.inclusive.cmp(&a.inclusive).then((a.caller, a.callee).cmp(&(b.caller, b.callee))));
+        edges
+    }
+
+    /// Taken backward jumps, the loop with the most executed instructions first.
+    pub fn loops(&self) -> Vec<LoopProfile> {
+        let mut loops: Vec<LoopProfile> = self.loops.iter()
+            .map(|(&(start, end), &iterations)| LoopProfile {
+                start: start,
+                end: end,
+                iterations: iterations,
+                instructions: self.addresses[start as usize..end as usize + 1].iter().sum(),
+            })
+            .collect();
+        loops.sort_by(|a, b| b.instructions.cmp(&a.instructions).then(b.iterations.cmp(&a.iterations)));
+        loops
+    }
+
+    /// One line per call stack with its instruction count, e.g. `main;draw_player 1200`, the
+    /// input format of flamegraph.pl, inferno and speedscope.
+    pub fn folded_stacks(&self, symbols: &SymbolTable) -> String {
+        let mut folded = String::new();
+        for (stack, count) in &self.stacks {
+            let names: Vec<String> = stack.iter().map(|address| function_name(*address, symbols)).collect();
+            folded.push_str(&format!("{} {}\n", names.join(";"), count));
+        }
+        folded
+    }
+
+    /// The `limit` hottest subroutines, call graph edges, loops, addresses and instruction types.
+    pub fn report(&self, symbols: &SymbolTable, limit: usize) -> String {
+        let total = self.instructions;
+        let mut lines = vec![format!("{} instructions in {} frames", total, self.frames)];
+
+        lines.push(String::new());
+        lines.push(format!("{:<24} {:>10} {:>6} {:>10} {:>6} {:>8}", "subroutine", "inclusive", "%", "exclusive", "%", "calls"));
+        for function in self.functions().iter().take(limit) {
+            lines.push(format!("{:<24} {:>10} {:>6} {:>10} {:>6} {:>8}",
+                function_name(function.address, symbols), function.inclusive, percent(function.inclusive, total),
+                function.exclusive, percent(function.exclusive, total), function.calls));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{:<49} {:>10} {:>8}", "call", "inclusive", "calls"));
+        for edge in self.call_graph().iter().take(limit) {
+            let call = format!("{} -> {}", function_name(edge.caller, symbols), function_name(edge.callee, symbols));
+            lines.push(format!("{:<49} {:>10} {:>8}", call, edge.inclusive, edge.calls));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{:<24} {:>12} {:>6} {:>10} {:>9}", "loop", "instructions", "%", "iterations", "per frame"));
+        for profile in self.loops().iter().take(limit) {
+            let range = format!("0x{:03X}-0x{:03X}{}", profile.start, profile.end, location(profile.start, symbols));
+            let per_frame = profile.iterations.checked_div(self.frames).unwrap_or(profile.iterations);
+            lines.push(format!("{:<24} {:>12} {:>6} {:>10} {:>9}", range, profile.instructions, percent(profile.instructions, total), profile.iterations, per_frame));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{:<24} {:>10} {:>6}  instruction", "address", "count", "%"));
+        let mut addresses: Vec<(usize, u64)> = self.addresses.iter().cloned().enumerate().filter(|&(_, count)| count > 0).collect();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for &(address, count) in addresses.iter().take(limit) {
+            let name = format!("0x{:03X}{}", address, location(address as u16, symbols));
+            lines.push(format!("{:<24} {:>10} {:>6}  {}", name, count, percent(count, total), disassemble_with_symbols(self.opcodes[address], symbols)));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{:<24} {:>10} {:>6}", "type", "count", "%"));
+        for (mnemonic, count) in self.mnemonics().into_iter().take(limit) {
+            lines.push(format!("{:<24} {:>10} {:>6}", mnemonic, count, percent(count, total)));
+        }
+        lines.join("\n")
+    }
+}
+
+impl StepObserver for Profiler {
+    fn observe(&mut self, chip8: &Chip8, step: &Step) {
+        let pc = step.pc;
+        let asm = Opcode::new(step.opcode).as_asm();
+        self.addresses[pc as usize] += 1;
+        self.opcodes[pc as usize] = step.opcode;
+        *self.mnemonics.entry(asm.mnemonic()).or_insert(0) += 1;
+        if let Some(count) = self.stacks.get_mut(self.stack.as_slice()) {
+            *count += 1;
+        }
+        if !self.stacks.contains_key(self.stack.as_slice()) {
+            self.stacks.insert(self.stack.clone(), 1);
+        }
+        self.instructions += 1;
+        if step.end_of_frame {
+            self.frames += 1;
+        }
+
+        self.follow_stack(chip8.get_stack().len(), asm);
+        match asm {
+            ASM::CALL(_) | ASM::RET => (),
+            _ if chip8.pc <= pc => *self.loops.entry((chip8.pc, pc)).or_insert(0) += 1,
+            _ => (),
+        }
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
+
+fn function_name(address: u16, symbols: &SymbolTable) -> String {
+    match symbols.symbol_at(address) {
+        Some(symbol) => symbol.name.clone(),
+        None => format!("0x{:03X}", address),
+    }
+}
+
+fn location(address: u16, symbols: &SymbolTable) -> String {
+    match symbols.describe(address) {
+        Some(name) => format!(" <{}>", name),
+        None => String::new(),
+    }
+}
+
+fn percent(count: u64, total: u64) -> String {
+    if total == 0 {
+        return String::from("-");
+    }
+    format!("{:.1}", count as f64 * 100.0 / total as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallEdge, FunctionProfile, LoopProfile, Profiler};
+    use super::super::{create_test_chip8, Chip8, RunCondition};
+    use super::super::symbols::SymbolTable;
+
+    // 0x200: CALL 0x206; ADD V1, 1; JP 0x200
+    // 0x206: CALL 0x20C; ADD V0, 1; RET
+    // 0x20C: RET
+    fn create_chip8() -> Chip8 {
+        create_test_chip8(7, vec![0x22, 0x06, 0x71, 0x01, 0x12, 0x00, 0x22, 0x0C, 0x70, 0x01, 0x00, 0xEE, 0x00, 0xEE])
+    }
+
+    fn profile(chip8: &mut Chip8, frames: u64) -> u32 {
+        let profiler = Profiler::new(chip8);
+        let id = chip8.observers_mut().add(Box::new(profiler));
+        chip8.run_until(&[RunCondition::Frames(frames)]);
+        id
+    }
+
+    #[test]
+    fn counts_addresses_and_instruction_types() {
+        let mut chip8 = create_chip8();
+
+        let id = profile(&mut chip8, 10);
+        let under_test = chip8.observers().get::<Profiler>(id).unwrap();
+
+        assert!(under_test.instructions() == 70);
+        assert!(under_test.frames() == 10);
+        assert!(under_test.address_count(0x200) == 10);
+        assert!(under_test.address_count(0x20E) == 0);
+        assert!(under_test.mnemonics() == vec![("ADDI", 20), ("CALL", 20), ("RET", 20), ("JMP", 10)]);
+    }
+
+    #[test]
+    fn call_graph_with_inclusive_and_exclusive_counts() {
+        let mut chip8 = create_chip8();
+
+        let id = profile(&mut chip8, 10);
+        let under_test = chip8.observers().get::<Profiler>(id).unwrap();
+
+        assert!(under_test.functions() == vec![
+            FunctionProfile { address: 0x200, calls: 0, inclusive: 70, exclusive: 30 },
+            FunctionProfile { address: 0x206, calls: 10, inclusive: 40, exclusive: 30 },
+            FunctionProfile { address: 0x20C, calls: 10, inclusive: 10, exclusive: 10 },
+        ]);
+        assert!(under_test.call_graph() == vec![
+            CallEdge { caller: 0x200, callee: 0x206, calls: 10, inclusive: 40 },
+            CallEdge { caller: 0x206, callee: 0x20C, calls: 10, inclusive: 10 },
+        ]);
+        assert!(under_test.loops() == vec![LoopProfile { start: 0x200, end: 0x204, iterations: 10, instructions: 30 }]);
+    }
+
+    #[test]
+    fn folded_stacks_with_symbols() {
+        let mut chip8 = create_chip8();
+        let symbols = SymbolTable::parse("main = 0x200\nupdate = 0x206").unwrap();
+
+        let id = profile(&mut chip8, 2);
+        let under_test = chip8.observers().get::<Profiler>(id).unwrap();
+
+        assert!(under_test.folded_stacks(&symbols) == "main 6\nmain;update 6\nmain;update;0x20C 2\n");
+    }
+
+    #[test]
+    fn report_lists_hot_spots() {
+        let mut chip8 = create_chip8();
+        let symbols = SymbolTable::parse("main = 0x200\nupdate = 0x206").unwrap();
+        let id = profile(&mut chip8, 10);
+        let under_test = chip8.observers().get::<Profiler>(id).unwrap();
+
+        let result = under_test.report(&symbols, 1);
+
+        let lines: Vec<&str> = result.lines().collect();
+        assert!(lines[0] == "70 instructions in 10 frames");
+        assert!(lines[3].starts_with("main") && lines[3].ends_with("0"));
+        assert!(lines[6].starts_with("main -> update"));
+        assert!(lines[9].starts_with("0x200-0x204 <main>"));
+        assert!(lines[12].starts_with("0x200 <main>") && lines[12].ends_with("0x2206: CALL update"));
+        assert!(lines[15].starts_with("ADDI"));
+        assert!(lines.len() == 16);
+    }
+}
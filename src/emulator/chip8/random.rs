@@ -0,0 +1,95 @@
+#[cfg(feature = "std")]
+use rand;
+
+const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+pub trait RandomSource: Send {
+    fn next_u8(&mut self) -> u8;
+    fn get_state(&self) -> u64 { 0 }
+    fn set_state(&mut self, _state: u64) {}
+}
+
+pub struct XorShiftRandom {
+    state: u64,
+}
+
+impl XorShiftRandom {
+    pub fn new(seed: u64) -> XorShiftRandom {
+        XorShiftRandom { state: if seed == 0 { DEFAULT_SEED } else { seed } }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> XorShiftRandom {
+        XorShiftRandom::new(rand::random::<u64>())
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub fn from_entropy() -> XorShiftRandom {
+        XorShiftRandom::new(DEFAULT_SEED)
+    }
+}
+
+impl RandomSource for XorShiftRandom {
+    fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn get_state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        if state != 0 {
+            self.state = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RandomSource, XorShiftRandom};
+
+    #[test]
+    fn next_u8_same_seed_same_sequence() {
+        let mut first = XorShiftRandom::new(42);
+        let mut second = XorShiftRandom::new(42);
+
+        for _ in 0..16 {
+            assert!(first.next_u8() == second.next_u8());
+        }
+    }
+
+    #[test]
+    fn next_u8_different_values() {
+        let mut under_test = XorShiftRandom::new(1);
+
+        let first = under_test.next_u8();
+        let differs = (0..16).any(|_| under_test.next_u8() != first);
+
+        assert!(differs);
+    }
+
+    #[test]
+    fn set_state_restores_sequence() {
+        let mut under_test = XorShiftRandom::new(7);
+        under_test.next_u8();
+        let state = under_test.get_state();
+        let expected = under_test.next_u8();
+
+        under_test.set_state(state);
+
+        assert!(under_test.next_u8() == expected);
+    }
+
+    #[test]
+    fn new_zero_seed_is_not_stuck() {
+        let mut under_test = XorShiftRandom::new(0);
+
+        let result = (0..16).any(|_| under_test.next_u8() != 0);
+
+        assert!(result);
+    }
+}
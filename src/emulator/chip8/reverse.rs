@@ -0,0 +1,422 @@
+use core::cmp;
+use core::mem;
+use alloc::vec::Vec;
+
+use super::Chip8;
+use super::control::StopReason;
+use super::draws::DrawLog;
+use super::observer::Observers;
+use super::watchpoint::Watchpoints;
+
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 1000;
+pub const DEFAULT_MAX_CHECKPOINTS: usize = 1000;
+
+struct Checkpoint {
+    instruction_count: u64,
+    frame_count: u64,
+    state: Vec<u8>,
+    halted: bool,
+    draw_log: Option<DrawLog>,
+}
+
+/// Snapshots taken every `interval` instructions and the key presses in between, from which any
+/// earlier instruction can be reached again by restoring a snapshot and re-executing. Replays are
+/// deterministic because the snapshots include the random generator state, unless a custom
+/// `RandomSource` cannot restore its state.
+pub struct ExecutionHistory {
+    interval: u64,
+    max_checkpoints: usize,
+    checkpoints: Vec<Checkpoint>,
+    inputs: Vec<(u64, u16)>,
+}
+
+impl ExecutionHistory {
+    pub fn new(interval: u64, max_checkpoints: usize) -> ExecutionHistory {
+        ExecutionHistory {
+            interval: cmp::max(interval, 1),
+            max_checkpoints: cmp::max(max_checkpoints, 1),
+            checkpoints: Vec::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Called before each instruction: replays the keys recorded for this instruction and takes a
+    /// snapshot when one is due.
+    pub fn record(&mut self, chip8: &mut Chip8) {
+        let count = chip8.instruction_count;
+        if let Some(keys) = self.input_at(count) {
+            chip8.input.release_key(0xFFFF);
+            chip8.input.press_key(keys);
+        }
+        let due = match self.checkpoints.last() {
+            Some(last) => count % self.interval == 0 && last.instruction_count < count,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        self.checkpoints.push(Checkpoint {
+            instruction_count: count,
+            frame_count: chip8.frame_count,
+            state: chip8.save_state(),
+            halted: chip8.halted,
+            draw_log: chip8.draw_log.clone(),
+        });
+        if self.checkpoints.len() > self.max_checkpoints {
+            self.checkpoints.remove(0);
+            let oldest = self.oldest();
+            self.inputs.retain(|&(at, _)| at >= oldest);
+        }
+    }
+
+    /// Records the keys held after a press or release. Keys recorded for later instructions by an
+    /// earlier run are dropped, as execution now takes a different course.
+    pub fn record_input(&mut self, count: u64, keys: u16) {
+        self.inputs.retain(|&(at, _)| at <= count);
+        self.inputs.push((count, keys));
+    }
+
+    fn input_at(&self, count: u64) -> Option<u16> {
+        self.inputs.iter().rev()
+            .take_while(|&&(at, _)| at >= count)
+            .find(|&&(at, _)| at == count)
+            .map(|&(_, keys)| keys)
+    }
+
+    /// The earliest instruction count that can still be reached.
+    pub fn oldest(&self) -> u64 {
+        self.checkpoints.first().map_or(0, |checkpoint| checkpoint.instruction_count)
+    }
+
+    pub fn checkpoints(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+        self.inputs.clear();
+    }
+
+    fn truncate(&mut self, count: u64) {
+        self.checkpoints.retain(|checkpoint| checkpoint.instruction_count <= count);
+    }
+}
+
+impl Chip8 {
+    /// Starts recording an execution history so `step_back` and `reverse_continue` can return to
+    /// earlier instructions. Memory use is about `max_checkpoints` times the size of a state and,
+    /// while it is enabled, of the draw log.
+    pub fn enable_history(&mut self, interval: u64, max_checkpoints: usize) {
+        self.history = Some(ExecutionHistory::new(interval, max_checkpoints));
+        self.record_history();
+    }
+
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    pub fn history(&self) -> Option<&ExecutionHistory> {
+        self.history.as_ref()
+    }
+
+    /// Forgets the recorded history and starts again from the current state, e.g. after loading a
+    /// state.
+    pub fn restart_history(&mut self) {
+        if let Some(ref mut history) = self.history {
+            history.clear();
+        }
+        self.record_history();
+    }
+
+    /// Returns to the state before the last executed instruction. Returns false at the start of
+    /// the recorded history.
+    pub fn step_back(&mut self) -> bool {
+        match self.history {
+            Some(ref history) if self.instruction_count > history.oldest() => (),
+            _ => return false,
+        }
+        let target = self.instruction_count - 1;
+        self.rewind_to(target);
+        true
+    }
+
+    /// Runs backwards to the most recent breakpoint or watchpoint hit before the current
+    /// instruction, leaving the machine where a forward run would have stopped. Without an
+    /// earlier hit it stops at the start of the recorded history and returns `None`.
+    pub fn reverse_continue(&mut self) -> Option<StopReason> {
+        let target = self.instruction_count;
+        let starts: Vec<u64> = match self.history {
+            Some(ref history) => history.checkpoints.iter().map(|checkpoint| checkpoint.instruction_count).collect(),
+            None => return None,
+        };
+        let (breakpoints, watchpoints) = (self.breakpoints.clone(), self.watchpoints.clone());
+        let mut found = None;
+        for index in (0..starts.len()).rev() {
+            if starts[index] >= target {
+                continue;
+            }
+            let end = cmp::min(starts.get(index + 1).cloned().unwrap_or(target), target);
+            found = self.last_hit(index, end, target);
+            if found.is_some() {
+                break;
+            }
+        }
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        match found {
+            Some((count, reason)) => {
+                self.rewind_to(count);
+                Some(reason)
+            },
+            None => {
+                let oldest = starts.first().cloned().unwrap_or(target);
+                self.rewind_to(cmp::min(oldest, target));
+                None
+            },
+        }
+    }
+
+    /// Re-executes from checkpoint `index` up to instruction `end` and returns the last breakpoint
+    /// or watchpoint hit on the way, ignoring a watchpoint hit that stopped at `target`.
+    fn last_hit(&mut self, index: usize, end: u64, target: u64) -> Option<(u64, StopReason)> {
+        self.restore_checkpoint(index);
+        let observers = mem::replace(&mut self.observers, Observers::new());
+        let mut last = None;
+        while self.instruction_count < end {
+            let count = self.instruction_count;
+            if let Some(id) = self.check_breakpoints() {
+                last = Some((count, StopReason::BreakpointHit(id)));
+            }
+            self.step();
+            match self.watchpoint_hit {
+                Some(hit) if self.instruction_count < target => last = Some((self.instruction_count, StopReason::WatchpointHit(hit))),
+                _ => (),
+            }
+        }
+        self.observers = observers;
+        last
+    }
+
+    fn rewind_to(&mut self, count: u64) {
+        let index = match self.history {
+            Some(ref history) => history.checkpoints.iter().rposition(|checkpoint| checkpoint.instruction_count <= count),
+            None => None,
+        };
+        let index = match index {
+            Some(index) => index,
+            None => return,
+        };
+        self.restore_checkpoint(index);
+        let observers = mem::replace(&mut self.observers, Observers::new());
+        let watchpoints = mem::replace(&mut self.watchpoints, Watchpoints::new());
+        while self.instruction_count < count {
+            self.step();
+        }
+        self.observers = observers;
+        self.watchpoints = watchpoints;
+        // Applies keys pressed while stopped at `count`, the later checkpoints no longer apply
+        let mut history = self.history.take().unwrap();
+        history.record(self);
+        history.truncate(count);
+        self.history = Some(history);
+    }
+
+    fn restore_checkpoint(&mut self, index: usize) {
+        let (instruction_count, frame_count, state, halted, draw_log) = {
+            let checkpoint = &self.history.as_ref().unwrap().checkpoints[index];
+            (checkpoint.instruction_count, checkpoint.frame_count, checkpoint.state.clone(), checkpoint.halted, checkpoint.draw_log.clone())
+        };
+        self.load_state(&state).expect("checkpoint state is valid");
+        self.instruction_count = instruction_count;
+        self.frame_count = frame_count;
+        self.halted = halted;
+        if self.draw_log.is_some() {
+            // a draw log enabled after the checkpoint was taken starts empty
+            self.draw_log = Some(draw_log.unwrap_or_default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{create_test_chip8, Chip8, Inspect, StopReason, WatchKind};
+    use super::super::breakpoint::BreakpointKind;
+    use emulator::Emulator;
+    use emulator::keypad::HexKey;
+
+    fn create_chip8_with_history(rom: Vec<u8>) -> Chip8 {
+        let mut chip8 = create_test_chip8(3, rom);
+        chip8.enable_history(4, 100);
+        chip8
+    }
+
+    #[test]
+    fn step_back_restores_registers_and_counts() {
+        let mut under_test = create_chip8_with_history(vec![0x70, 0x01, 0x12, 0x00]);
+        for _ in 0..11 {
+            under_test.step();
+        }
+
+        assert!(under_test.step_back());
+
+        assert!(under_test.get_instruction_count() == 10);
+        assert!(under_test.get_frame_count() == 3);
+        assert!(under_test.get_data_register(0) == 5);
+        assert!(under_test.get_program_counter() == 0x200);
+    }
+
+    #[test]
+    fn step_back_stops_at_start_of_history() {
+        let mut under_test = create_chip8_with_history(vec![0x70, 0x01, 0x12, 0x00]);
+        under_test.step();
+
+        assert!(under_test.step_back());
+        assert!(!under_test.step_back());
+        assert!(under_test.get_program_counter() == 0x200);
+    }
+
+    #[test]
+    fn step_back_replays_random_numbers() {
+        let mut under_test = create_chip8_with_history(vec![0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF, 0xC4, 0xFF, 0xC5, 0xFF, 0x12, 0x0C]);
+        for _ in 0..6 {
+            under_test.step();
+        }
+        let values: Vec<u8> = (0..6).map(|register| under_test.get_data_register(register)).collect();
+
+        under_test.step_back();
+        under_test.step();
+
+        assert!((0..6).map(|register| under_test.get_data_register(register)).collect::<Vec<u8>>() == values);
+    }
+
+    #[test]
+    fn step_back_replays_key_presses() {
+        // LD V0, K with a key pressed after 5 instructions of waiting
+        let mut under_test = create_chip8_with_history(vec![0xF0, 0x0A, 0x12, 0x02]);
+        for _ in 0..5 {
+            under_test.step();
+        }
+        under_test.press_key(HexKey::Key7);
+        under_test.step();
+        under_test.release_key(HexKey::Key7);
+        under_test.step();
+
+        under_test.step_back();
+        under_test.step_back();
+        under_test.step_back();
+        under_test.step();
+        under_test.step();
+
+        assert!(under_test.get_data_register(0) == 7);
+        assert!(under_test.get_program_counter() == 0x202);
+    }
+
+    // CLS, LD F, V0 and DRW in the first two frames, then a jump to itself halts the machine
+    const DRAW_AND_HALT: [u8; 10] = [0x00, 0xE0, 0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x08];
+
+    fn create_chip8_with_draw_log_and_history() -> Chip8 {
+        let mut chip8 = create_test_chip8(3, DRAW_AND_HALT.to_vec());
+        chip8.enable_draw_log();
+        chip8.enable_history(4, 100);
+        for _ in 0..4 {
+            chip8.step();
+        }
+        chip8
+    }
+
+    #[test]
+    fn step_back_restores_halted_and_draw_log() {
+        let mut under_test = create_chip8_with_draw_log_and_history();
+        let draws = under_test.draw_log().unwrap().calls();
+        under_test.step();
+        let halted = under_test.is_halted();
+
+        assert!(under_test.step_back());
+
+        assert!(halted);
+        assert!(!under_test.is_halted());
+        assert!(draws.len() == 2);
+        assert!(under_test.draw_log().unwrap().calls() == draws);
+    }
+
+    #[test]
+    fn reverse_continue_restores_halted_and_draw_log() {
+        let mut under_test = create_chip8_with_draw_log_and_history();
+        let draws = under_test.draw_log().unwrap().calls();
+        under_test.breakpoints_mut().add(BreakpointKind::Address(0x208));
+        under_test.step();
+
+        let result = under_test.reverse_continue();
+
+        assert!(result == Some(StopReason::BreakpointHit(1)));
+        assert!(under_test.get_instruction_count() == 4);
+        assert!(!under_test.is_halted());
+        assert!(under_test.draw_log().unwrap().calls() == draws);
+    }
+
+    #[test]
+    fn reverse_continue_to_breakpoint() {
+        let mut under_test = create_chip8_with_history(vec![0x70, 0x01, 0x12, 0x00]);
+        under_test.breakpoints_mut().add(BreakpointKind::Address(0x202));
+        for _ in 0..20 {
+            under_test.step();
+        }
+
+        let first = under_test.reverse_continue();
+        let second = under_test.reverse_continue();
+
+        assert!(first == Some(StopReason::BreakpointHit(1)));
+        assert!(second == Some(StopReason::BreakpointHit(1)));
+        assert!(under_test.get_instruction_count() == 17);
+        assert!(under_test.get_program_counter() == 0x202);
+        assert!(under_test.get_data_register(0) == 9);
+        assert!(under_test.breakpoints().get(1).unwrap().hits == 0);
+    }
+
+    #[test]
+    fn reverse_continue_to_memory_corruption() {
+        // LDI 0x300, STOR V0 once, then count in V1 forever
+        let mut under_test = create_chip8_with_history(vec![0xA3, 0x00, 0x60, 0x2A, 0xF0, 0x55, 0x71, 0x01, 0x12, 0x06]);
+        under_test.watchpoints_mut().add(0x300, 0x300, WatchKind::Write);
+        for _ in 0..50 {
+            under_test.step();
+        }
+
+        let result = under_test.reverse_continue();
+
+        match result {
+            Some(StopReason::WatchpointHit(hit)) => assert!(hit.pc == 0x204 && hit.access.value == 0x2A),
+            _ => panic!("expected a watchpoint hit, got {:?}", result),
+        }
+        assert!(under_test.get_instruction_count() == 3);
+        assert!(under_test.get_program_counter() == 0x206);
+    }
+
+    #[test]
+    fn reverse_continue_without_hit_stops_at_start() {
+        let mut under_test = create_chip8_with_history(vec![0x70, 0x01, 0x12, 0x00]);
+        for _ in 0..9 {
+            under_test.step();
+        }
+
+        assert!(under_test.reverse_continue().is_none());
+        assert!(under_test.get_instruction_count() == 0);
+        assert!(under_test.get_data_register(0) == 0);
+    }
+
+    #[test]
+    fn oldest_checkpoints_are_dropped() {
+        let mut under_test = create_test_chip8(1, vec![0x70, 0x01, 0x12, 0x00]);
+        under_test.enable_history(2, 3);
+        for _ in 0..10 {
+            under_test.step();
+        }
+
+        assert!(under_test.history().unwrap().checkpoints() == 3);
+        assert!(under_test.history().unwrap().oldest() == 4);
+        for _ in 0..6 {
+            assert!(under_test.step_back());
+        }
+        assert!(!under_test.step_back());
+    }
+}
@@ -0,0 +1,256 @@
+use core::fmt::{self, Formatter, Display};
+use alloc::vec::Vec;
+
+use super::{is_program_address, Chip8};
+use super::component::memory::MEM_SIZE;
+use super::component::registers::REGISTER_COUNT;
+
+const MAGIC: &'static [u8; 4] = b"R8ST";
+const VERSION: u8 = 2;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    StackTooDeep(usize),
+    ScreenSizeMismatch(usize, usize),
+    AddressOutOfMemory(u16),
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            StateError::InvalidMagic => write!(f, "Not a rust8 save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "Unsupported save state version {}", version),
+            StateError::Truncated => write!(f, "Save state is truncated"),
+            StateError::StackTooDeep(depth) => write!(f, "Save state stack depth {} exceeds the configured depth", depth),
+            StateError::ScreenSizeMismatch(width, height) => write!(f, "Save state screen size {}x{} does not match", width, height),
+            StateError::AddressOutOfMemory(address) => write!(f, "Save state address 0x{:X} is outside of memory", address),
+        }
+    }
+}
+
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(MEM_SIZE + 2048 + 64);
+        state.extend_from_slice(MAGIC);
+        state.push(VERSION);
+        push_u16(&mut state, self.pc);
+        push_u16(&mut state, self.registers.get_address_register_value());
+        state.extend_from_slice(self.registers.get_data_registers(0x0, (REGISTER_COUNT - 1) as u8));
+        state.push(self.delay_timer.get_value());
+        state.push(self.sound_timer.get_value());
+        push_u16(&mut state, self.input.get_pressed_keys());
+        push_u32(&mut state, self.instructions_in_frame);
+        push_u16(&mut state, self.stack.len() as u16);
+        for address in &self.stack {
+            push_u16(&mut state, *address);
+        }
+        state.extend_from_slice(self.memory.as_slice());
+        let (width, height) = self.screen.get_dimensions();
+        push_u16(&mut state, width as u16);
+        push_u16(&mut state, height as u16);
+        state.extend(self.screen.retrieve_state().iter().map(|pixel| *pixel as u8));
+        push_u32(&mut state, (self.random.get_state() >> 32) as u32);
+        push_u32(&mut state, self.random.get_state() as u32);
+        state
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader { state: state, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(StateError::InvalidMagic);
+        }
+        let version = reader.take_u8()?;
+        if version == 0 || version > VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let pc = reader.take_u16()?;
+        if !is_program_address(pc) {
+            return Err(StateError::AddressOutOfMemory(pc));
+        }
+        let address_register = reader.take_u16()?;
+        let data_registers = reader.take(REGISTER_COUNT)?;
+        let delay_timer = reader.take_u8()?;
+        let sound_timer = reader.take_u8()?;
+        let pressed_keys = reader.take_u16()?;
+        let instructions_in_frame = reader.take_u32()?;
+        let stack_depth = reader.take_u16()? as usize;
+        if stack_depth > self.config.stack_depth {
+            return Err(StateError::StackTooDeep(stack_depth));
+        }
+        let mut stack = Vec::with_capacity(stack_depth);
+        for _ in 0..stack_depth {
+            let address = reader.take_u16()?;
+            if !is_program_address(address) {
+                return Err(StateError::AddressOutOfMemory(address));
+            }
+            stack.push(address);
+        }
+        let memory = reader.take(MEM_SIZE)?;
+        let (width, height) = (reader.take_u16()? as usize, reader.take_u16()? as usize);
+        if (width, height) != self.screen.get_dimensions() {
+            return Err(StateError::ScreenSizeMismatch(width, height));
+        }
+        let screen: Vec<bool> = reader.take(width * height)?.iter().map(|pixel| *pixel != 0).collect();
+        let random_state = if version >= 2 {
+            Some(((reader.take_u32()? as u64) << 32) + reader.take_u32()? as u64)
+        } else {
+            None
+        };
+
+        self.pc = pc;
+        self.registers.set_address_register_value(address_register);
+        for (register, value) in data_registers.iter().enumerate() {
+            self.registers.set_data_register_by_value(register as u8, *value);
+        }
+        self.delay_timer.set_value(delay_timer);
+        self.sound_timer.set_value(sound_timer);
+        self.input.release_key(0xFFFF);
+        self.input.press_key(pressed_keys);
+        self.instructions_in_frame = instructions_in_frame % self.config.instructions_per_frame;
+        self.stack = stack;
+        self.memory.as_mut_slice().copy_from_slice(memory);
+        self.screen.restore(screen.as_slice());
+        if let Some(random_state) = random_state {
+            self.random.set_state(random_state);
+        }
+        self.need_redraw = true;
+        if let Some(ref mut draw_log) = self.draw_log {
+            draw_log.clear();
+        }
+        Ok(())
+    }
+}
+
+fn push_u16(state: &mut Vec<u8>, value: u16) {
+    state.push((value >> 8) as u8);
+    state.push(value as u8);
+}
+
+fn push_u32(state: &mut Vec<u8>, value: u32) {
+    push_u16(state, (value >> 16) as u16);
+    push_u16(state, value as u16);
+}
+
+struct StateReader<'a> {
+    state: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], StateError> {
+        if self.position + length > self.state.len() {
+            return Err(StateError::Truncated);
+        }
+        let bytes = &self.state[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn take_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take_u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.take(2)?;
+        Ok(((bytes[0] as u16) << 8) + bytes[1] as u16)
+    }
+
+    fn take_u32(&mut self) -> Result<u32, StateError> {
+        Ok(((self.take_u16()? as u32) << 16) + self.take_u16()? as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateError;
+    use super::super::{create_test_chip8, Inspect};
+    use emulator::Emulator;
+
+    const ROM: [u8; 14] = [
+        0x60, 0x05, // LD V0, 0x05
+        0xA2, 0x0A, // LDI 0x20A
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x22, 0x0C, // CALL 0x20C
+        0x12, 0x08, // JMP 0x208
+        0xF0, 0x90, // sprite data
+        0x12, 0x0C, // JMP 0x20C
+    ];
+
+    #[test]
+    fn save_load_state_roundtrip() {
+        let mut under_test = create_test_chip8(1, ROM.to_vec());
+        for _ in 0..4 { under_test.update(); }
+        under_test.set_delay_timer(9);
+        let state = under_test.save_state();
+        let mut restored = create_test_chip8(1, ROM.to_vec());
+
+        restored.load_state(&state).unwrap();
+
+        assert!(restored.save_state() == state);
+        assert!(restored.get_program_counter() == 0x20C);
+        assert!(restored.get_stack() == [0x208]);
+        assert!(restored.get_delay_timer() == 9);
+        assert!(restored.retrieve_screen_pixels() == under_test.retrieve_screen_pixels());
+    }
+
+    #[test]
+    fn load_state_restores_random_sequence() {
+        let mut under_test = create_test_chip8(1, vec![0xC0, 0xFF, 0x12, 0x00]);
+        under_test.update();
+        let state = under_test.save_state();
+        under_test.update();
+        under_test.update();
+        let expected = under_test.get_data_register(0x0);
+
+        under_test.load_state(&state).unwrap();
+        under_test.update();
+        under_test.update();
+
+        assert!(under_test.get_data_register(0x0) == expected);
+    }
+
+    #[test]
+    fn load_state_invalid_magic() {
+        let mut under_test = create_test_chip8(1, ROM.to_vec());
+
+        let result = under_test.load_state(b"NOPE");
+
+        assert!(result == Err(StateError::InvalidMagic));
+    }
+
+    #[test]
+    fn load_state_truncated_keeps_machine() {
+        let mut under_test = create_test_chip8(1, ROM.to_vec());
+        let state = under_test.save_state();
+        under_test.update();
+
+        let result = under_test.load_state(&state[..state.len() - 1]);
+
+        assert!(result == Err(StateError::Truncated));
+        assert!(under_test.get_data_register(0x0) == 0x05);
+    }
+
+    #[test]
+    fn load_state_rejects_addresses_outside_memory() {
+        let mut under_test = create_test_chip8(1, ROM.to_vec());
+        for _ in 0..4 { under_test.update(); }
+        let state = under_test.save_state();
+        let mut bad_pc = state.clone();
+        bad_pc[5..7].copy_from_slice(&[0x0F, 0xFF]);
+        // magic, version, PC, I, V0 to VF, DT and ST, keys, instructions in frame and stack depth
+        let stack_offset = 4 + 1 + 2 + 2 + 16 + 1 + 1 + 2 + 4 + 2;
+        let mut bad_stack = state.clone();
+        bad_stack[stack_offset..stack_offset + 2].copy_from_slice(&[0x10, 0x00]);
+
+        let pc_result = under_test.load_state(&bad_pc);
+        let stack_result = under_test.load_state(&bad_stack);
+
+        assert!(pc_result == Err(StateError::AddressOutOfMemory(0xFFF)));
+        assert!(stack_result == Err(StateError::AddressOutOfMemory(0x1000)));
+        assert!(under_test.get_program_counter() == 0x20C);
+        assert!(under_test.load_state(&state).is_ok());
+    }
+}
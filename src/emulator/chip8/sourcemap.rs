@@ -0,0 +1,126 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::builder::parse_number;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct SourceLine {
+    pub address: u16,
+    pub file: String,
+    pub line: u32,
+}
+
+/// Maps addresses to the source lines they were assembled from. Entries are kept sorted by
+/// address.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct SourceMap {
+    lines: Vec<SourceLine>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        Default::default()
+    }
+
+    /// Parses one `address file:line` entry per line, e.g. `0x208 game.8o:12`. Empty lines and
+    /// lines starting with `#` or `;` are skipped.
+    pub fn parse(text: &str) -> Result<SourceMap, String> {
+        let mut map = SourceMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let (address, file, number) = parse_line(line).map_err(|error| format!("Line {}: {}", index + 1, error))?;
+            map.add(address, file, number);
+        }
+        Ok(map)
+    }
+
+    pub fn add(&mut self, address: u16, file: &str, line: u32) {
+        let index = self.lines.iter().position(|entry| entry.address > address).unwrap_or(self.lines.len());
+        self.lines.insert(index, SourceLine { address: address, file: String::from(file), line: line });
+    }
+
+    /// The line of the closest entry at or before `address`.
+    pub fn location(&self, address: u16) -> Option<&SourceLine> {
+        self.lines.iter().rev().find(|entry| entry.address <= address)
+    }
+
+    /// The first address of `line` in `path`, or of the next line after it that has code. `path`
+    /// matches a file of the map if it is the same or ends with it, so editors can pass absolute
+    /// paths. Returns the line that was found along with its address.
+    pub fn resolve(&self, path: &str, line: u32) -> Option<(u32, u16)> {
+        self.lines.iter()
+            .filter(|entry| entry.line >= line && same_file(path, &entry.file))
+            .min_by_key(|entry| (entry.line, entry.address))
+            .map(|entry| (entry.line, entry.address))
+    }
+
+    pub fn list(&self) -> &[SourceLine] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+fn same_file(path: &str, file: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let file = file.replace('\\', "/");
+    path == file || path.ends_with(&format!("/{}", file.trim_start_matches("./")))
+}
+
+fn parse_line(line: &str) -> Result<(u16, &str, u32), String> {
+    let error = || format!("Expected 'address file:line', got '{}'", line);
+    let mut tokens = line.splitn(2, char::is_whitespace);
+    let address = parse_number(tokens.next().ok_or_else(error)?).map_err(|_| error())?;
+    let location = tokens.next().ok_or_else(error)?.trim();
+    let separator = location.rfind(':').ok_or_else(error)?;
+    let number = location[separator + 1..].parse::<u32>().map_err(|_| error())?;
+    if address > 0xFFFF {
+        return Err(format!("Address 0x{:X} is out of range", address));
+    }
+    Ok((address as u16, &location[..separator], number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SourceMap;
+
+    const SOURCE_MAP: &'static str = "\
+# generated by the assembler
+0x200 game.8o:3
+0x202 game.8o:4
+0x204 game.8o:4
+0x208 lib/draw.8o:10
+";
+
+    #[test]
+    fn parse_and_locate() {
+        let under_test = SourceMap::parse(SOURCE_MAP).unwrap();
+
+        assert!(under_test.list().len() == 4);
+        assert!(under_test.location(0x204).map(|entry| entry.line) == Some(4));
+        assert!(under_test.location(0x20A).map(|entry| entry.file.as_str()) == Some("lib/draw.8o"));
+        assert!(under_test.location(0x100).is_none());
+    }
+
+    #[test]
+    fn resolve_lines_by_path_suffix() {
+        let under_test = SourceMap::parse(SOURCE_MAP).unwrap();
+
+        assert!(under_test.resolve("/home/user/rom/game.8o", 4) == Some((4, 0x202)));
+        assert!(under_test.resolve("game.8o", 1) == Some((3, 0x200)));
+        assert!(under_test.resolve("C:\\rom\\lib\\draw.8o", 10) == Some((10, 0x208)));
+        assert!(under_test.resolve("other.8o", 3).is_none());
+        assert!(under_test.resolve("game.8o", 5).is_none());
+    }
+
+    #[test]
+    fn parse_reports_line() {
+        assert!(SourceMap::parse("0x200 game.8o:3\n0x202 game.8o") == Err(String::from("Line 2: Expected 'address file:line', got '0x202 game.8o'")));
+        assert!(SourceMap::parse("0x10000 game.8o:1").is_err());
+    }
+}
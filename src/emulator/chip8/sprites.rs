@@ -0,0 +1,235 @@
+use core::cmp;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::Chip8;
+use super::inspect::Inspect;
+
+pub const ADDRESS_REGISTER_COLOR: [u8; 3] = [0xFF, 0xD0, 0x40];
+pub const LAST_DRAW_COLOR: [u8; 3] = [0x40, 0xFF, 0x60];
+
+const GRID_COLOR: [u8; 3] = [0x40, 0x40, 0x40];
+
+/// How memory is cut into sprites: `Chip8(rows)` sprites are 8 pixels wide with one byte per row,
+/// SCHIP sprites are 16x16 with two bytes per row.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SpriteFormat {
+    Chip8(usize),
+    SuperChip,
+}
+
+impl SpriteFormat {
+    pub fn width(&self) -> usize {
+        match *self {
+            SpriteFormat::Chip8(_) => 8,
+            SpriteFormat::SuperChip => 16,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        match *self {
+            SpriteFormat::Chip8(rows) => cmp::max(rows, 1),
+            SpriteFormat::SuperChip => 16,
+        }
+    }
+
+    pub fn bytes_per_sprite(&self) -> usize {
+        self.rows() * self.width() / 8
+    }
+}
+
+/// A memory range drawn in a color instead of white, and marked with `label` in text output.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Highlight {
+    pub start: u16,
+    pub length: usize,
+    pub color: [u8; 3],
+    pub label: char,
+}
+
+impl Highlight {
+    fn contains(&self, address: usize) -> bool {
+        address >= self.start as usize && address < self.start as usize + self.length
+    }
+}
+
+/// An RGB image, three bytes per pixel row by row.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, color: [u8; 3]) -> Image {
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for _ in 0..width * height {
+            pixels.extend_from_slice(&color);
+        }
+        Image { width: width, height: height, pixels: pixels }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let index = (y * self.width + x) * 3;
+        [self.pixels[index], self.pixels[index + 1], self.pixels[index + 2]]
+    }
+
+    fn fill(&mut self, x: usize, y: usize, size: usize, color: [u8; 3]) {
+        for row in y..y + size {
+            for column in x..x + size {
+                let index = (row * self.width + column) * 3;
+                self.pixels[index..index + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Encodes the image as binary PPM, which most image viewers and converters read.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend_from_slice(&self.pixels);
+        ppm
+    }
+}
+
+/// Draws `length` bytes of `memory` from `start` as a sheet of sprites, `columns` sprites per row
+/// with a one pixel grid between them and every sprite pixel `scale` pixels wide. Set pixels are
+/// white, or the color of the first highlight that contains their byte. Unset pixels of a
+/// highlighted byte are drawn in a darker shade of its color.
+pub fn render_sheet(memory: &[u8], start: u16, length: usize, format: SpriteFormat, columns: usize, scale: usize, highlights: &[Highlight]) -> Image {
+    let (columns, scale) = (cmp::max(columns, 1), cmp::max(scale, 1));
+    let end = cmp::min(start as usize + length, memory.len());
+    let length = end.saturating_sub(start as usize);
+    let sprites = (length + format.bytes_per_sprite() - 1) / format.bytes_per_sprite();
+    let (cell_width, cell_height) = (format.width() * scale + 1, format.rows() * scale + 1);
+    let rows = cmp::max((sprites + columns - 1) / columns, 1);
+    let mut image = Image::new(columns * cell_width + 1, rows * cell_height + 1, GRID_COLOR);
+    let bytes_per_row = format.width() / 8;
+    for offset in 0..length {
+        let address = start as usize + offset;
+        let sprite = offset / format.bytes_per_sprite();
+        let row = offset % format.bytes_per_sprite() / bytes_per_row;
+        let byte_column = offset % bytes_per_row;
+        let color = highlights.iter().find(|highlight| highlight.contains(address)).map(|highlight| highlight.color);
+        let left = sprite % columns * cell_width + 1 + byte_column * 8 * scale;
+        let top = sprite / columns * cell_height + 1 + row * scale;
+        for bit in 0..8 {
+            let set = memory[address] & (0x80 >> bit) != 0;
+            let pixel = match (set, color) {
+                (true, Some(color)) => color,
+                (true, None) => [0xFF, 0xFF, 0xFF],
+                (false, Some(color)) => [color[0] / 4, color[1] / 4, color[2] / 4],
+                (false, None) => [0x00, 0x00, 0x00],
+            };
+            image.fill(left + bit * scale, top, scale, pixel);
+        }
+    }
+    image
+}
+
+/// One line per sprite row with `#` for set pixels, followed by the labels of the highlights that
+/// contain the row. Sprites are separated by an empty line.
+pub fn sprite_text(memory: &[u8], start: u16, length: usize, format: SpriteFormat, highlights: &[Highlight]) -> Vec<String> {
+    let end = cmp::min(start as usize + length, memory.len());
+    let bytes_per_row = format.width() / 8;
+    let mut lines = Vec::new();
+    let mut address = start as usize;
+    while address < end {
+        let row_end = cmp::min(address + bytes_per_row, end);
+        let pixels: String = memory[address..row_end].iter()
+            .flat_map(|byte| (0..8).map(move |bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' }))
+            .collect();
+        let mut line = format!("0x{:03X}  {:<width$}", address, pixels, width = format.width());
+        for highlight in highlights.iter().filter(|highlight| (address..row_end).any(|address| highlight.contains(address))) {
+            line.push_str(&format!("  {}", highlight.label));
+        }
+        let sprite_end = (address - start as usize + bytes_per_row) % format.bytes_per_sprite() == 0;
+        lines.push(line);
+        if sprite_end && row_end < end {
+            lines.push(String::new());
+        }
+        address = row_end;
+    }
+    lines
+}
+
+impl Chip8 {
+    /// Address and number of rows of the sprite drawn by the last DXYN.
+    pub fn last_sprite(&self) -> Option<(u16, usize)> {
+        self.last_sprite
+    }
+
+    /// Highlights for the sprite I points to, one sprite of `format` long, and for the rows read by
+    /// the last DXYN.
+    pub fn sprite_highlights(&self, format: SpriteFormat) -> Vec<Highlight> {
+        let mut highlights = vec![Highlight { start: self.get_address_register(), length: format.bytes_per_sprite(), color: ADDRESS_REGISTER_COLOR, label: 'I' }];
+        if let Some((start, length)) = self.last_sprite {
+            highlights.push(Highlight { start: start, length: length, color: LAST_DRAW_COLOR, label: 'D' });
+        }
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_sheet, sprite_text, Highlight, SpriteFormat, ADDRESS_REGISTER_COLOR, LAST_DRAW_COLOR};
+    use super::super::{create_test_chip8, FONT_ADDRESS, MEM_SIZE};
+    use super::super::inspect::Inspect;
+
+    #[test]
+    fn sheet_layout_and_colors() {
+        let memory = [0x80, 0x01, 0xFF, 0x00];
+        let highlight = Highlight { start: 2, length: 2, color: LAST_DRAW_COLOR, label: 'D' };
+
+        let under_test = render_sheet(&memory, 0, 4, SpriteFormat::Chip8(2), 2, 1, &[highlight]);
+
+        assert!((under_test.width, under_test.height) == (19, 4));
+        assert!(under_test.pixel(0, 0) == [0x40, 0x40, 0x40]);
+        assert!(under_test.pixel(1, 1) == [0xFF, 0xFF, 0xFF]);
+        assert!(under_test.pixel(2, 1) == [0x00, 0x00, 0x00]);
+        assert!(under_test.pixel(8, 2) == [0xFF, 0xFF, 0xFF]);
+        assert!(under_test.pixel(10, 1) == LAST_DRAW_COLOR);
+        assert!(under_test.pixel(10, 2) == [0x10, 0x3F, 0x18]);
+    }
+
+    #[test]
+    fn super_chip_sprites_are_16_pixels_wide() {
+        let mut memory = [0; 32];
+        memory[1] = 0x01;
+
+        let under_test = render_sheet(&memory, 0, 32, SpriteFormat::SuperChip, 4, 2, &[]);
+
+        assert!((under_test.width, under_test.height) == (4 * 33 + 1, 34));
+        assert!(under_test.pixel(31, 1) == [0xFF, 0xFF, 0xFF]);
+        assert!(under_test.pixel(29, 1) == [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn ppm_header() {
+        let under_test = render_sheet(&[0xFF], 0, 1, SpriteFormat::Chip8(1), 1, 1, &[]);
+
+        let result = under_test.to_ppm();
+
+        assert!(result.starts_with(b"P6\n10 3\n255\n"));
+        assert!(result.len() == 12 + 10 * 3 * 3);
+    }
+
+    #[test]
+    fn font_as_text_with_draw_highlight() {
+        // LD V0, 1; LDSPR V0; DRW V0, V0, 5
+        let mut chip8 = create_test_chip8(1, vec![0x60, 0x01, 0xF0, 0x29, 0xD0, 0x05]);
+        for _ in 0..3 {
+            chip8.step();
+        }
+        let highlights = chip8.sprite_highlights(SpriteFormat::Chip8(5));
+
+        let result = sprite_text(chip8.read_memory(0, MEM_SIZE), FONT_ADDRESS, 10, SpriteFormat::Chip8(5), &highlights);
+
+        assert!(chip8.last_sprite() == Some((FONT_ADDRESS + 5, 5)));
+        assert!(highlights[0] == Highlight { start: FONT_ADDRESS + 5, length: 5, color: ADDRESS_REGISTER_COLOR, label: 'I' });
+        assert!(result.len() == 11);
+        assert!(result[0] == format!("0x{:03X}  ####....", FONT_ADDRESS));
+        assert!(result[5].is_empty());
+        assert!(result[6] == format!("0x{:03X}  ..#.....  I  D", FONT_ADDRESS + 5));
+    }
+}
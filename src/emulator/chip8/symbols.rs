@@ -0,0 +1,228 @@
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::prelude::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::builder::parse_number;
+use super::component::opcode::{ASM, Opcode};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SymbolKind {
+    Code,
+    Data,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u16,
+    pub kind: Option<SymbolKind>,
+}
+
+/// Names for addresses, as exported by Octo and other assemblers. Symbols are kept sorted by
+/// address.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        Default::default()
+    }
+
+    /// Parses one symbol per line, either `name = address` or `address name`, optionally followed
+    /// by `code` or `data`. Addresses are decimal or hexadecimal with a `0x` or `$` prefix, empty
+    /// lines and lines starting with `#` or `;` are skipped.
+    pub fn parse(text: &str) -> Result<SymbolTable, String> {
+        let mut table = SymbolTable::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let (name, address, kind) = parse_line(line).map_err(|error| format!("Line {}: {}", index + 1, error))?;
+            table.add(name, address, kind);
+        }
+        Ok(table)
+    }
+
+    /// Reads a symbol file in the format of `parse`.
+    #[cfg(feature = "std")]
+    pub fn from_file(filename: &str) -> Result<SymbolTable, String> {
+        let mut text = String::new();
+        File::open(filename)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|error| format!("Could not read {}: {}", filename, error))?;
+        SymbolTable::parse(&text).map_err(|error| format!("{}: {}", filename, error))
+    }
+
+    /// Adds a symbol, replacing an earlier one with the same name.
+    pub fn add(&mut self, name: &str, address: u16, kind: Option<SymbolKind>) {
+        self.symbols.retain(|symbol| symbol.name != name);
+        let index = self.symbols.iter().position(|symbol| symbol.address > address).unwrap_or(self.symbols.len());
+        self.symbols.insert(index, Symbol { name: String::from(name), address: address, kind: kind });
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        self.symbols.iter().find(|symbol| symbol.name == name).map(|symbol| symbol.address)
+    }
+
+    /// Returns the symbol at exactly `address`, preferring code labels over data.
+    pub fn symbol_at(&self, address: u16) -> Option<&Symbol> {
+        self.symbols.iter()
+            .find(|symbol| symbol.address == address && symbol.kind == Some(SymbolKind::Code))
+            .or_else(|| self.symbols.iter().find(|symbol| symbol.address == address))
+    }
+
+    /// Names `address` relative to the closest symbol at or before it, e.g. `draw_player+0x4`.
+    pub fn describe(&self, address: u16) -> Option<String> {
+        let symbol = self.symbol_at(address).or_else(|| self.symbols.iter().rev().find(|symbol| symbol.address < address))?;
+        if symbol.address == address {
+            Some(symbol.name.clone())
+        } else {
+            Some(format!("{}+0x{:X}", symbol.name, address - symbol.address))
+        }
+    }
+
+    /// Turns a number, a symbol name or `name+offset` into an address.
+    pub fn resolve(&self, text: &str) -> Result<u16, String> {
+        let (base, offset) = match text.find('+') {
+            Some(index) => (text[..index].trim(), parse_address(text[index + 1..].trim())?),
+            None => (text.trim(), 0),
+        };
+        let base = match self.lookup(base) {
+            Some(address) => address,
+            None => parse_address(base).map_err(|_| format!("Unknown symbol or address '{}'", base))?,
+        };
+        base.checked_add(offset).ok_or(format!("Address '{}' is out of range", text))
+    }
+
+    pub fn list(&self) -> &[Symbol] {
+        self.symbols.as_slice()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+fn parse_line(line: &str) -> Result<(&str, u16, Option<SymbolKind>), String> {
+    let tokens: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == '=').filter(|token| !token.is_empty()).collect();
+    if tokens.len() < 2 || tokens.len() > 3 {
+        return Err(format!("Expected 'name = address' or 'address name', got '{}'", line));
+    }
+    let (name, address) = match (parse_address(tokens[0]), parse_address(tokens[1])) {
+        (Err(_), Ok(address)) if line.contains('=') => (tokens[0], address),
+        (Ok(address), Err(_)) => (tokens[1], address),
+        _ => return Err(format!("Expected 'name = address' or 'address name', got '{}'", line)),
+    };
+    let kind = match tokens.get(2) {
+        Some(&"code") => Some(SymbolKind::Code),
+        Some(&"data") => Some(SymbolKind::Data),
+        Some(kind) => return Err(format!("Unknown symbol type '{}'", kind)),
+        None => None,
+    };
+    Ok((name, address, kind))
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    let number = if let Some(hex) = text.strip_prefix('$') {
+        u64::from_str_radix(hex, 16).map_err(|_| format!("Invalid number '{}'", text))?
+    } else {
+        parse_number(text)?
+    };
+    if number > 0xFFFF {
+        return Err(format!("Address '{}' is out of range", text));
+    }
+    Ok(number as u16)
+}
+
+/// Formats the instruction like `ASM`'s `Debug` output, but with the target of jumps, calls and
+/// `LDI` replaced by its symbol, e.g. `CALL draw_player` instead of `CALL(838)`.
+pub fn symbolize_asm(opcode: u16, symbols: &SymbolTable) -> String {
+    let asm = Opcode::new(opcode).as_asm();
+    let (mnemonic, address) = match asm {
+        ASM::SYS(address) => ("SYS", address),
+        ASM::JMP(address) => ("JMP", address),
+        ASM::CALL(address) => ("CALL", address),
+        ASM::LDI(address) => ("LDI", address),
+        ASM::RJMP(address) => ("RJMP", address),
+        _ => return format!("{:?}", asm),
+    };
+    match symbols.symbol_at(address) {
+        Some(symbol) => format!("{} {}", mnemonic, symbol.name),
+        None => format!("{:?}", asm),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{symbolize_asm, SymbolKind, SymbolTable};
+
+    const SYMBOLS: &'static str = "\
+# exported labels
+main = 0x200 code
+draw_player = 0x208
+0x300 sprite data
+$302 score
+";
+
+    #[test]
+    fn parse_both_line_formats() {
+        let under_test = SymbolTable::parse(SYMBOLS).unwrap();
+
+        assert!(under_test.list().len() == 4);
+        assert!(under_test.lookup("draw_player") == Some(0x208));
+        assert!(under_test.lookup("score") == Some(0x302));
+        assert!(under_test.symbol_at(0x300).unwrap().kind == Some(SymbolKind::Data));
+        assert!(under_test.symbol_at(0x200).unwrap().kind == Some(SymbolKind::Code));
+    }
+
+    #[test]
+    fn parse_reports_line() {
+        let result = SymbolTable::parse("main = 0x200\nbroken\n");
+
+        assert!(result == Err(String::from("Line 2: Expected 'name = address' or 'address name', got 'broken'")));
+        assert!(SymbolTable::parse("main = 0x200 text").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_file_reports_missing_file() {
+        let result = SymbolTable::from_file("/nonexistent/game.sym");
+
+        assert!(result.unwrap_err().starts_with("Could not read /nonexistent/game.sym"));
+    }
+
+    #[test]
+    fn describe_relative_to_previous_symbol() {
+        let under_test = SymbolTable::parse(SYMBOLS).unwrap();
+
+        assert!(under_test.describe(0x208) == Some(String::from("draw_player")));
+        assert!(under_test.describe(0x20C) == Some(String::from("draw_player+0x4")));
+        assert!(under_test.describe(0x100).is_none());
+    }
+
+    #[test]
+    fn resolve_names_offsets_and_numbers() {
+        let under_test = SymbolTable::parse(SYMBOLS).unwrap();
+
+        assert!(under_test.resolve("sprite") == Ok(0x300));
+        assert!(under_test.resolve("sprite+2") == Ok(0x302));
+        assert!(under_test.resolve("0x20A") == Ok(0x20A));
+        assert!(under_test.resolve("missing").is_err());
+    }
+
+    #[test]
+    fn symbolize_address_operands() {
+        let under_test = SymbolTable::parse(SYMBOLS).unwrap();
+
+        assert!(symbolize_asm(0x2208, &under_test) == "CALL draw_player");
+        assert!(symbolize_asm(0xA300, &under_test) == "LDI sprite");
+        assert!(symbolize_asm(0x220A, &under_test) == "CALL(522)");
+        assert!(symbolize_asm(0x6005, &under_test) == "LD(0, 5)");
+    }
+}
@@ -0,0 +1,460 @@
+use std::any::Any;
+use std::io;
+use std::io::prelude::*;
+
+use emulator::json::Json;
+use super::{Chip8, Inspect, REGISTER_COUNT, register_values};
+use super::component::memory::AccessKind;
+use super::component::opcode::Opcode;
+use super::observer::{Step, StepObserver};
+use super::symbols::symbolize_asm;
+
+const MAGIC: &'static [u8; 4] = b"R8TR";
+const VERSION: u8 = 1;
+const DT_INDEX: u8 = REGISTER_COUNT as u8;
+const ST_INDEX: u8 = REGISTER_COUNT as u8 + 1;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TraceFormat {
+    Jsonl,
+    Binary,
+}
+
+/// Limits a trace to instructions whose PC lies in `addresses` and that run during `frames`, both
+/// ranges inclusive.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct TraceFilter {
+    pub addresses: Option<(u16, u16)>,
+    pub frames: Option<(u64, u64)>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, pc: u16, frame: u64) -> bool {
+        self.addresses.map_or(true, |(start, end)| pc >= start && pc <= end)
+            && self.frames.map_or(true, |(first, last)| frame >= first && frame <= last)
+    }
+}
+
+/// One executed instruction. `registers` holds the V registers, DT (16) and ST (17) that the
+/// instruction changed, `writes` the memory it wrote in order.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TraceRecord {
+    pub cycle: u64,
+    pub frame: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub asm: String,
+    pub registers: Vec<(u8, u8)>,
+    pub i: u16,
+    pub writes: Vec<(u16, u8)>,
+    pub vf: u8,
+}
+
+impl TraceRecord {
+    pub fn to_json(&self) -> String {
+        let number = |value: u64| Json::Number(value as f64);
+        let registers = self.registers.iter()
+            .map(|&(register, value)| (register_name(register), number(value as u64)))
+            .collect();
+        let writes = self.writes.iter()
+            .map(|&(address, value)| Json::Array(vec![number(address as u64), number(value as u64)]))
+            .collect();
+        let members = vec![
+            ("cycle", number(self.cycle)),
+            ("frame", number(self.frame)),
+            ("pc", number(self.pc as u64)),
+            ("opcode", number(self.opcode as u64)),
+            ("asm", Json::String(self.asm.clone())),
+            ("registers", Json::Object(registers)),
+            ("i", number(self.i as u64)),
+            ("writes", Json::Array(writes)),
+            ("vf", number(self.vf as u64)),
+        ];
+        format!("{}", Json::Object(members.into_iter().map(|(key, value)| (String::from(key), value)).collect()))
+    }
+
+    /// Parses a JSONL line. Only `cycle`, `pc` and `opcode` are required so traces from other
+    /// emulators can leave out what they do not track, `asm` is decoded from the opcode if missing.
+    pub fn from_json(line: &str) -> Result<TraceRecord, String> {
+        let json = Json::parse(line)?;
+        let number = |key: &str, default: Option<u64>| -> Result<u64, String> {
+            match json.get(key) {
+                Some(value) => value.as_u64().ok_or(format!("'{}' is not a number", key)),
+                None => default.ok_or(format!("Missing '{}'", key)),
+            }
+        };
+        let opcode = number("opcode", None)? as u16;
+        let mut registers = Vec::new();
+        for (name, value) in json.get("registers").and_then(Json::as_object).unwrap_or(&[]) {
+            let register = parse_register_name(name).ok_or(format!("Unknown register '{}'", name))?;
+            registers.push((register, value.as_u64().ok_or(format!("'{}' is not a number", name))? as u8));
+        }
+        let mut writes = Vec::new();
+        for write in json.get("writes").and_then(Json::as_array).unwrap_or(&[]) {
+            match write.as_array() {
+                Some(pair) if pair.len() == 2 && pair[0].as_u64().is_some() && pair[1].as_u64().is_some() =>
+                    writes.push((pair[0].as_u64().unwrap() as u16, pair[1].as_u64().unwrap() as u8)),
+                _ => return Err(String::from("Writes must be [address, value] pairs")),
+            }
+        }
+        Ok(TraceRecord {
+            cycle: number("cycle", None)?,
+            frame: number("frame", Some(0))?,
+            pc: number("pc", None)? as u16,
+            opcode: opcode,
+            asm: json.get("asm").and_then(Json::as_str).map(String::from).unwrap_or(decode_asm(opcode)),
+            registers: registers,
+            i: number("i", Some(0))? as u16,
+            writes: writes,
+            vf: number("vf", Some(0))? as u8,
+        })
+    }
+
+    pub fn write_binary(&self, output: &mut Vec<u8>) {
+        push_u64(output, self.cycle);
+        push_u64(output, self.frame);
+        push_u16(output, self.pc);
+        push_u16(output, self.opcode);
+        push_u16(output, self.i);
+        output.push(self.vf);
+        output.push(self.registers.len() as u8);
+        for &(register, value) in &self.registers {
+            output.push(register);
+            output.push(value);
+        }
+        push_u16(output, self.writes.len() as u16);
+        for &(address, value) in &self.writes {
+            push_u16(output, address);
+            output.push(value);
+        }
+    }
+}
+
+pub fn register_name(register: u8) -> String {
+    match register {
+        DT_INDEX => String::from("DT"),
+        ST_INDEX => String::from("ST"),
+        _ => format!("V{:X}", register),
+    }
+}
+
+fn parse_register_name(name: &str) -> Option<u8> {
+    match name {
+        "DT" => Some(DT_INDEX),
+        "ST" => Some(ST_INDEX),
+        _ if name.len() == 2 && (name.starts_with('V') || name.starts_with('v')) => u8::from_str_radix(&name[1..], 16).ok(),
+        _ => None,
+    }
+}
+
+/// Reads a trace in either format, telling them apart by the binary header.
+pub fn read_trace(trace: &[u8]) -> Result<Vec<TraceRecord>, String> {
+    if trace.starts_with(MAGIC) {
+        return read_binary_trace(trace);
+    }
+    let text = String::from_utf8_lossy(trace);
+    text.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| TraceRecord::from_json(line).map_err(|error| format!("Line {}: {}", index + 1, error)))
+        .collect()
+}
+
+/// Reads a binary trace written by `TraceRecorder`. The binary format leaves out `asm`, it is
+/// decoded again from the opcode.
+pub fn read_binary_trace(trace: &[u8]) -> Result<Vec<TraceRecord>, String> {
+    let mut reader = TraceReader { trace: trace, position: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(String::from("Not a rust8 binary trace"));
+    }
+    let version = reader.take(1)?[0];
+    if version != VERSION {
+        return Err(format!("Unsupported trace version {}", version));
+    }
+    let mut records = Vec::new();
+    while reader.position < trace.len() {
+        let cycle = reader.take_u64()?;
+        let frame = reader.take_u64()?;
+        let pc = reader.take_u16()?;
+        let opcode = reader.take_u16()?;
+        let i = reader.take_u16()?;
+        let vf = reader.take(1)?[0];
+        let register_count = reader.take(1)?[0] as usize;
+        let registers = reader.take(register_count * 2)?.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+        let write_count = reader.take_u16()? as usize;
+        let writes = reader.take(write_count * 3)?.chunks(3).map(|write| (((write[0] as u16) << 8) + write[1] as u16, write[2])).collect();
+        records.push(TraceRecord {
+            cycle: cycle, frame: frame, pc: pc, opcode: opcode, asm: decode_asm(opcode),
+            registers: registers, i: i, writes: writes, vf: vf,
+        });
+    }
+    Ok(records)
+}
+
+fn decode_asm(opcode: u16) -> String {
+    format!("{:?}", Opcode::new(opcode).as_asm())
+}
+
+/// Writes a record for each observed instruction that passes the filter. `asm` names jump, call
+/// and `LDI` targets from the machine's symbol table. After a write fails nothing more is
+/// recorded, `finish` returns the error.
+pub struct TraceRecorder<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    filter: TraceFilter,
+    recorded: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> TraceRecorder<W> {
+    pub fn new(mut writer: W, format: TraceFormat) -> io::Result<TraceRecorder<W>> {
+        if format == TraceFormat::Binary {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&[VERSION])?;
+        }
+        Ok(TraceRecorder { writer: writer, format: format, filter: TraceFilter::default(), recorded: 0, error: None })
+    }
+
+    pub fn filter(mut self, filter: TraceFilter) -> TraceRecorder<W> {
+        self.filter = filter;
+        self
+    }
+
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Flushes the writer, or returns the error that stopped the recording.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.flush(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, record: &TraceRecord) -> io::Result<()> {
+        self.recorded += 1;
+        match self.format {
+            TraceFormat::Jsonl => writeln!(self.writer, "{}", record.to_json()),
+            TraceFormat::Binary => {
+                let mut bytes = Vec::new();
+                record.write_binary(&mut bytes);
+                self.writer.write_all(&bytes)
+            },
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> StepObserver for TraceRecorder<W> {
+    fn observe(&mut self, chip8: &Chip8, step: &Step) {
+        if self.error.is_some() || !self.filter.matches(step.pc, step.frame) {
+            return;
+        }
+        let after = register_values(chip8);
+        let record = TraceRecord {
+            cycle: step.cycle,
+            frame: step.frame,
+            pc: step.pc,
+            opcode: step.opcode,
+            asm: symbolize_asm(step.opcode, chip8.symbols()),
+            registers: (0..after.len()).filter(|index| step.registers[*index] != after[*index]).map(|index| (index as u8, after[index])).collect(),
+            i: chip8.get_address_register(),
+            writes: step.accesses.iter().filter(|access| access.kind == AccessKind::Write).map(|access| (access.address, access.value)).collect(),
+            vf: chip8.get_data_register(0xF),
+        };
+        if let Err(error) = self.write(&record) {
+            self.error = Some(error);
+        }
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
+
+fn push_u16(output: &mut Vec<u8>, value: u16) {
+    output.push((value >> 8) as u8);
+    output.push(value as u8);
+}
+
+fn push_u64(output: &mut Vec<u8>, value: u64) {
+    push_u16(output, (value >> 48) as u16);
+    push_u16(output, (value >> 32) as u16);
+    push_u16(output, (value >> 16) as u16);
+    push_u16(output, value as u16);
+}
+
+struct TraceReader<'a> {
+    trace: &'a [u8],
+    position: usize,
+}
+
+impl<'a> TraceReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.position + length > self.trace.len() {
+            return Err(String::from("Trace is truncated"));
+        }
+        let bytes = &self.trace[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn take_u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(((bytes[0] as u16) << 8) + bytes[1] as u16)
+    }
+
+    fn take_u64(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            value = (value << 16) + self.take_u16()? as u64;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_binary_trace, read_trace, TraceFilter, TraceFormat, TraceRecord, TraceRecorder};
+    use super::super::{create_test_chip8, Chip8, RunCondition, SymbolTable};
+
+    const ROM: [u8; 12] = [
+        0x60, 0x7B, // LD V0, 123
+        0xA3, 0x00, // LDI 0x300
+        0xF0, 0x33, // BCD V0
+        0x80, 0x04, // ADD V0, V0
+        0xF0, 0x15, // LD DT, V0
+        0x12, 0x0A, // JMP 0x20A
+    ];
+
+    // Records up to `instructions` instructions, stopping early when the machine halts.
+    fn record(chip8: &mut Chip8, recorder: TraceRecorder<Vec<u8>>, instructions: u64) -> Vec<u8> {
+        let id = chip8.observers_mut().add(Box::new(recorder));
+        chip8.run_until(&[RunCondition::Instructions(instructions), RunCondition::Halt]);
+        let recorder = chip8.observers_mut().get_mut::<TraceRecorder<Vec<u8>>>(id).unwrap();
+        recorder.finish().unwrap();
+        recorder.get_ref().clone()
+    }
+
+    fn record_jsonl(filter: TraceFilter) -> Vec<String> {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Jsonl).unwrap().filter(filter);
+        let output = record(&mut chip8, under_test, 10);
+        String::from_utf8(output).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn jsonl_records_registers_and_writes() {
+        let result = record_jsonl(TraceFilter::default());
+
+        assert!(result.len() == 6);
+        assert!(result[0] == r#"{"cycle":0,"frame":0,"pc":512,"opcode":24699,"asm":"LD(0, 123)","registers":{"V0":123},"i":0,"writes":[],"vf":0}"#);
+        assert!(result[2] == r#"{"cycle":2,"frame":1,"pc":516,"opcode":61491,"asm":"BCD(0)","registers":{},"i":768,"writes":[[768,1],[769,2],[770,3]],"vf":0}"#);
+        assert!(result[3].contains(r#""registers":{"V0":246}"#));
+        assert!(result[4].contains(r#""registers":{"DT":246}"#));
+    }
+
+    #[test]
+    fn asm_uses_symbols() {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        *chip8.symbols_mut() = SymbolTable::parse("digits = 0x300").unwrap();
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Jsonl).unwrap();
+
+        let output = String::from_utf8(record(&mut chip8, under_test, 2)).unwrap();
+
+        assert!(output.lines().nth(1).unwrap().contains(r#""asm":"LDI digits""#));
+    }
+
+    #[test]
+    fn jsonl_escapes_symbol_names() {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        *chip8.symbols_mut() = SymbolTable::parse("say\"hi\\ = 0x300").unwrap();
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Jsonl).unwrap();
+
+        let output = String::from_utf8(record(&mut chip8, under_test, 2)).unwrap();
+
+        let line = output.lines().nth(1).unwrap();
+        assert!(line.contains(r#""asm":"LDI say\"hi\\""#));
+        assert!(TraceRecord::from_json(line).unwrap().asm == "LDI say\"hi\\");
+    }
+
+    #[test]
+    fn vf_result_of_carry() {
+        let mut chip8 = create_test_chip8(2, vec![0x60, 0xF0, 0x80, 0x04]);
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Jsonl).unwrap();
+
+        let output = String::from_utf8(record(&mut chip8, under_test, 2)).unwrap();
+
+        assert!(output.lines().nth(1).unwrap().contains(r#""registers":{"V0":224,"VF":1},"i":0,"writes":[],"vf":1"#));
+    }
+
+    #[test]
+    fn filter_by_address_and_frame() {
+        let addresses = record_jsonl(TraceFilter { addresses: Some((0x204, 0x206)), frames: None });
+        let frames = record_jsonl(TraceFilter { addresses: None, frames: Some((2, 2)) });
+
+        assert!(addresses.len() == 2);
+        assert!(addresses[0].contains(r#""pc":516"#));
+        assert!(frames.len() == 2);
+        assert!(frames[0].contains(r#""cycle":4"#));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Binary).unwrap();
+        let trace = record(&mut chip8, under_test, 10);
+
+        let result = read_binary_trace(&trace).unwrap();
+
+        assert!(result.len() == 6);
+        assert!(result[2] == TraceRecord {
+            cycle: 2, frame: 1, pc: 0x204, opcode: 0xF033, asm: String::from("BCD(0)"),
+            registers: vec![], i: 0x300, writes: vec![(0x300, 1), (0x301, 2), (0x302, 3)], vf: 0,
+        });
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let lines = record_jsonl(TraceFilter::default());
+
+        let result = read_trace(lines.join("\n").as_bytes()).unwrap();
+
+        assert!(result.len() == 6);
+        assert!(result[2].writes == vec![(0x300, 1), (0x301, 2), (0x302, 3)]);
+        assert!(result.iter().map(TraceRecord::to_json).collect::<Vec<String>>() == lines);
+    }
+
+    #[test]
+    fn from_json_fills_in_optional_fields() {
+        let result = TraceRecord::from_json(r#"{"cycle":3,"pc":516,"opcode":61491,"registers":{"v1":2}}"#).unwrap();
+
+        assert!(result.asm == "BCD(0)");
+        assert!(result.registers == vec![(1, 2)]);
+        assert!(result.frame == 0);
+        assert!(TraceRecord::from_json(r#"{"cycle":3,"pc":516}"#) == Err(String::from("Missing 'opcode'")));
+    }
+
+    #[test]
+    fn read_binary_trace_rejects_truncated() {
+        let mut chip8 = create_test_chip8(2, ROM.to_vec());
+        let under_test = TraceRecorder::new(Vec::new(), TraceFormat::Binary).unwrap();
+        let mut trace = record(&mut chip8, under_test, 1);
+        trace.pop();
+
+        assert!(read_binary_trace(&trace).is_err());
+        assert!(read_binary_trace(b"NOPE").is_err());
+    }
+}
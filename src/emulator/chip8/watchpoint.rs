@@ -0,0 +1,211 @@
+use core::fmt::{self, Formatter, Display};
+use alloc::vec::Vec;
+
+use super::component::memory::{AccessKind, MemoryAccess};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Change,
+}
+
+impl WatchKind {
+    fn matches(&self, access: &MemoryAccess) -> bool {
+        match *self {
+            WatchKind::Read => access.kind == AccessKind::Read,
+            WatchKind::Write => access.kind == AccessKind::Write,
+            WatchKind::Change => access.kind == AccessKind::Write && access.old != access.value,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Watchpoint {
+    pub id: u32,
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+    pub enabled: bool,
+    pub hits: u64,
+}
+
+impl Display for Watchpoint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            WatchKind::Read => "read",
+            WatchKind::Write => "write",
+            WatchKind::Change => "change",
+        };
+        if self.start == self.end {
+            write!(f, "{} 0x{:03X}", kind, self.start)
+        } else {
+            write!(f, "{} 0x{:03X}-0x{:03X}", kind, self.start, self.end)
+        }
+    }
+}
+
+/// Reported when a watched address is accessed, with the PC and opcode of the instruction that
+/// caused the access.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct WatchpointHit {
+    pub id: u32,
+    pub pc: u16,
+    pub opcode: u16,
+    pub access: MemoryAccess,
+}
+
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Watchpoints {
+    watchpoints: Vec<Watchpoint>,
+    next_id: u32,
+}
+
+impl Watchpoints {
+    pub fn new() -> Watchpoints {
+        Default::default()
+    }
+
+    /// Watches the addresses from `start` to `end`, both inclusive. Every executed instruction is
+    /// checked and counted in `hits`, but only `Chip8::run_until` stops at a hit.
+    pub fn add(&mut self, start: u16, end: u16, kind: WatchKind) -> u32 {
+        self.next_id += 1;
+        self.watchpoints.push(Watchpoint { id: self.next_id, start: start, end: end, kind: kind, enabled: true, hits: 0 });
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|watchpoint| watchpoint.id != id);
+        self.watchpoints.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Watchpoint> {
+        self.watchpoints.iter().find(|watchpoint| watchpoint.id == id)
+    }
+
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        self.watchpoints.iter_mut()
+            .find(|watchpoint| watchpoint.id == id)
+            .map(|watchpoint| watchpoint.enabled = enabled)
+            .is_some()
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        self.watchpoints.as_slice()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    /// Counts a hit for every enabled watchpoint matching one of `accesses` and returns the first
+    /// match.
+    pub fn check(&mut self, accesses: &[MemoryAccess]) -> Option<(u32, MemoryAccess)> {
+        let mut first = None;
+        for access in accesses {
+            for watchpoint in self.watchpoints.iter_mut().filter(|watchpoint| watchpoint.enabled) {
+                if access.address < watchpoint.start || access.address > watchpoint.end || !watchpoint.kind.matches(access) {
+                    continue;
+                }
+                watchpoint.hits += 1;
+                if first.is_none() {
+                    first = Some((watchpoint.id, *access));
+                }
+            }
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WatchKind, WatchpointHit};
+    use super::super::{create_test_chip8, AccessKind, Chip8, Inspect, MemoryAccess, RunCondition, StopReason};
+    use emulator::Emulator;
+
+    const ROM: [u8; 18] = [
+        0x60, 0x7B, // LD V0, 123
+        0xA3, 0x00, // LDI 0x300
+        0xF0, 0x33, // BCD V0
+        0xF2, 0x65, // READ V2
+        0xF2, 0x33, // BCD V2
+        0x61, 0x05, // LD V1, 5
+        0xF1, 0x55, // STOR V1
+        0xD0, 0x13, // DRW V0, V1, 3
+        0x12, 0x10, // JMP 0x210
+    ];
+
+    fn run_to_watchpoint(chip8: &mut Chip8) -> WatchpointHit {
+        match chip8.run_until(&[RunCondition::Halt, RunCondition::Frames(10)]).reason {
+            StopReason::WatchpointHit(hit) => hit,
+            reason => panic!("expected watchpoint hit, got {:?}", reason),
+        }
+    }
+
+    #[test]
+    fn write_watchpoint_reports_instruction() {
+        let mut under_test = create_test_chip8(20, ROM.to_vec());
+        let id = under_test.watchpoints_mut().add(0x301, 0x302, WatchKind::Write);
+
+        let result = run_to_watchpoint(&mut under_test);
+
+        assert!(result.id == id);
+        assert!(result.pc == 0x204);
+        assert!(result.opcode == 0xF033);
+        assert!(result.access == MemoryAccess { kind: AccessKind::Write, address: 0x301, old: 0, value: 2 });
+        assert!(under_test.get_program_counter() == 0x206);
+    }
+
+    #[test]
+    fn read_watchpoint_fires_for_read_and_draw() {
+        let mut under_test = create_test_chip8(20, ROM.to_vec());
+        under_test.watchpoints_mut().add(0x302, 0x302, WatchKind::Read);
+
+        let read = run_to_watchpoint(&mut under_test);
+        let draw = run_to_watchpoint(&mut under_test);
+
+        assert!(read.opcode == 0xF265);
+        assert!(draw.opcode == 0xD013);
+        assert!(draw.access.value == 3);
+    }
+
+    #[test]
+    fn change_watchpoint_ignores_same_value() {
+        let mut under_test = create_test_chip8(20, ROM.to_vec());
+        under_test.watchpoints_mut().add(0x302, 0x302, WatchKind::Change);
+
+        let first = run_to_watchpoint(&mut under_test);
+        let second = under_test.run_until(&[RunCondition::Halt, RunCondition::Frames(10)]);
+
+        assert!(first.pc == 0x204);
+        assert!(first.access == MemoryAccess { kind: AccessKind::Write, address: 0x302, old: 0, value: 3 });
+        assert!(second.reason == StopReason::Halt);
+    }
+
+    #[test]
+    fn disabled_watchpoint_does_not_stop() {
+        let mut under_test = create_test_chip8(20, ROM.to_vec());
+        let id = under_test.watchpoints_mut().add(0x300, 0x3FF, WatchKind::Write);
+        under_test.watchpoints_mut().set_enabled(id, false);
+
+        let result = under_test.run_until(&[RunCondition::Halt, RunCondition::Frames(10)]);
+
+        assert!(result.reason == StopReason::Halt);
+    }
+
+    #[test]
+    fn update_counts_hits_without_recording_accesses() {
+        let mut under_test = create_test_chip8(20, ROM.to_vec());
+        let id = under_test.watchpoints_mut().add(0x300, 0x3FF, WatchKind::Write);
+
+        under_test.update();
+
+        assert!(under_test.watchpoints().get(id).unwrap().hits == 8);
+        assert!(under_test.memory.take_accesses().is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+use core::fmt::{self, Formatter, Display};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value, just enough for trace files and debug adapter messages. Objects keep their keys
+/// in order.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses `text`, arrays and objects may be nested `MAX_DEPTH` levels deep.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text: text.as_bytes(), position: 0, depth: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.text.len() {
+            return Err(format!("Unexpected character at {}", parser.position));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members.iter().find(|member| member.0 == key).map(|member| &member.1),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Json::Number(number) if number >= 0.0 && number == (number as u64) as f64 => Some(number as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Number(number) if number == (number as i64) as f64 => Some(number as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match *self {
+            Json::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match *self {
+            Json::Object(ref members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(ref value) => write_string(f, value),
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 { write!(f, ",")? }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref members) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 { write!(f, ",")? }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.text.len() && (self.text[self.position] as char).is_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.position).cloned()
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        if self.peek() != Some(expected) {
+            return Err(format!("Expected '{}' at {}", expected as char, self.position));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some(b'{') => self.parse_nested(Parser::parse_object),
+            Some(b'[') => self.parse_nested(Parser::parse_array),
+            Some(b'"') => self.parse_string().map(Json::String),
+            Some(b't') => self.parse_literal("true", Json::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Json::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Json::Null),
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.parse_number(),
+            _ => Err(format!("Unexpected character at {}", self.position)),
+        }
+    }
+
+    fn parse_nested(&mut self, parse: fn(&mut Parser<'a>) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("Nested deeper than {} levels at {}", MAX_DEPTH, self.position));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(literal.as_bytes()) {
+            return Err(format!("Unexpected character at {}", self.position));
+        }
+        self.position += literal.len();
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self.position < self.text.len() && b"+-.eE0123456789".contains(&self.text[self.position]) {
+            self.position += 1;
+        }
+        let number = core::str::from_utf8(&self.text[start..self.position]).unwrap_or("");
+        number.parse::<f64>().map(Json::Number).map_err(|_| format!("Invalid number at {}", start))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let c = *self.text.get(self.position).ok_or("Unterminated string")?;
+            self.position += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.position).ok_or("Unterminated string")?;
+                    self.position += 1;
+                    match escape {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(0x08),
+                        b'f' => bytes.push(0x0C),
+                        b'u' => {
+                            let hex = self.text.get(self.position..self.position + 4).ok_or("Unterminated string")?;
+                            let code = core::str::from_utf8(hex).ok().and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                            let c = code.and_then(core::char::from_u32).unwrap_or('\u{FFFD}');
+                            let mut buffer = [0u8; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                            self.position += 4;
+                        },
+                        other => bytes.push(other),
+                    }
+                },
+                other => bytes.push(other),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| String::from("Invalid UTF-8 in string"))
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                },
+                _ => return Err(format!("Expected ',' or ']' at {}", self.position)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            members.push((key, self.parse_value()?));
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(members));
+                },
+                _ => return Err(format!("Expected ',' or '}}' at {}", self.position)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn parse_nested_values() {
+        let result = Json::parse(r#" {"a": [1, -2.5, true, null], "b": {"c": "d\nA"}} "#).unwrap();
+
+        assert!(result.get("a").unwrap().as_array().unwrap() == [Json::Number(1.0), Json::Number(-2.5), Json::Bool(true), Json::Null]);
+        assert!(result.get("b").and_then(|b| b.get("c")).and_then(Json::as_str) == Some("d\nA"));
+        assert!(result.get("e").is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("\"open").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn nesting_deeper_than_max_depth_rejected() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(Json::parse(&nested(128)).is_ok());
+        assert!(Json::parse(&nested(129)).is_err());
+        assert!(Json::parse(&"[".repeat(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let value = Json::Object(vec![
+            (String::from("text"), Json::String(String::from("say \"hi\"\n"))),
+            (String::from("values"), Json::Array(vec![Json::Number(3.0), Json::Number(0.5), Json::Bool(false)])),
+        ]);
+
+        let result = format!("{}", value);
+
+        assert!(result == r#"{"text":"say \"hi\"\n","values":[3,0.5,false]}"#);
+        assert!(Json::parse(&result).unwrap() == value);
+    }
+
+    #[test]
+    fn as_u64_only_for_whole_numbers() {
+        assert!(Json::Number(42.0).as_u64() == Some(42));
+        assert!(Json::Number(4.5).as_u64().is_none());
+        assert!(Json::Number(-1.0).as_u64().is_none());
+    }
+}
@@ -0,0 +1,228 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum HexKey {
+    Key0, Key1, Key2, Key3,
+    Key4, Key5, Key6, Key7,
+    Key8, Key9, KeyA, KeyB,
+    KeyC, KeyD, KeyE, KeyF,
+}
+
+pub const HEX_KEYS: [HexKey; 16] = [
+    HexKey::Key0, HexKey::Key1, HexKey::Key2, HexKey::Key3,
+    HexKey::Key4, HexKey::Key5, HexKey::Key6, HexKey::Key7,
+    HexKey::Key8, HexKey::Key9, HexKey::KeyA, HexKey::KeyB,
+    HexKey::KeyC, HexKey::KeyD, HexKey::KeyE, HexKey::KeyF,
+];
+
+impl HexKey {
+    pub fn from_index(index: u8) -> Option<HexKey> {
+        HEX_KEYS.get(index as usize).cloned()
+    }
+
+    pub fn index(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn mask(&self) -> u16 {
+        0b1 << self.index()
+    }
+}
+
+/// Maps physical keys or buttons of type `K` to hex keys. A physical key may drive several hex
+/// keys and several physical keys may drive the same hex key, which stays pressed until the last
+/// of them is released.
+#[derive(Clone, Debug)]
+pub struct KeyMap<K> {
+    bindings: Vec<(K, HexKey)>,
+    held: Vec<K>,
+}
+
+impl<K: PartialEq + Clone> Default for KeyMap<K> {
+    fn default() -> KeyMap<K> {
+        KeyMap::new()
+    }
+}
+
+impl<K: PartialEq + Clone> KeyMap<K> {
+    pub fn new() -> KeyMap<K> {
+        KeyMap { bindings: Vec::new(), held: Vec::new() }
+    }
+
+    pub fn from_bindings(bindings: &[(K, HexKey)]) -> KeyMap<K> {
+        let mut map = KeyMap::new();
+        for &(ref key, hex_key) in bindings {
+            map.bind(key.clone(), hex_key);
+        }
+        map
+    }
+
+    pub fn bind(&mut self, key: K, hex_key: HexKey) {
+        if !self.bindings.iter().any(|binding| binding.0 == key && binding.1 == hex_key) {
+            self.bindings.push((key, hex_key));
+        }
+    }
+
+    /// Removes all bindings of `key` and returns the hex keys that are no longer pressed, like
+    /// `release` does when `key` was held, so the caller can release them on the emulator.
+    pub fn unbind(&mut self, key: &K) -> Vec<HexKey> {
+        let released = self.release(key);
+        self.bindings.retain(|binding| binding.0 != *key);
+        released
+    }
+
+    pub fn lookup(&self, key: &K) -> Vec<HexKey> {
+        self.bindings.iter().filter(|binding| binding.0 == *key).map(|binding| binding.1).collect()
+    }
+
+    pub fn is_pressed(&self, hex_key: HexKey) -> bool {
+        self.held.iter().any(|key| self.lookup(key).contains(&hex_key))
+    }
+
+    /// Marks `key` as held and returns the hex keys that became pressed.
+    pub fn press(&mut self, key: &K) -> Vec<HexKey> {
+        if self.held.contains(key) {
+            return Vec::new();
+        }
+        let newly_pressed = self.lookup(key).into_iter().filter(|hex_key| !self.is_pressed(*hex_key)).collect();
+        self.held.push(key.clone());
+        newly_pressed
+    }
+
+    /// Marks `key` as released and returns the hex keys that are no longer pressed.
+    pub fn release(&mut self, key: &K) -> Vec<HexKey> {
+        if !self.held.contains(key) {
+            return Vec::new();
+        }
+        self.held.retain(|held| held != key);
+        self.lookup(key).into_iter().filter(|hex_key| !self.is_pressed(*hex_key)).collect()
+    }
+}
+
+/// A default key map plus named overrides, usually one per ROM.
+#[derive(Clone, Debug)]
+pub struct KeyProfiles<K> {
+    default: KeyMap<K>,
+    profiles: Vec<(String, KeyMap<K>)>,
+}
+
+impl<K: PartialEq + Clone> KeyProfiles<K> {
+    pub fn new(default: KeyMap<K>) -> KeyProfiles<K> {
+        KeyProfiles { default: default, profiles: Vec::new() }
+    }
+
+    pub fn add_profile(&mut self, name: &str, map: KeyMap<K>) {
+        self.profiles.retain(|profile| profile.0 != name);
+        self.profiles.push((String::from(name), map));
+    }
+
+    /// Returns the profile whose name matches the ROM file name, without directory and extension.
+    pub fn for_rom(&self, filename: &str) -> &KeyMap<K> {
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+        let name = match name.rfind('.') {
+            Some(index) if index > 0 => &name[..index],
+            _ => name,
+        };
+        self.profiles.iter()
+            .find(|profile| profile.0 == name)
+            .map(|profile| &profile.1)
+            .unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HexKey, KeyMap, KeyProfiles};
+
+    #[test]
+    fn hex_key_from_index() {
+        assert!(HexKey::from_index(0xA) == Some(HexKey::KeyA));
+        assert!(HexKey::from_index(0x10).is_none());
+        assert!(HexKey::KeyF.mask() == 0b1000_0000_0000_0000);
+    }
+
+    #[test]
+    fn press_release_single_binding() {
+        let mut under_test = KeyMap::from_bindings(&[('w', HexKey::Key5)]);
+
+        let pressed = under_test.press(&'w');
+        let released = under_test.release(&'w');
+
+        assert!(pressed == vec![HexKey::Key5]);
+        assert!(released == vec![HexKey::Key5]);
+    }
+
+    #[test]
+    fn press_unbound_key() {
+        let mut under_test = KeyMap::from_bindings(&[('w', HexKey::Key5)]);
+
+        let result = under_test.press(&'x');
+
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn press_two_keys_for_same_hex_key() {
+        let mut under_test = KeyMap::from_bindings(&[('w', HexKey::Key2), ('i', HexKey::Key2)]);
+
+        let first = under_test.press(&'w');
+        let second = under_test.press(&'i');
+        let first_released = under_test.release(&'w');
+        let still_pressed = under_test.is_pressed(HexKey::Key2);
+        let second_released = under_test.release(&'i');
+
+        assert!(first == vec![HexKey::Key2]);
+        assert!(second.is_empty());
+        assert!(first_released.is_empty());
+        assert!(still_pressed);
+        assert!(second_released == vec![HexKey::Key2]);
+    }
+
+    #[test]
+    fn press_key_bound_to_two_hex_keys() {
+        let mut under_test = KeyMap::from_bindings(&[(' ', HexKey::Key4), (' ', HexKey::Key6)]);
+
+        let result = under_test.press(&' ');
+
+        assert!(result == vec![HexKey::Key4, HexKey::Key6]);
+    }
+
+    #[test]
+    fn unbind_releases_held_key() {
+        let mut under_test = KeyMap::from_bindings(&[('w', HexKey::Key5)]);
+        under_test.press(&'w');
+
+        let released = under_test.unbind(&'w');
+
+        assert!(released == vec![HexKey::Key5]);
+        assert!(!under_test.is_pressed(HexKey::Key5));
+        assert!(under_test.lookup(&'w').is_empty());
+    }
+
+    #[test]
+    fn unbind_keeps_hex_key_held_by_other_key() {
+        let mut under_test = KeyMap::from_bindings(&[('w', HexKey::Key2), ('w', HexKey::Key3), ('i', HexKey::Key2)]);
+        under_test.press(&'w');
+        under_test.press(&'i');
+
+        let released = under_test.unbind(&'w');
+        let not_held = under_test.unbind(&'x');
+
+        assert!(released == vec![HexKey::Key3]);
+        assert!(not_held.is_empty());
+        assert!(under_test.is_pressed(HexKey::Key2));
+    }
+
+    #[test]
+    fn for_rom_uses_matching_profile() {
+        let mut under_test = KeyProfiles::new(KeyMap::from_bindings(&[('w', HexKey::Key5)]));
+        under_test.add_profile("pong", KeyMap::from_bindings(&[('w', HexKey::Key1)]));
+
+        let pong = under_test.for_rom("games/pong.ch8").lookup(&'w');
+        let other = under_test.for_rom("games/tetris.ch8").lookup(&'w');
+
+        assert!(pong == vec![HexKey::Key1]);
+        assert!(other == vec![HexKey::Key5]);
+    }
+}
@@ -1,13 +1,38 @@
+pub mod audio;
 pub mod chip8;
+pub mod json;
+pub mod keypad;
+pub mod overlay;
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod runner;
+pub mod scheduler;
 
-pub trait Emulator {
+use core::time::Duration;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use self::chip8::Inspect;
+use self::keypad::HexKey;
+
+pub trait Emulator: Send {
     fn retrieve_screen_pixels(&self) -> &[bool];
     fn retrieve_screen_size(&self) -> (usize, usize);
+    /// Runs one frame, the configured instructions per frame followed by a timer tick.
     fn update(&mut self);
+    fn advance(&mut self, elapsed: Duration) -> u32;
+    /// Executes one instruction and returns whether it ended a frame.
+    fn step(&mut self) -> bool;
+    fn reset(&mut self);
     fn get_name(&self) -> &str;
     fn load(&mut self, game_data: Vec<u8>);
-    fn press_key(&mut self, key: u16);
-    fn release_key(&mut self, key: u16);
+    fn press_key(&mut self, key: HexKey);
+    fn release_key(&mut self, key: HexKey);
     fn needs_redraw(&mut self) -> bool;
     fn should_beep(&mut self) -> bool;
+    fn is_sound_playing(&self) -> bool;
+    fn save_state(&self) -> Vec<u8>;
+    fn load_state(&mut self, state: &[u8]) -> Result<(), String>;
+    fn inspect(&self) -> Option<&Inspect> { None }
+    fn inspect_mut(&mut self) -> Option<&mut Inspect> { None }
 }
\ No newline at end of file
@@ -0,0 +1,262 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use emulator::chip8::{disassemble, Inspect, MEM_SIZE, REGISTER_COUNT};
+use emulator::chip8::draws::{calls_at, DrawCall, DrawOp};
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+pub const PANEL_COLUMNS: usize = 32;
+
+const MEMORY_ROW_LENGTH: usize = 8;
+
+/// A copy of the machine state taken between frames, so a front end can show it while the
+/// emulator keeps running on another thread.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MachineSnapshot {
+    pub registers: [u8; REGISTER_COUNT],
+    pub address_register: u16,
+    pub program_counter: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub pressed_keys: u16,
+    pub memory: Vec<u8>,
+    pub draws: Vec<DrawCall>,
+}
+
+impl MachineSnapshot {
+    pub fn capture(machine: &Inspect) -> MachineSnapshot {
+        let mut registers = [0; REGISTER_COUNT];
+        for (register, value) in registers.iter_mut().enumerate() {
+            *value = machine.get_data_register(register as u8);
+        }
+        MachineSnapshot {
+            registers: registers,
+            address_register: machine.get_address_register(),
+            program_counter: machine.get_program_counter(),
+            stack: machine.get_stack().to_vec(),
+            delay_timer: machine.get_delay_timer(),
+            sound_timer: machine.get_sound_timer(),
+            pressed_keys: machine.get_pressed_keys(),
+            memory: machine.read_memory(0, MEM_SIZE).to_vec(),
+            draws: machine.get_draw_calls(),
+        }
+    }
+
+    fn opcode_at(&self, address: usize) -> u16 {
+        ((self.memory[address] as u16) << 8) + self.memory[address + 1] as u16
+    }
+}
+
+/// Text of the debug panel: registers, timers, stack, keys, `disassembly` instructions around PC
+/// and `memory_rows` rows of memory starting at the row that contains I. Lines are at most
+/// `PANEL_COLUMNS` characters long.
+pub fn panel_lines(snapshot: &MachineSnapshot, disassembly: usize, memory_rows: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (row, values) in snapshot.registers.chunks(4).enumerate() {
+        let registers: Vec<String> = values.iter().enumerate()
+            .map(|(column, value)| format!("V{:X} {:02X}", row * 4 + column, value))
+            .collect();
+        lines.push(registers.join("  "));
+    }
+    lines.push(format!("I {:03X}  PC {:03X}  DT {:02X}  ST {:02X}",
+        snapshot.address_register, snapshot.program_counter, snapshot.delay_timer, snapshot.sound_timer));
+    let stack: Vec<String> = snapshot.stack.iter().rev().map(|address| format!("{:03X}", address)).collect();
+    lines.push(format!("SP {:X}  {}", snapshot.stack.len(), stack.join(" ")));
+    let keys: String = (0..16)
+        .map(|key| if snapshot.pressed_keys & (1 << key) != 0 { format!("{:X}", key) } else { String::from(".") })
+        .collect();
+    lines.push(format!("KEYS {}", keys));
+
+    lines.push(String::new());
+    let pc = snapshot.program_counter as usize;
+    let start = pc.saturating_sub(disassembly / 2 * 2);
+    for address in (0..disassembly).map(|line| start + line * 2).filter(|address| address + 1 < MEM_SIZE) {
+        let marker = if address == pc { ">" } else { " " };
+        lines.push(format!("{}{:03X} {}", marker, address, disassemble(snapshot.opcode_at(address))));
+    }
+
+    lines.push(String::new());
+    let start = snapshot.address_register as usize / MEMORY_ROW_LENGTH * MEMORY_ROW_LENGTH;
+    for address in (0..memory_rows).map(|row| start + row * MEMORY_ROW_LENGTH).filter(|address| *address < MEM_SIZE) {
+        let bytes: Vec<String> = snapshot.memory[address..address + MEMORY_ROW_LENGTH].iter().map(|byte| format!("{:02X}", byte)).collect();
+        lines.push(format!("{:03X} {}", address, bytes.join(" ")));
+    }
+    for line in lines.iter_mut() {
+        line.truncate(PANEL_COLUMNS);
+    }
+    lines
+}
+
+/// Text listing the last `limit` draw calls of the previous and current frame that flipped the
+/// pixel at `x`, `y`: frame, PC, I, height, coordinates and `C` for a collision.
+pub fn draw_lines(snapshot: &MachineSnapshot, x: u8, y: u8, limit: usize) -> Vec<String> {
+    let calls = calls_at(&snapshot.draws, x, y);
+    let mut lines = vec![format!("PIXEL {},{}  {} DRAWS", x, y, calls.len())];
+    for call in calls.iter().skip(calls.len().saturating_sub(limit)) {
+        let mut line = match call.op {
+            DrawOp::Clear => format!("F{} {:03X} CLS", call.frame, call.pc),
+            DrawOp::Sprite { address, height, x, y, collision } =>
+                format!("F{} {:03X} DRW {:03X} H{} {},{}{}", call.frame, call.pc, address, height, x, y, if collision { " C" } else { "" }),
+        };
+        line.truncate(PANEL_COLUMNS);
+        lines.push(line);
+    }
+    lines
+}
+
+/// Rows of a 5x7 pixel glyph, the most significant of the five low bits is the leftmost pixel.
+/// Letters are drawn upper case, except `x` for hexadecimal numbers, unknown characters as `?`.
+pub fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    let character = if character == 'x' { character } else { character.to_ascii_uppercase() };
+    match character {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        'x' => [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '*' => [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Positions of the lit pixels of `text` drawn from the top left corner, one pixel of space
+/// between characters.
+pub fn text_pixels(text: &str) -> Vec<(usize, usize)> {
+    let mut pixels = Vec::new();
+    for (index, character) in text.chars().enumerate() {
+        for (y, row) in glyph(character).iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row & (0x10 >> x) != 0 {
+                    pixels.push((index * (GLYPH_WIDTH + 1) + x, y));
+                }
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw_lines, glyph, panel_lines, text_pixels, MachineSnapshot, PANEL_COLUMNS};
+    use emulator::Emulator;
+    use emulator::chip8::{create_test_chip8, Inspect};
+    use emulator::keypad::HexKey;
+
+    fn create_snapshot() -> MachineSnapshot {
+        // LD V0, 5; LDI 0x304; CALL 0x208; JMP 0x206; RET
+        let mut chip8 = create_test_chip8(1, vec![0x60, 0x05, 0xA3, 0x04, 0x22, 0x08, 0x12, 0x06, 0x00, 0xEE]);
+        chip8.write_memory(0x300, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        chip8.press_key(HexKey::KeyA);
+        for _ in 0..3 {
+            chip8.step();
+        }
+        MachineSnapshot::capture(&chip8)
+    }
+
+    #[test]
+    fn capture_machine_state() {
+        let under_test = create_snapshot();
+
+        assert!(under_test.registers[0] == 5);
+        assert!(under_test.address_register == 0x304);
+        assert!(under_test.program_counter == 0x208);
+        assert!(under_test.stack == vec![0x206]);
+        assert!(under_test.memory[0x301] == 0xAD);
+    }
+
+    #[test]
+    fn panel_shows_registers_stack_and_keys() {
+        let under_test = panel_lines(&create_snapshot(), 5, 2);
+
+        assert!(under_test[0] == "V0 05  V1 00  V2 00  V3 00");
+        assert!(under_test[4] == "I 304  PC 208  DT 00  ST 00");
+        assert!(under_test[5] == "SP 1  206");
+        assert!(under_test[6] == "KEYS ..........A.....");
+        assert!(under_test.iter().all(|line| line.len() <= PANEL_COLUMNS));
+    }
+
+    #[test]
+    fn panel_follows_pc_and_i() {
+        let under_test = panel_lines(&create_snapshot(), 5, 2);
+
+        assert!(under_test[8] == " 204 0x2208: CALL(520)");
+        assert!(under_test[10] == ">208 0x00EE: RET");
+        assert!(under_test[14] == "300 DE AD BE EF 00 00 00 00");
+        assert!(under_test.len() == 16);
+    }
+
+    #[test]
+    fn draw_lines_for_pixel() {
+        // LDI 0x20A; DRW V0, V0, 1; CLS; DRW V0, V0, 1; JMP 0x208; one sprite row
+        let mut chip8 = create_test_chip8(10, vec![0xA2, 0x0A, 0xD0, 0x01, 0x00, 0xE0, 0xD0, 0x01, 0x12, 0x08, 0x80]);
+        chip8.set_draw_log(true);
+        for _ in 0..4 {
+            chip8.step();
+        }
+        let snapshot = MachineSnapshot::capture(&chip8);
+
+        let under_test = draw_lines(&snapshot, 0, 0, 2);
+
+        assert!(under_test == vec!["PIXEL 0,0  3 DRAWS", "F0 204 CLS", "F0 206 DRW 20A H1 0,0"]);
+        assert!(draw_lines(&snapshot, 1, 0, 2) == vec!["PIXEL 1,0  0 DRAWS"]);
+    }
+
+    #[test]
+    fn glyphs_for_text() {
+        assert!(glyph('a') == glyph('A'));
+        assert!(glyph('x') != glyph('X'));
+        assert!(glyph('~') == glyph('?'));
+        assert!(text_pixels("1") == vec![(2, 0), (1, 1), (2, 1), (2, 2), (2, 3), (2, 4), (2, 5), (1, 6), (2, 6), (3, 6)]);
+        assert!(text_pixels(" -")[0] == (6, 3));
+    }
+}
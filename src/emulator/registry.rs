@@ -0,0 +1,183 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use emulator::Emulator;
+use emulator::chip8::{Chip8Builder, Variant};
+
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct Capabilities {
+    pub sound: bool,
+    pub high_resolution: bool,
+    pub color: bool,
+    pub save_states: bool,
+}
+
+pub struct SystemDescriptor {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub extensions: &'static [&'static str],
+    pub capabilities: Capabilities,
+    factory: fn() -> Result<Box<Emulator>, String>,
+}
+
+impl SystemDescriptor {
+    pub fn new(name: &'static str, description: &'static str, extensions: &'static [&'static str],
+               capabilities: Capabilities, factory: fn() -> Result<Box<Emulator>, String>) -> SystemDescriptor {
+        SystemDescriptor {
+            name: name,
+            description: description,
+            extensions: extensions,
+            capabilities: capabilities,
+            factory: factory,
+        }
+    }
+
+    pub fn create(&self) -> Result<Box<Emulator>, String> {
+        (self.factory)()
+    }
+
+    pub fn supports_extension(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|supported| supported.eq_ignore_ascii_case(extension))
+    }
+}
+
+pub struct Registry {
+    systems: Vec<SystemDescriptor>,
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        let mut registry = Registry::new();
+        registry.register(SystemDescriptor::new(
+            "chip8", "CHIP-8 interpreter", &["ch8", "c8"],
+            Capabilities { sound: true, save_states: true, ..Default::default() }, create_chip8));
+        registry
+    }
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { systems: Vec::new() }
+    }
+
+    pub fn register(&mut self, system: SystemDescriptor) {
+        self.systems.retain(|registered| registered.name != system.name);
+        self.systems.push(system);
+    }
+
+    pub fn systems(&self) -> &[SystemDescriptor] {
+        self.systems.as_slice()
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&SystemDescriptor> {
+        self.systems.iter().find(|system| system.name == name)
+    }
+
+    pub fn find_by_extension(&self, extension: &str) -> Option<&SystemDescriptor> {
+        self.systems.iter().find(|system| system.supports_extension(extension))
+    }
+
+    pub fn find_for_file(&self, filename: &str) -> Option<&SystemDescriptor> {
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+        match name.rfind('.') {
+            Some(index) if index > 0 => self.find_by_extension(&name[index + 1..]),
+            _ => None,
+        }
+    }
+}
+
+fn create_variant(variant: Variant) -> Result<Box<Emulator>, String> {
+    match Chip8Builder::new().variant(variant).build() {
+        Ok(chip8) => Ok(Box::new(chip8)),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn create_chip8() -> Result<Box<Emulator>, String> {
+    create_variant(Variant::Chip8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Registry, SystemDescriptor};
+    use emulator::Emulator;
+
+    fn create_failing() -> Result<Box<Emulator>, String> {
+        Err(String::from("failing"))
+    }
+
+    #[test]
+    fn find_by_name_success() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_by_name("chip8").unwrap();
+
+        assert!(result.capabilities == Capabilities { sound: true, save_states: true, ..Default::default() });
+    }
+
+    #[test]
+    fn find_by_name_unknown() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_by_name("nes");
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn find_for_file_ignores_case() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_for_file("games/PONG.CH8").unwrap();
+
+        assert!(result.name == "chip8");
+    }
+
+    #[test]
+    fn find_for_file_unsupported_variant() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_for_file("roms/t8nks.xo8");
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn find_for_file_dot_in_directory() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_for_file("roms.c8/game");
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn find_for_file_without_extension() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_for_file("games/game");
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn create_chip8_success() {
+        let under_test: Registry = Default::default();
+
+        let result = under_test.find_by_name("chip8").unwrap().create();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn register_replaces_same_name() {
+        let mut under_test: Registry = Default::default();
+        under_test.register(SystemDescriptor::new("chip8", "replacement", &["ch8"], Capabilities::default(), create_failing));
+
+        let result = under_test.find_by_name("chip8").unwrap();
+
+        assert!(under_test.systems().len() == 1);
+        assert!(result.create().is_err());
+    }
+}
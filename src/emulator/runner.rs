@@ -0,0 +1,324 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use emulator::Emulator;
+use emulator::audio::SquareWave;
+use emulator::keypad::HexKey;
+use emulator::overlay::MachineSnapshot;
+use emulator::scheduler::{FrameScheduler, MAX_CATCH_UP_FRAMES};
+
+pub const DEFAULT_FRAMES_PER_SECOND: u32 = 60;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Command {
+    Load(Vec<u8>),
+    Pause,
+    Resume,
+    Step,
+    AdvanceFrame,
+    Reset,
+    PressKey(HexKey),
+    ReleaseKey(HexKey),
+    SaveState,
+    LoadState(Vec<u8>),
+    Inspect(bool),
+    Shutdown,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Frame {
+    pub number: u64,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<bool>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Event {
+    Frame(Frame),
+    Audio(Vec<i16>),
+    State(Vec<u8>),
+    Machine(MachineSnapshot),
+    Error(String),
+}
+
+/// Owns an emulator on a background thread. The runner starts paused, send `Command::Resume`
+/// to run at `frames_per_second`, at least 1. Commands are processed between frames, every
+/// completed frame is published as an `Event::Frame` followed by its `Event::Audio` chunk. After
+/// `Command::Inspect(true)` every frame and step is also followed by an `Event::Machine`, which
+/// includes the draw calls of the last two frames.
+pub struct Runner {
+    commands: Sender<Command>,
+    events: Receiver<Event>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Runner {
+    pub fn spawn(emulator: Box<Emulator>, frames_per_second: u32, sample_rate: u32) -> Runner {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut worker = Worker {
+                emulator: emulator,
+                events: event_sender,
+                wave: SquareWave::new(sample_rate),
+                frames_per_second: frames_per_second.max(1),
+                frame_number: 0,
+                paused: true,
+                inspecting: false,
+            };
+            worker.run(command_receiver);
+        });
+        Runner { commands: command_sender, events: event_receiver, thread: Some(thread) }
+    }
+
+    pub fn send(&self, command: Command) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    pub fn try_recv(&self) -> Option<Event> {
+        self.events.try_recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Emulation thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Worker {
+    emulator: Box<Emulator>,
+    events: Sender<Event>,
+    wave: SquareWave,
+    frames_per_second: u32,
+    frame_number: u64,
+    paused: bool,
+    inspecting: bool,
+}
+
+impl Worker {
+    fn run(&mut self, commands: Receiver<Command>) {
+        let mut scheduler = FrameScheduler::new(self.frames_per_second, MAX_CATCH_UP_FRAMES);
+        let mut last_tick = Instant::now();
+        loop {
+            let command = if self.paused {
+                match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => return,
+                }
+            } else {
+                match commands.recv_timeout(scheduler.time_until_next_frame()) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            };
+
+            let now = Instant::now();
+            let elapsed = now - last_tick;
+            last_tick = now;
+            let frames = if self.paused { 0 } else { scheduler.frames_due(elapsed) };
+            let running = (0..frames).all(|_| {
+                self.emulator.update();
+                self.publish_frame()
+            });
+            let running = running && match command {
+                Some(command) => self.handle(command),
+                None => true,
+            };
+            if !running {
+                return;
+            }
+        }
+    }
+
+    fn handle(&mut self, command: Command) -> bool {
+        match command {
+            Command::Load(rom) => self.emulator.load(rom),
+            Command::Pause => {
+                self.paused = true;
+                return self.publish_machine();
+            },
+            Command::Resume => self.paused = false,
+            Command::Step => {
+                self.emulator.step();
+                return self.send(Event::Frame(self.frame())) && self.publish_machine();
+            },
+            Command::AdvanceFrame => {
+                self.emulator.update();
+                return self.publish_frame();
+            },
+            Command::Reset => {
+                self.emulator.reset();
+                self.frame_number = 0;
+            },
+            Command::PressKey(key) => self.emulator.press_key(key),
+            Command::ReleaseKey(key) => self.emulator.release_key(key),
+            Command::SaveState => {
+                let state = self.emulator.save_state();
+                return self.send(Event::State(state));
+            },
+            Command::LoadState(state) => {
+                if let Err(error) = self.emulator.load_state(&state) {
+                    return self.send(Event::Error(error));
+                }
+            },
+            Command::Inspect(inspecting) => {
+                self.inspecting = inspecting;
+                if let Some(machine) = self.emulator.inspect_mut() {
+                    machine.set_draw_log(inspecting);
+                }
+                return self.publish_machine();
+            },
+            Command::Shutdown => return false,
+        }
+        true
+    }
+
+    fn publish_frame(&mut self) -> bool {
+        self.frame_number += 1;
+        let frame = self.frame();
+        let mut samples = vec![0; self.wave.samples_per_frame(self.frames_per_second)];
+        self.wave.fill(&mut samples, self.emulator.is_sound_playing());
+        self.send(Event::Frame(frame)) && self.send(Event::Audio(samples)) && self.publish_machine()
+    }
+
+    fn publish_machine(&self) -> bool {
+        if !self.inspecting {
+            return true;
+        }
+        match self.emulator.inspect() {
+            Some(machine) => self.send(Event::Machine(MachineSnapshot::capture(machine))),
+            None => true,
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        let (width, height) = self.emulator.retrieve_screen_size();
+        Frame {
+            number: self.frame_number,
+            width: width,
+            height: height,
+            pixels: self.emulator.retrieve_screen_pixels().to_vec(),
+        }
+    }
+
+    fn send(&self, event: Event) -> bool {
+        self.events.send(event).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Command, Event, Runner};
+    use emulator::chip8::create_test_chip8;
+
+    const TIMEOUT_MS: u64 = 2000;
+
+    fn create_paused_runner(rom: Vec<u8>) -> Runner {
+        let under_test = Runner::spawn(Box::new(create_test_chip8(1, Vec::new())), 1000, 8000);
+        under_test.send(Command::Load(rom));
+        under_test
+    }
+
+    fn next_event(runner: &Runner) -> Event {
+        runner.recv_timeout(Duration::from_millis(TIMEOUT_MS)).expect("no event from runner")
+    }
+
+    #[test]
+    fn advance_frame_publishes_frame_and_audio() {
+        let under_test = create_paused_runner(vec![0x60, 0x05, 0xF0, 0x18, 0x12, 0x04]);
+        under_test.send(Command::AdvanceFrame);
+        under_test.send(Command::AdvanceFrame);
+        under_test.send(Command::AdvanceFrame);
+
+        let events: Vec<Event> = (0..6).map(|_| next_event(&under_test)).collect();
+
+        match events[4] {
+            Event::Frame(ref frame) => {
+                assert!(frame.number == 3);
+                assert!((frame.width, frame.height) == (64, 32));
+            },
+            ref event => panic!("expected frame, got {:?}", event),
+        }
+        match events[5] {
+            Event::Audio(ref samples) => {
+                assert!(samples.len() == 8);
+                assert!(samples.iter().any(|sample| *sample != 0));
+            },
+            ref event => panic!("expected audio, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn save_and_load_state() {
+        let under_test = create_paused_runner(vec![0x70, 0x01, 0x12, 0x00]);
+        under_test.send(Command::SaveState);
+        let state = match next_event(&under_test) {
+            Event::State(state) => state,
+            event => panic!("expected state, got {:?}", event),
+        };
+        under_test.send(Command::LoadState(state));
+        under_test.send(Command::LoadState(b"NOPE".to_vec()));
+
+        let result = next_event(&under_test);
+
+        assert!(result == Event::Error(String::from("Not a rust8 save state")));
+    }
+
+    #[test]
+    fn inspect_publishes_machine_after_steps() {
+        let under_test = create_paused_runner(vec![0x60, 0x05, 0xA3, 0x00, 0x12, 0x04]);
+        under_test.send(Command::Inspect(true));
+        under_test.send(Command::Step);
+        under_test.send(Command::Step);
+
+        let events: Vec<Event> = (0..5).map(|_| next_event(&under_test)).collect();
+
+        match (&events[0], &events[4]) {
+            (Event::Machine(first), Event::Machine(last)) => {
+                assert!(first.program_counter == 0x200);
+                assert!(last.registers[0] == 5 && last.address_register == 0x300 && last.program_counter == 0x204);
+            },
+            events => panic!("expected machine snapshots, got {:?}", events),
+        }
+        match events[3] {
+            Event::Frame(_) => (),
+            ref event => panic!("expected frame, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn resume_runs_frames() {
+        let under_test = create_paused_runner(vec![0x12, 0x00]);
+        under_test.send(Command::Resume);
+
+        let frames = (0..10)
+            .filter(|_| matches!(next_event(&under_test), Event::Frame(_)))
+            .count();
+
+        assert!(frames == 5);
+        under_test.shutdown();
+    }
+}
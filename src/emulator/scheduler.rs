@@ -0,0 +1,103 @@
+use core::time::Duration;
+
+pub const FRAMES_PER_SECOND: u32 = 60;
+pub const MAX_CATCH_UP_FRAMES: u32 = 6;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Turns elapsed wall-clock time into a number of frames to run. Time that does not add up to a
+/// whole frame is carried over to the next call; after a stall at most `max_catch_up` frames are
+/// reported and the rest of the backlog is dropped. A rate of 0 frames per second counts as 1.
+#[derive(PartialEq, Clone, Debug)]
+pub struct FrameScheduler {
+    frame_nanos: u64,
+    max_catch_up: u32,
+    leftover_nanos: u64,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> FrameScheduler {
+        FrameScheduler::new(FRAMES_PER_SECOND, MAX_CATCH_UP_FRAMES)
+    }
+}
+
+impl FrameScheduler {
+    pub fn new(frames_per_second: u32, max_catch_up: u32) -> FrameScheduler {
+        FrameScheduler {
+            frame_nanos: (NANOS_PER_SECOND / frames_per_second.max(1) as u64).max(1),
+            max_catch_up: max_catch_up,
+            leftover_nanos: 0,
+        }
+    }
+
+    pub fn frames_due(&mut self, elapsed: Duration) -> u32 {
+        let elapsed_nanos = elapsed.as_secs().saturating_mul(NANOS_PER_SECOND).saturating_add(elapsed.subsec_nanos() as u64);
+        let available = self.leftover_nanos.saturating_add(elapsed_nanos);
+        let frames = available / self.frame_nanos;
+        self.leftover_nanos = available % self.frame_nanos;
+        if frames > self.max_catch_up as u64 {
+            self.max_catch_up
+        } else {
+            frames as u32
+        }
+    }
+
+    pub fn time_until_next_frame(&self) -> Duration {
+        Duration::new(0, (self.frame_nanos - self.leftover_nanos) as u32)
+    }
+
+    pub fn reset(&mut self) {
+        self.leftover_nanos = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::FrameScheduler;
+
+    #[test]
+    fn frames_due_whole_frames() {
+        let mut under_test = FrameScheduler::new(50, 6);
+
+        let result = under_test.frames_due(Duration::from_millis(60));
+
+        assert!(result == 3);
+    }
+
+    #[test]
+    fn frames_due_carries_leftover() {
+        let mut under_test = FrameScheduler::new(50, 6);
+
+        let first = under_test.frames_due(Duration::from_millis(15));
+        let second = under_test.frames_due(Duration::from_millis(15));
+        let third = under_test.frames_due(Duration::from_millis(15));
+
+        assert!(first == 0);
+        assert!(second == 1);
+        assert!(third == 1);
+        assert!(under_test.time_until_next_frame() == Duration::from_millis(15));
+    }
+
+    #[test]
+    fn zero_frames_per_second_clamped() {
+        let mut under_test = FrameScheduler::new(0, 6);
+
+        let result = under_test.frames_due(Duration::from_millis(2500));
+
+        assert!(result == 2);
+        assert!(under_test.time_until_next_frame() == Duration::from_millis(500));
+    }
+
+    #[test]
+    fn frames_due_caps_catch_up() {
+        let mut under_test = FrameScheduler::new(50, 6);
+
+        let stalled = under_test.frames_due(Duration::from_secs(10));
+        let next = under_test.frames_due(Duration::from_millis(20));
+
+        assert!(stalled == 6);
+        assert!(next == 1);
+    }
+}
@@ -0,0 +1,306 @@
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::time::Duration;
+
+use emulator::Emulator;
+use emulator::audio::SquareWave;
+use emulator::keypad::HexKey;
+use emulator::chip8::{Chip8, Chip8Builder, Inspect};
+
+pub const RUST8_OK: c_int = 0;
+pub const RUST8_ERROR: c_int = -1;
+
+const FRAMES_PER_SECOND: u32 = 60;
+
+pub struct Rust8 {
+    chip8: Chip8,
+    wave: SquareWave,
+}
+
+fn create(builder: Chip8Builder) -> *mut Rust8 {
+    match builder.log_logo(false).build() {
+        Ok(chip8) => Box::into_raw(Box::new(Rust8 { chip8: chip8, wave: Default::default() })),
+        Err(error) => {
+            error!("Could not create emulator: {}", error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Runs `function` on the handle, returning `RUST8_ERROR` for a null handle or a panic.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+unsafe fn guard<F: FnOnce(&mut Rust8) -> c_int>(handle: *mut Rust8, function: F) -> c_int {
+    if handle.is_null() {
+        return RUST8_ERROR;
+    }
+    let handle = &mut *handle;
+    match panic::catch_unwind(AssertUnwindSafe(|| function(handle))) {
+        Ok(result) => result,
+        Err(_) => RUST8_ERROR,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust8_create() -> *mut Rust8 {
+    create(Chip8Builder::new())
+}
+
+#[no_mangle]
+pub extern "C" fn rust8_create_with_seed(seed: u64) -> *mut Rust8 {
+    create(Chip8Builder::new().seed(seed))
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet. It
+/// must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_destroy(handle: *mut Rust8) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `data` must be null or point to `length` readable
+/// bytes, which are copied before the call returns.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_load_rom(handle: *mut Rust8, data: *const u8, length: usize) -> c_int {
+    if data.is_null() {
+        return RUST8_ERROR;
+    }
+    let rom = slice::from_raw_parts(data, length);
+    guard(handle, |handle| {
+        if let Err(error) = handle.chip8.config().check_rom(rom.len()) {
+            error!("Could not load ROM: {}", error);
+            return RUST8_ERROR;
+        }
+        handle.chip8.load(rom.to_vec());
+        RUST8_OK
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_step(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| {
+        handle.chip8.step();
+        RUST8_OK
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_run_frame(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| {
+        handle.chip8.update();
+        RUST8_OK
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_run_frames(handle: *mut Rust8, frames: u32) -> c_int {
+    guard(handle, |handle| {
+        let mut completed = 0;
+        while completed < frames && !handle.chip8.is_halted() {
+            handle.chip8.update();
+            completed += 1;
+        }
+        completed as c_int
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_advance(handle: *mut Rust8, microseconds: u64) -> c_int {
+    guard(handle, |handle| handle.chip8.advance(Duration::from_micros(microseconds)) as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_reset(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| {
+        handle.chip8.reset();
+        RUST8_OK
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_is_halted(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| handle.chip8.is_halted() as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+/// bytes that stay valid and are not accessed elsewhere during the call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_read_memory(handle: *mut Rust8, address: u16, buffer: *mut u8, length: usize) -> c_int {
+    if buffer.is_null() {
+        return RUST8_ERROR;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    guard(handle, |handle| {
+        let memory = handle.chip8.read_memory(address, buffer.len());
+        buffer[..memory.len()].copy_from_slice(memory);
+        memory.len() as c_int
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_screen_width(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| handle.chip8.retrieve_screen_size().0 as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_screen_height(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| handle.chip8.retrieve_screen_size().1 as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+/// bytes that stay valid and are not accessed elsewhere during the call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_get_framebuffer(handle: *mut Rust8, buffer: *mut u8, length: usize) -> c_int {
+    if buffer.is_null() {
+        return RUST8_ERROR;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    guard(handle, |handle| {
+        let pixels = handle.chip8.retrieve_screen_pixels();
+        if buffer.len() < pixels.len() {
+            return RUST8_ERROR;
+        }
+        for (target, pixel) in buffer.iter_mut().zip(pixels) {
+            *target = *pixel as u8;
+        }
+        pixels.len() as c_int
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_press_key(handle: *mut Rust8, key: u8) -> c_int {
+    guard(handle, |handle| {
+        match HexKey::from_index(key) {
+            Some(key) => {
+                handle.chip8.press_key(key);
+                RUST8_OK
+            },
+            None => RUST8_ERROR,
+        }
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_release_key(handle: *mut Rust8, key: u8) -> c_int {
+    guard(handle, |handle| {
+        match HexKey::from_index(key) {
+            Some(key) => {
+                handle.chip8.release_key(key);
+                RUST8_OK
+            },
+            None => RUST8_ERROR,
+        }
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_set_sample_rate(handle: *mut Rust8, sample_rate: u32) -> c_int {
+    guard(handle, |handle| {
+        if sample_rate == 0 {
+            return RUST8_ERROR;
+        }
+        handle.wave.set_sample_rate(sample_rate);
+        RUST8_OK
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_audio_samples_per_frame(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| handle.wave.samples_per_frame(FRAMES_PER_SECOND) as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+/// `int16_t` samples that stay valid and are not accessed elsewhere during the call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_get_audio_samples(handle: *mut Rust8, buffer: *mut i16, length: usize) -> c_int {
+    if buffer.is_null() {
+        return RUST8_ERROR;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    guard(handle, |handle| {
+        let active = handle.chip8.get_sound_timer() > 0;
+        handle.wave.fill(buffer, active);
+        buffer.len() as c_int
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a handle returned by `rust8_create` that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_save_state_size(handle: *mut Rust8) -> c_int {
+    guard(handle, |handle| handle.chip8.save_state().len() as c_int)
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `buffer` must be null or point to `length` writable
+/// bytes that stay valid and are not accessed elsewhere during the call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_save_state(handle: *mut Rust8, buffer: *mut u8, length: usize) -> c_int {
+    if buffer.is_null() {
+        return RUST8_ERROR;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    guard(handle, |handle| {
+        let state = handle.chip8.save_state();
+        if buffer.len() < state.len() {
+            return RUST8_ERROR;
+        }
+        buffer[..state.len()].copy_from_slice(&state);
+        state.len() as c_int
+    })
+}
+
+/// # Safety
+/// `handle` must be null or a live handle. `data` must be null or point to `length` readable
+/// bytes, which are only read during the call.
+#[no_mangle]
+pub unsafe extern "C" fn rust8_load_state(handle: *mut Rust8, data: *const u8, length: usize) -> c_int {
+    if data.is_null() {
+        return RUST8_ERROR;
+    }
+    let state = slice::from_raw_parts(data, length);
+    guard(handle, |handle| {
+        match handle.chip8.load_state(state) {
+            Ok(()) => RUST8_OK,
+            Err(error) => {
+                error!("Could not load state: {}", error);
+                RUST8_ERROR
+            }
+        }
+    })
+}
+
@@ -1,13 +1,30 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(bare_trait_objects, clippy::redundant_field_names, clippy::redundant_static_lifetimes, clippy::upper_case_acronyms)]
+// the suggested replacements need a newer compiler than the crate supports
+#![allow(clippy::manual_div_ceil, clippy::manual_is_multiple_of, clippy::unnecessary_map_or)]
+
 pub mod emulator;
+#[cfg(feature = "std")]
+pub mod ffi;
+/// cbindgen:ignore
+#[cfg(feature = "libretro")]
+pub mod libretro;
 
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
 #[macro_use]
 extern crate log;
-extern crate log4rs;
+#[cfg(feature = "std")]
 extern crate rand;
 
 pub use emulator::Emulator;
+pub use emulator::chip8::Chip8Builder;
+pub use emulator::registry::Registry;
 use emulator::chip8::Chip8;
+use alloc::boxed::Box;
 
 pub fn create_chip8() -> Box<Chip8> {
     Box::new(Chip8::new())
-}
\ No newline at end of file
+}
@@ -0,0 +1,546 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use emulator::Emulator;
+use emulator::audio::SquareWave;
+use emulator::keypad::HexKey;
+use emulator::chip8::{Chip8, Chip8Builder, Inspect, Quirks};
+
+const RETRO_API_VERSION: c_uint = 1;
+
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_ENVIRONMENT_SET_SUPPORT_NO_GAME: c_uint = 18;
+
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const FRAMES_PER_SECOND: u32 = 60;
+const SAMPLE_RATE: u32 = 44100;
+const PIXEL_ON: u32 = 0xFFFF_FFFF;
+const PIXEL_OFF: u32 = 0xFF00_0000;
+
+// RetroPad button id to CHIP-8 hex key, following the usual 2/4/6/8 direction layout.
+const KEY_MAPPING: [(c_uint, HexKey, &'static [u8]); 16] = [
+    (4, HexKey::Key2, b"Up (2)\0"),
+    (5, HexKey::Key8, b"Down (8)\0"),
+    (6, HexKey::Key4, b"Left (4)\0"),
+    (7, HexKey::Key6, b"Right (6)\0"),
+    (8, HexKey::Key5, b"A (5)\0"),
+    (0, HexKey::Key0, b"B (0)\0"),
+    (9, HexKey::Key1, b"X (1)\0"),
+    (1, HexKey::Key3, b"Y (3)\0"),
+    (10, HexKey::Key7, b"L (7)\0"),
+    (11, HexKey::Key9, b"R (9)\0"),
+    (12, HexKey::KeyA, b"L2 (A)\0"),
+    (13, HexKey::KeyB, b"R2 (B)\0"),
+    (14, HexKey::KeyC, b"L3 (C)\0"),
+    (15, HexKey::KeyD, b"R3 (D)\0"),
+    (2, HexKey::KeyE, b"Select (E)\0"),
+    (3, HexKey::KeyF, b"Start (F)\0"),
+];
+
+const OPTION_SPEED: &'static [u8] = b"rust8_instructions_per_frame\0";
+const OPTION_VF_RESET: &'static [u8] = b"rust8_quirk_vf_reset\0";
+const OPTION_LOAD_STORE: &'static [u8] = b"rust8_quirk_load_store_increments_i\0";
+const OPTION_JUMP: &'static [u8] = b"rust8_quirk_jump_uses_vx\0";
+const OPTION_CLIP: &'static [u8] = b"rust8_quirk_clip_sprites\0";
+
+type EnvironmentCallback = extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshCallback = extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleCallback = extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchCallback = extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollCallback = extern "C" fn();
+type InputStateCallback = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+#[repr(C)]
+pub struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    geometry: GameGeometry,
+    timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+#[repr(C)]
+struct Variable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+struct InputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+#[derive(Default)]
+struct Callbacks {
+    environment: Option<EnvironmentCallback>,
+    video_refresh: Option<VideoRefreshCallback>,
+    audio_sample_batch: Option<AudioSampleBatchCallback>,
+    input_poll: Option<InputPollCallback>,
+    input_state: Option<InputStateCallback>,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+struct Options {
+    instructions_per_frame: u32,
+    quirks: Quirks,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { instructions_per_frame: 10, quirks: Default::default() }
+    }
+}
+
+struct Core {
+    callbacks: Callbacks,
+    options: Options,
+    chip8: Option<Chip8>,
+    rom: Vec<u8>,
+    wave: SquareWave,
+    video: Vec<u32>,
+    audio: Vec<i16>,
+    pressed_keys: u16,
+}
+
+static CORE: AtomicPtr<Core> = AtomicPtr::new(ptr::null_mut());
+
+/// Runs `function` on the core, which is created on first use. The frontend calls the core from
+/// one thread and never re-enters it from a callback, so the reference is the only one while
+/// `function` runs.
+fn with_core<R, F: FnOnce(&mut Core) -> R>(function: F) -> R {
+    let mut core = CORE.load(Ordering::Acquire);
+    if core.is_null() {
+        core = Box::into_raw(Box::new(Core {
+            callbacks: Default::default(),
+            options: Default::default(),
+            chip8: None,
+            rom: Vec::new(),
+            wave: SquareWave::new(SAMPLE_RATE),
+            video: Vec::new(),
+            audio: Vec::new(),
+            pressed_keys: 0,
+        }));
+        CORE.store(core, Ordering::Release);
+    }
+    function(unsafe { &mut *core })
+}
+
+/// Like `with_core`, but returns `None` if the emulator panics instead of unwinding into the
+/// frontend.
+fn guard<R, F: FnOnce(&mut Core) -> R>(function: F) -> Option<R> {
+    panic::catch_unwind(AssertUnwindSafe(|| with_core(function))).ok()
+}
+
+fn c_str(bytes: &'static [u8]) -> *const c_char {
+    bytes.as_ptr() as *const c_char
+}
+
+impl Core {
+    fn environment(&self, cmd: c_uint, data: *mut c_void) -> bool {
+        match self.callbacks.environment {
+            Some(environment) => environment(cmd, data),
+            None => false,
+        }
+    }
+
+    fn variable(&self, key: &'static [u8]) -> Option<String> {
+        let mut variable = Variable { key: c_str(key), value: ptr::null() };
+        if !self.environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable as *mut Variable as *mut c_void) || variable.value.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(variable.value) }.to_str().ok().map(String::from)
+    }
+
+    fn enabled(&self, key: &'static [u8], default: bool) -> bool {
+        match self.variable(key) {
+            Some(value) => value == "enabled",
+            None => default,
+        }
+    }
+
+    fn read_options(&self) -> Options {
+        let default: Options = Default::default();
+        let instructions_per_frame = self.variable(OPTION_SPEED)
+            .and_then(|value| value.parse::<u32>().ok())
+            .unwrap_or(default.instructions_per_frame);
+        Options {
+            instructions_per_frame: instructions_per_frame,
+            quirks: Quirks {
+                vf_reset: self.enabled(OPTION_VF_RESET, default.quirks.vf_reset),
+                load_store_increments_i: self.enabled(OPTION_LOAD_STORE, default.quirks.load_store_increments_i),
+                jump_uses_vx: self.enabled(OPTION_JUMP, default.quirks.jump_uses_vx),
+                clip_sprites: self.enabled(OPTION_CLIP, default.quirks.clip_sprites),
+            },
+        }
+    }
+
+    fn build_chip8(&self) -> Option<Chip8> {
+        let built = Chip8Builder::new()
+            .instructions_per_frame(self.options.instructions_per_frame)
+            .quirks(self.options.quirks)
+            .log_logo(false)
+            .log_instructions(false)
+            .build();
+        match built {
+            Ok(mut chip8) => {
+                chip8.load(self.rom.clone());
+                Some(chip8)
+            },
+            Err(error) => {
+                error!("Could not create emulator: {}", error);
+                None
+            }
+        }
+    }
+
+    fn apply_changed_options(&mut self) {
+        let mut updated = false;
+        self.environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated as *mut bool as *mut c_void);
+        if !updated {
+            return;
+        }
+        let options = self.read_options();
+        if options == self.options {
+            return;
+        }
+        self.options = options;
+        let state = self.chip8.as_ref().map(|chip8| chip8.save_state());
+        if let Some(mut chip8) = self.build_chip8() {
+            if let Some(state) = state {
+                let _ = chip8.load_state(&state);
+            }
+            self.chip8 = Some(chip8);
+        }
+    }
+
+    fn poll_input(&mut self) {
+        if let Some(input_poll) = self.callbacks.input_poll {
+            input_poll();
+        }
+        let input_state = match self.callbacks.input_state {
+            Some(input_state) => input_state,
+            None => return,
+        };
+        let chip8 = match self.chip8 {
+            Some(ref mut chip8) => chip8,
+            None => return,
+        };
+        for &(id, key, _) in KEY_MAPPING.iter() {
+            let bit = key.mask();
+            let pressed = input_state(0, RETRO_DEVICE_JOYPAD, 0, id) != 0;
+            if pressed && self.pressed_keys & bit == 0 {
+                chip8.press_key(key);
+                self.pressed_keys |= bit;
+            } else if !pressed && self.pressed_keys & bit != 0 {
+                chip8.release_key(key);
+                self.pressed_keys &= !bit;
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        let chip8 = match self.chip8 {
+            Some(ref chip8) => chip8,
+            None => return,
+        };
+        let (width, height) = chip8.retrieve_screen_size();
+        self.video.clear();
+        self.video.extend(chip8.retrieve_screen_pixels().iter().map(|pixel| if *pixel { PIXEL_ON } else { PIXEL_OFF }));
+        if let Some(video_refresh) = self.callbacks.video_refresh {
+            video_refresh(self.video.as_ptr() as *const c_void, width as c_uint, height as c_uint, width * 4);
+        }
+
+        let active = chip8.get_sound_timer() > 0;
+        let mut mono = vec![0; self.wave.samples_per_frame(FRAMES_PER_SECOND)];
+        self.wave.fill(&mut mono, active);
+        self.audio.clear();
+        for sample in mono {
+            self.audio.push(sample);
+            self.audio.push(sample);
+        }
+        if let Some(audio_sample_batch) = self.callbacks.audio_sample_batch {
+            audio_sample_batch(self.audio.as_ptr(), self.audio.len() / 2);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentCallback) {
+    with_core(|core| set_environment(core, callback));
+}
+
+fn set_environment(core: &mut Core, callback: EnvironmentCallback) {
+    core.callbacks.environment = Some(callback);
+
+    let mut no_game = false;
+    core.environment(RETRO_ENVIRONMENT_SET_SUPPORT_NO_GAME, &mut no_game as *mut bool as *mut c_void);
+
+    let mut variables = [
+        Variable { key: c_str(OPTION_SPEED), value: c_str(b"Instructions per frame; 10|1|5|15|20|30|50|100\0") },
+        Variable { key: c_str(OPTION_VF_RESET), value: c_str(b"Quirk: logic ops reset VF; enabled|disabled\0") },
+        Variable { key: c_str(OPTION_LOAD_STORE), value: c_str(b"Quirk: FX55/FX65 increment I; disabled|enabled\0") },
+        Variable { key: c_str(OPTION_JUMP), value: c_str(b"Quirk: BNNN jumps to VX + NNN; disabled|enabled\0") },
+        Variable { key: c_str(OPTION_CLIP), value: c_str(b"Quirk: clip sprites at screen edges; enabled|disabled\0") },
+        Variable { key: ptr::null(), value: ptr::null() },
+    ];
+    core.environment(RETRO_ENVIRONMENT_SET_VARIABLES, variables.as_mut_ptr() as *mut c_void);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshCallback) {
+    with_core(|core| core.callbacks.video_refresh = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleCallback) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchCallback) {
+    with_core(|core| core.callbacks.audio_sample_batch = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollCallback) {
+    with_core(|core| core.callbacks.input_poll = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateCallback) {
+    with_core(|core| core.callbacks.input_state = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {
+    with_core(|_| ());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    let core = CORE.swap(ptr::null_mut(), Ordering::AcqRel);
+    if !core.is_null() {
+        drop(unsafe { Box::from_raw(core) });
+    }
+}
+
+/// # Safety
+/// `info` must be null or point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    if info.is_null() {
+        return;
+    }
+    *info = SystemInfo {
+        library_name: c_str(b"rust8\0"),
+        library_version: c_str(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()),
+        valid_extensions: c_str(b"ch8|c8\0"),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must be null or point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    if info.is_null() {
+        return;
+    }
+    let (width, height) = with_core(|core| match core.chip8 {
+        Some(ref chip8) => chip8.retrieve_screen_size(),
+        None => (64, 32),
+    });
+    *info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: width as c_uint,
+            base_height: height as c_uint,
+            max_width: width as c_uint,
+            max_height: height as c_uint,
+            aspect_ratio: width as f32 / height as f32,
+        },
+        timing: SystemTiming { fps: FRAMES_PER_SECOND as f64, sample_rate: SAMPLE_RATE as f64 },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(|core| {
+        core.pressed_keys = 0;
+        if let Some(ref mut chip8) = core.chip8 {
+            chip8.reset();
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let result = guard(|core| {
+        core.apply_changed_options();
+        core.poll_input();
+        if let Some(ref mut chip8) = core.chip8 {
+            chip8.update();
+        }
+        core.render();
+    });
+    if result.is_none() {
+        error!("The emulator panicked, unloading the game");
+        with_core(|core| core.chip8 = None);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(|core| match core.chip8 {
+        Some(ref chip8) => chip8.save_state().len(),
+        None => 0,
+    })
+}
+
+/// # Safety
+/// `data` must be null or point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let state = match with_core(|core| core.chip8.as_ref().map(|chip8| chip8.save_state())) {
+        Some(state) => state,
+        None => return false,
+    };
+    if data.is_null() || size < state.len() {
+        return false;
+    }
+    slice::from_raw_parts_mut(data as *mut u8, state.len()).copy_from_slice(&state);
+    true
+}
+
+/// # Safety
+/// `data` must be null or point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    let state = slice::from_raw_parts(data as *const u8, size);
+    guard(|core| match core.chip8 {
+        Some(ref mut chip8) => chip8.load_state(state).is_ok(),
+        None => false,
+    }).unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` whose `data` is null or points to `size`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let rom = slice::from_raw_parts((*game).data as *const u8, (*game).size);
+    with_core(|core| load_game(core, rom))
+}
+
+fn load_game(core: &mut Core, rom: &[u8]) -> bool {
+    let mut pixel_format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !core.environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut pixel_format as *mut c_uint as *mut c_void) {
+        return false;
+    }
+    let mut descriptors: Vec<InputDescriptor> = KEY_MAPPING.iter()
+        .map(|&(id, _, description)| InputDescriptor { port: 0, device: RETRO_DEVICE_JOYPAD, index: 0, id: id, description: c_str(description) })
+        .collect();
+    descriptors.push(InputDescriptor { port: 0, device: 0, index: 0, id: 0, description: ptr::null() });
+    core.environment(RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, descriptors.as_mut_ptr() as *mut c_void);
+
+    core.options = core.read_options();
+    if let Err(error) = Chip8Builder::new().config().check_rom(rom.len()) {
+        error!("Could not load ROM: {}", error);
+        return false;
+    }
+    core.rom = rom.to_vec();
+    core.pressed_keys = 0;
+    core.chip8 = core.build_chip8();
+    core.chip8.is_some()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_game_type: c_uint, _info: *const GameInfo, _num_info: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_core(|core| {
+        core.chip8 = None;
+        core.rom.clear();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}